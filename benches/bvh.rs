@@ -0,0 +1,124 @@
+//! Compares the SAH and median split `BVH`s on a couple of the built-in
+//! scenes, and on a scene full of fast-moving spheres, by how long they take
+//! to build and how quickly they can find what camera rays hit.
+//!
+//! Run with `cargo bench --bench bvh`.
+
+use rand::prelude::*;
+use std::time::{Duration, Instant};
+use weekend_tracer_rs::{
+    bvh::{SplitMethod, BVH},
+    camera::Camera,
+    create_world,
+    hittable::{moving_sphere::MovingSphere, world::World, Hittable},
+    material::Material,
+    ray::Ray,
+    scenes,
+    scenes::Scene,
+    texture, vec3,
+    vec3::Vec3,
+};
+
+/// How many rays to trace through each tree.
+const RAYS: usize = 1_000_000;
+
+fn main() {
+    let mut benchmarks: Vec<(&str, Scene)> = ["random_scene", "next_week_final"]
+        .iter()
+        .map(|&name| {
+            let builtin = scenes::builtin_scene(name).unwrap();
+            (name, (builtin.build)(1.0, &mut StdRng::seed_from_u64(0)))
+        })
+        .collect();
+    benchmarks.push(("falling_spheres", falling_spheres()));
+
+    for (name, scene) in benchmarks {
+        let camera = scene.camera;
+        let (time0, time1) = (camera.time0, camera.time1);
+
+        let mut rng = StdRng::seed_from_u64(1);
+        let rays: Vec<Ray> = (0..RAYS)
+            .map(|_| {
+                let (s, t) = rng.gen();
+                camera.get_ray(&mut rng, s, t)
+            })
+            .collect();
+
+        println!("{} ({} objects):", name, scene.world.objects.len());
+
+        let mut times = vec![];
+        for &method in &[SplitMethod::Median, SplitMethod::Sah] {
+            let bvh = BVH::with_split_method(scene.world.objects.clone(), time0, time1, method);
+            let stats = bvh.stats();
+
+            let (hits, elapsed) = trace(&bvh, &rays);
+            times.push(elapsed);
+
+            println!(
+                "  {:?}: built in {:.2?}, {} nodes, {} deep; {} hits in {:.2?} ({:.2} Mrays/s)",
+                method,
+                stats.build_time,
+                stats.nodes,
+                stats.depth,
+                hits,
+                elapsed,
+                RAYS as f64 / elapsed.as_secs_f64() / 1e6,
+            );
+        }
+
+        println!(
+            "  SAH speedup: {:.2}x",
+            times[0].as_secs_f64() / times[1].as_secs_f64()
+        );
+    }
+}
+
+/// Lots of small spheres falling like rain, each moving many times its own
+/// size while the shutter is open.
+fn falling_spheres() -> Scene {
+    let mut rng = StdRng::seed_from_u64(0);
+    let material = Material::lambertian(texture::constant(Vec3::from(0.5)));
+
+    let objects: Vec<Box<dyn Hittable>> = (0..5000)
+        .map(|_| {
+            let center = Vec3::random_range(&mut rng, -10.0, 10.0);
+            let motion = vec3!(0.0, -8.0, 0.0) + Vec3::random_range(&mut rng, -0.5, 0.5);
+            Box::new(MovingSphere::new(
+                center,
+                center + motion,
+                0.0,
+                1.0,
+                0.1,
+                material.clone(),
+            )) as Box<dyn Hittable>
+        })
+        .collect();
+
+    Scene {
+        world: World::new(objects),
+        lights: create_world!(),
+        camera: Camera::new(
+            vec3!(0.0, 0.0, 30.0),
+            vec3!(),
+            vec3!(0.0, 1.0, 0.0),
+            40.0,
+            1.0,
+            0.0,
+            30.0,
+            0.0,
+            1.0,
+        ),
+        background: vec3!(),
+    }
+}
+
+/// Find the closest hit for every ray, returning how many hit something and
+/// how long it took.
+fn trace(bvh: &BVH, rays: &[Ray]) -> (usize, Duration) {
+    let start = Instant::now();
+    let hits = rays
+        .iter()
+        .filter(|ray| bvh.hit(ray, 0.001, f32::INFINITY).is_some())
+        .count();
+    (hits, start.elapsed())
+}
@@ -45,7 +45,15 @@ impl AABB {
     }
 
     /// Test if a ray hits the bounding box at some point.
+    #[inline]
     pub fn hit(&self, ray: &Ray, tmin: f32, tmax: f32) -> bool {
+        self.clip(ray, tmin, tmax).is_some()
+    }
+
+    /// The part of the range `tmin..tmax` where the ray is inside the box, or
+    /// `None` if it misses the box.
+    #[inline]
+    pub fn clip(&self, ray: &Ray, tmin: f32, tmax: f32) -> Option<(f32, f32)> {
         let mut tmin = tmin;
         let mut tmax = tmax;
 
@@ -63,11 +71,11 @@ impl AABB {
             tmax = if t1 < tmax { t1 } else { tmax };
 
             if tmax <= tmin {
-                return false;
+                return None;
             }
         }
 
-        true
+        Some((tmin, tmax))
     }
 
     /// Returns the longest axis in the box.
@@ -91,6 +99,29 @@ impl AABB {
         max - min
     }
 
+    /// Linearly interpolates between two boxes, corner by corner. `fraction`
+    /// is 0 for `self`, and 1 for `other`.
+    ///
+    /// # Usage
+    ///
+    /// ```
+    /// # use weekend_tracer_rs::{aabb::AABB, vec3, vec3::Vec3};
+    /// let start = AABB::new(vec3!(0.0, 0.0, 0.0), vec3!(1.0, 1.0, 1.0));
+    /// let end = AABB::new(vec3!(4.0, 0.0, 0.0), vec3!(5.0, 1.0, 1.0));
+    ///
+    /// assert_eq!(
+    ///     start.lerp(&end, 0.25),
+    ///     AABB::new(vec3!(1.0, 0.0, 0.0), vec3!(2.0, 1.0, 1.0)),
+    /// );
+    /// ```
+    #[inline]
+    pub fn lerp(&self, other: &AABB, fraction: f32) -> AABB {
+        AABB::new(
+            self.min + fraction * (other.min - self.min),
+            self.max + fraction * (other.max - self.max),
+        )
+    }
+
     /// Returns the surface area of a box.
     pub fn area(&self) -> f32 {
         let x = self.axis_range(X);
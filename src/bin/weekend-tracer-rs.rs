@@ -1,11 +1,30 @@
 use clap::{clap_app, crate_version};
-use std::sync::Arc;
+use rand::SeedableRng;
+use rand_chacha::ChaCha8Rng;
+use std::{
+    error::Error,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
 
 #[cfg(feature = "gui-support")]
-use minifb::{Key, Window, WindowOptions};
+use minifb::{Key, KeyRepeat, Window, WindowOptions};
+#[cfg(feature = "gui-support")]
+use std::sync::atomic::{AtomicBool, Ordering};
 
 use weekend_tracer_rs::{
-    bvh::BVH, camera::Camera, hittable::Hittable, renderer, scenes, vec3, vec3::Vec3,
+    bvh::BVH,
+    hittable::Hittable,
+    renderer,
+    renderer::{
+        checkpoint::{Checkpoint, Checkpointing},
+        tile::{Rect, TileOrder},
+        Accumulator, AdaptiveSampling, Pixel, Renderer,
+    },
+    sampler::Sampler,
+    scene_file, scenes,
+    tonemap::{Operator, ToneMapper},
+    vec3::Vec3,
 };
 
 // Some defaults
@@ -19,8 +38,9 @@ const HEIGHT: usize = 100;
 const HEIGHT: usize = 400;
 const SAMPLES_PER_PIXEL: usize = 100;
 const MAX_REFLECTION_DEPTH: usize = 50;
-
-const BACKGROUND_COLOR: Vec3 = vec3!();
+const DEFAULT_SCENE: &str = "cornell_box";
+const CHECKPOINT_INTERVAL: f32 = 60.0;
+const ADAPTIVE_THRESHOLD: f32 = 0.05;
 
 fn main() {
     #[allow(unused_mut)]
@@ -32,16 +52,35 @@ fn main() {
                  <OUTPUT_FILE> (e.g. image.png, image.JPEG, etc...). The output \
                  format is deduced from the file name extension. JPEG, PNG, \
                  GIF, BMP, TIFF, ICO, and PPM (the binary version) formats are \
-                 supported.")
+                 supported. OpenEXR (.exr) and Radiance HDR (.hdr) files get \
+                 the raw, linear radiance of the render instead; every other \
+                 format is tone mapped (see --tonemap and --exposure) and sRGB \
+                 encoded.")
         (@group image_output +multiple =>
-            (@arg OUTPUT_FILE: required_unless[gui compute_pi compute_int_x_squared] "The file to be outputted to.")
+            (@arg OUTPUT_FILE: required_unless[gui compute_pi compute_int_x_squared list_scenes] "The file to be outputted to.")
             (@arg ppm: -p --ppm "Output to an ASCII PPM file (e.g. test.ppm, image.ppm, etc...).")
         )
         (@arg dimensions: -d --dimensions <WIDTH> <HEIGHT> !required "Set the dimensions for the render. 300x300 by default.")
-        (@arg samples: -s --samples <SAMPLES_PER_PIXEL> !required "Sets the number of samples to be taken per pixel.")
+        (@arg samples: -s --samples +takes_value value_name("MIN[,MAX]") +require_delimiter #{1, 2} "Sets the number of samples to be taken per pixel. If a <MAX> is given too (e.g. -s 16,256), each pixel gets between <MIN> and <MAX> samples, depending on how noisy it is (see --adaptive-threshold).")
+        (@arg adaptive_threshold: --("adaptive-threshold") <ERROR> !required requires[samples] "Sets the relative error below which a pixel counts as noise-free, when sampling adaptively. 0.05 by default.")
+        (@arg sampler: --sampler <SAMPLER> !required "Sets how the random numbers for each sample are chosen: independent, stratified, halton, sobol, or cmj (correlated multi-jittered). Defaults to independent.")
+        (@arg seed: --seed <SEED> !required "Sets the seed for the random numbers used in the render. Renders with the same seed (and everything else the same) come out identical. 0 by default.")
+        (@arg sample_heatmap: --("sample-heatmap") <FILE> !required "Also write an image of how many samples each pixel got to <FILE>.")
         (@arg reflections: -r --max_reflection_depth <DEPTH> !required "Sets the maximum reflection depth.")
-        (@arg compute_pi: --compute_pi conflicts_with[dimensions samples reflections image_output gui compute_int_x_squared] "Computes pi (because why not?).")
-        (@arg compute_int_x_squared: --compute_int_x_squared conflicts_with[dimensions samples reflections image_output gui compute_pi] "Computes the integral of x^2 between x=0 and x=2.")
+        (@arg region: --region <X> <Y> <W> <H> !required "Only render the <W>x<H> rectangle with its top-left corner at (<X>, <Y>). The output image is just that rectangle.")
+        (@arg tile_size: --("tile-size") <SIZE> !required "Sets the width and height of the tiles the image is rendered in. 16 by default.")
+        (@arg tile_order: --("tile-order") <ORDER> !required "Sets the order tiles are rendered in: scanline, spiral, or hilbert. Defaults to hilbert.")
+        (@arg checkpoint: --checkpoint <FILE> !required "Periodically save the render in progress to <FILE>, so that it can be picked up again with --resume.")
+        (@arg checkpoint_interval: --("checkpoint-interval") <SECONDS> !required "Sets the time between checkpoints. 60 seconds by default.")
+        (@arg resume: --resume <CHECKPOINT> !required "Carry on with the render saved in <CHECKPOINT>, until every pixel has enough samples (see --samples). The scene (including the meshes and images that a scene file names) and settings have to be the same as the saved render's. Checkpoints are saved back to <CHECKPOINT>, unless --checkpoint says otherwise.")
+        (@arg tonemap: --tonemap <OPERATOR> !required "Sets the tone mapping operator: clamp, reinhard, extended-reinhard, aces, or hable. Defaults to clamp.")
+        (@arg exposure: --exposure <STOPS> !required "Brightens (or darkens, if negative) the image by some number of stops before tone mapping.")
+        (@arg white: --white <LUMINANCE> !required requires[tonemap] "Sets the white point of the extended-reinhard operator.")
+        (@arg scene: --scene <FILE> !required conflicts_with[builtin_scene] "Render a scene described in a RON scene file.")
+        (@arg builtin_scene: --("builtin-scene") <NAME> !required "Render one of the built-in scenes. See --list-scenes. Defaults to cornell_box.")
+        (@arg list_scenes: --("list-scenes") conflicts_with[dimensions samples reflections scene builtin_scene image_output gui] "List the built-in scenes and exit.")
+        (@arg compute_pi: --compute_pi conflicts_with[dimensions samples reflections scene image_output gui compute_int_x_squared] "Computes pi (because why not?).")
+        (@arg compute_int_x_squared: --compute_int_x_squared conflicts_with[dimensions samples reflections scene image_output gui compute_pi] "Computes the integral of x^2 between x=0 and x=2.")
     );
 
     #[cfg(feature = "gui-support")]
@@ -50,8 +89,13 @@ fn main() {
             clap::Arg::with_name("gui")
                 .short("g")
                 .long("gui")
-                .help("Render to a window instead of to stdout.")
-                .conflicts_with("image_output"),
+                .help(
+                    "Render progressively to a window. ESC stops the render early, \
+                     keeping the image so far. If <OUTPUT_FILE> is given too, the \
+                     image is written to it once the window closes, and S saves it \
+                     at any time.",
+                )
+                .conflicts_with("ppm"),
         );
     }
 
@@ -65,6 +109,10 @@ fn main() {
         compute_int_x_squared();
     }
 
+    if matches.is_present("list_scenes") {
+        list_scenes();
+    }
+
     let dimensions = if let Some(v) = matches.values_of("dimensions") {
         v.map(str::parse::<usize>)
             .map(|x| {
@@ -84,16 +132,57 @@ fn main() {
     let height = dimensions[1];
     let aspect_ratio = (width as f32) / (height as f32);
 
-    let samples_per_pixel = matches
-        .value_of("samples")
-        .unwrap_or(&SAMPLES_PER_PIXEL.to_string())
-        .parse::<usize>()
+    let samples = if let Some(v) = matches.values_of("samples") {
+        v.map(str::parse::<usize>)
+            .map(|x| {
+                x.unwrap_or_else(|e| {
+                    panic!(
+                        "<MIN> or <MAX> could not be parsed into a positive integer!\n{}",
+                        e
+                    )
+                })
+            })
+            .collect::<Vec<_>>()
+    } else {
+        vec![SAMPLES_PER_PIXEL]
+    };
+
+    let samples_per_pixel = *samples.last().unwrap();
+    let adaptive = if samples.len() == 2 {
+        let threshold = matches
+            .value_of("adaptive_threshold")
+            .unwrap_or(&ADAPTIVE_THRESHOLD.to_string())
+            .parse::<f32>()
+            .unwrap_or_else(|e| panic!("Could not parse <ERROR> into a number!\n{}", e));
+
+        if samples[0] > samples[1] {
+            eprintln!("error: <MIN> samples can't be more than <MAX> samples");
+            std::process::exit(1)
+        }
+
+        Some(AdaptiveSampling {
+            min_samples: samples[0],
+            threshold,
+        })
+    } else {
+        None
+    };
+
+    let sampler = matches
+        .value_of("sampler")
+        .map(str::parse::<Sampler>)
+        .transpose()
         .unwrap_or_else(|e| {
-            panic!(
-                "Could not parse <SAMPLES_PER_PIXEL> into a positive integer!\n{}",
-                e
-            )
-        });
+            eprintln!("error: {}", e);
+            std::process::exit(1)
+        })
+        .unwrap_or_default();
+
+    let seed = matches
+        .value_of("seed")
+        .unwrap_or("0")
+        .parse::<u64>()
+        .unwrap_or_else(|e| panic!("Could not parse <SEED> into a positive integer!\n{}", e));
 
     let max_reflection_depth = matches
         .value_of("reflections")
@@ -101,97 +190,285 @@ fn main() {
         .parse::<usize>()
         .unwrap_or_else(|e| panic!("Could not parse <DEPTH> into a positive integer!\n{}", e));
 
-    let (world, lights, camera) = scenes::cornell_box(aspect_ratio);
-    let lights = Arc::new(lights);
-    let bvh = BVH::new(world.objects, 0.0, 1.0);
-
-    // let lookfrom = vec3!(478.0, 278.0, -600.0);
-    // let lookat = vec3!(278.0, 278.0, 0.0);
-    // let vup = vec3!(0.0, 1.0);
-    // let dist_to_focus = (vec3!(260.0, 150.0, 45.0) - lookfrom).length();
-    // let aperture = 0.5;
-    // let vfov = 40.0; // degrees
-
-    // let camera = Camera::new(
-    //     lookfrom,
-    //     lookat,
-    //     vup,
-    //     vfov,
-    //     aspect_ratio,
-    //     aperture,
-    //     dist_to_focus,
-    //     0.0,
-    //     1.0,
-    // );
-
-    if matches.is_present("gui") {
-        #[cfg(feature = "gui-support")]
-        gui_output(
-            bvh,
-            lights,
-            camera,
-            width,
-            height,
-            samples_per_pixel,
-            max_reflection_depth,
-        );
-    } else {
-        // Calling .unwrap() is safe here because we require that the OUTPUT_FILE
-        // is present if --gui/-g is not present.
-        let output_file = matches.value_of("OUTPUT_FILE").unwrap();
-
-        if matches.is_present("ppm") {
-            ppm_output(
-                output_file,
-                bvh,
-                lights,
-                camera,
-                width,
-                height,
-                samples_per_pixel,
-                max_reflection_depth,
-            )
-            .unwrap();
-        } else {
-            image_output(
-                output_file,
-                bvh,
-                lights,
-                camera,
-                width,
-                height,
-                samples_per_pixel,
-                max_reflection_depth,
+    let tile_size = matches
+        .value_of("tile_size")
+        .unwrap_or(&renderer::DEFAULT_TILE_SIZE.to_string())
+        .parse::<usize>()
+        .ok()
+        .filter(|&size| size > 0)
+        .unwrap_or_else(|| panic!("Could not parse <SIZE> into a positive integer!"));
+
+    let tile_order = matches
+        .value_of("tile_order")
+        .map(str::parse::<TileOrder>)
+        .transpose()
+        .unwrap_or_else(|e| {
+            eprintln!("error: {}", e);
+            std::process::exit(1)
+        })
+        .unwrap_or_default();
+
+    let region = matches.values_of("region").map(|v| {
+        let v = v
+            .map(str::parse::<usize>)
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap_or_else(|e| {
+                panic!(
+                    "<X>, <Y>, <W> or <H> could not be parsed into a positive integer!\n{}",
+                    e
+                )
+            });
+        let region = Rect::new(v[0], v[1], v[2], v[3]);
+
+        if region.area() == 0 || !Rect::new(0, 0, width, height).contains(&region) {
+            eprintln!(
+                "error: the region {}x{} at ({}, {}) doesn't fit inside the {}x{} image",
+                region.width, region.height, region.x, region.y, width, height
             );
+            std::process::exit(1)
         }
-    }
-}
+        region
+    });
 
-/// Render to a simple cross-platform window using the `minifb` crate.
-#[cfg(feature = "gui-support")]
-fn gui_output(
-    bvh: BVH,
-    lights: Arc<dyn Hittable>,
-    camera: Camera,
-    width: usize,
-    height: usize,
-    samples_per_pixel: usize,
-    max_reflection_depth: usize,
-) {
-    let buffer: Vec<u32> = renderer::convert_to_argb(renderer::render(
+    let tone_mapper = tone_mapper(&matches);
+
+    // Something that identifies the scene, for telling checkpoints apart.
+    let scene_id: Vec<u8>;
+
+    let scene = if let Some(scene_file) = matches.value_of("scene") {
+        let (scene, assets) = scene_file::load_with_assets(scene_file, aspect_ratio)
+            .unwrap_or_else(|e| {
+                eprintln!("error: {}", e);
+                std::process::exit(1)
+            });
+        // The files the scene names are part of it too, so that editing a
+        // mesh or a texture is noticed as well.
+        let mut id = std::fs::read(scene_file).unwrap_or_default();
+        for asset in assets {
+            id.extend(asset.to_string_lossy().as_bytes());
+            id.extend(std::fs::read(&asset).unwrap_or_default());
+        }
+        scene_id = id;
+        scene
+    } else {
+        let name = matches.value_of("builtin_scene").unwrap_or(DEFAULT_SCENE);
+        let builtin = scenes::builtin_scene(name).unwrap_or_else(|| {
+            eprintln!(
+                "error: there is no built-in scene called {:?}. Use --list-scenes to see them all.",
+                name
+            );
+            std::process::exit(1)
+        });
+        // Always build the same scene, so that checkpoints of scenes with
+        // randomly placed objects can be resumed. Unlike `StdRng`, ChaCha8
+        // is guaranteed to give the same numbers in every version of rand.
+        // The generator is part of the scene's identity, since a different
+        // one places everything differently.
+        scene_id = format!("{} ChaCha8Rng(0)", name).into_bytes();
+        (builtin.build)(aspect_ratio, &mut ChaCha8Rng::seed_from_u64(0))
+    };
+
+    let lights: Option<Arc<dyn Hittable>> = if scene.lights.objects.is_empty() {
+        None
+    } else {
+        Some(Arc::new(scene.lights))
+    };
+    let bvh = BVH::new(scene.world.objects, scene.camera.time0, scene.camera.time1);
+    let stats = bvh.stats();
+    eprintln!(
+        "Built a BVH over {} objects ({} unbounded) in {:.2?}: {} nodes ({} leaves), {} deep",
+        bvh.len(),
+        bvh.unbounded_len(),
+        stats.build_time,
+        stats.nodes,
+        stats.leaves,
+        stats.depth
+    );
+
+    let mut renderer = Renderer {
         width,
         height,
         samples_per_pixel,
+        adaptive,
+        sampler,
+        seed,
         max_reflection_depth,
         bvh,
         lights,
-        camera,
-        BACKGROUND_COLOR,
-    ))
-    .collect();
+        camera: scene.camera,
+        background_color: scene.background,
+        tile_size,
+        tile_order,
+        region,
+        checkpointing: None,
+    };
+
+    let fingerprint = renderer.fingerprint(&scene_id);
+    let resume = matches.value_of("resume");
+    if let Some(path) = matches.value_of("checkpoint").or(resume) {
+        let seconds = matches
+            .value_of("checkpoint_interval")
+            .unwrap_or(&CHECKPOINT_INTERVAL.to_string())
+            .to_string();
+        // Rejects negative, infinite and far too long intervals, as well as
+        // things that aren't numbers.
+        let interval = seconds
+            .parse::<f32>()
+            .ok()
+            .and_then(|seconds| Duration::try_from_secs_f32(seconds).ok())
+            .unwrap_or_else(|| {
+                panic!(
+                    "Could not parse <SECONDS> into a positive number of seconds: {:?}",
+                    seconds
+                )
+            });
+
+        renderer.checkpointing = Some(Checkpointing {
+            path: path.into(),
+            interval,
+            fingerprint,
+        });
+    }
+
+    let Rect { width, height, .. } = renderer.region();
+    let accumulator = if let Some(path) = resume {
+        let checkpoint = Checkpoint::load(path, fingerprint, (width, height)).unwrap_or_else(|e| {
+            eprintln!("error: could not resume from {}: {}", path, e);
+            std::process::exit(1)
+        });
+        Mutex::new(checkpoint.accumulator)
+    } else {
+        Mutex::new(Accumulator::new(width, height))
+    };
+
+    let output = matches.value_of("OUTPUT_FILE").map(|file| Output {
+        file,
+        ppm: matches.is_present("ppm"),
+        tone_mapper,
+    });
+
+    #[cfg(feature = "gui-support")]
+    {
+        if matches.is_present("gui") {
+            gui_render(&renderer, &accumulator, &tone_mapper, output.as_ref());
+        } else {
+            renderer.render_into(&accumulator);
+        }
+    }
+    #[cfg(not(feature = "gui-support"))]
+    renderer.render_into(&accumulator);
+
+    let accumulator = accumulator.into_inner().unwrap();
+    let rendered = accumulator.image();
+
+    if let Some(file) = matches.value_of("sample_heatmap") {
+        if let Err(e) = image_output(file, &accumulator.sample_heatmap(), width, height) {
+            eprintln!("error: could not write {}: {}", file, e);
+            std::process::exit(1);
+        }
+    }
+
+    // The OUTPUT_FILE is optional if --gui/-g is present.
+    if let Some(output) = output {
+        if let Err(e) = output.write(&rendered, width, height) {
+            eprintln!("error: could not write {}: {}", output.file, e);
+            std::process::exit(1);
+        }
+    }
+}
+
+/// Where, and how, to write the rendered image.
+struct Output<'a> {
+    file: &'a str,
+    /// Write an ASCII PPM file, whatever the file extension.
+    ppm: bool,
+    tone_mapper: ToneMapper,
+}
+
+impl Output<'_> {
+    /// Write a linear render out. The format is chosen from the file
+    /// extension.
+    fn write(&self, rendered: &[Vec3], width: usize, height: usize) -> Result<(), Box<dyn Error>> {
+        let extension = std::path::Path::new(self.file)
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .map(str::to_ascii_lowercase);
+
+        if self.ppm {
+            let pixels = self.tone_mapper.to_pixels(rendered);
+            return ppm_output(self.file, &pixels, width, height).map_err(|e| e.into());
+        }
+
+        match extension.as_deref() {
+            Some("exr") => exr_output(self.file, rendered, width, height),
+            Some("hdr") => hdr_output(self.file, rendered, width, height),
+            _ => {
+                let pixels = self.tone_mapper.to_pixels(rendered);
+                image_output(self.file, &pixels, width, height)
+            }
+        }
+    }
+}
+
+/// Build the tone mapper from the `--tonemap`, `--exposure` and `--white`
+/// options.
+fn tone_mapper(matches: &clap::ArgMatches) -> ToneMapper {
+    let mut operator = matches
+        .value_of("tonemap")
+        .map(str::parse::<Operator>)
+        .transpose()
+        .unwrap_or_else(|e| {
+            eprintln!("error: {}", e);
+            std::process::exit(1)
+        })
+        .unwrap_or_default();
+
+    if let Some(white) = matches.value_of("white") {
+        let white = white
+            .parse::<f32>()
+            .unwrap_or_else(|e| panic!("Could not parse <LUMINANCE> into a number!\n{}", e));
+
+        match &mut operator {
+            Operator::ExtendedReinhard { white: w } => *w = white,
+            _ => {
+                eprintln!(
+                    "error: --white only applies to the extended-reinhard operator, not {}",
+                    operator
+                );
+                std::process::exit(1)
+            }
+        }
+    }
+
+    let exposure = matches
+        .value_of("exposure")
+        .map(str::parse::<f32>)
+        .transpose()
+        .unwrap_or_else(|e| panic!("Could not parse <STOPS> into a number!\n{}", e))
+        .unwrap_or(0.0);
+
+    ToneMapper::new(operator, exposure)
+}
+
+/// Render progressively into a simple cross-platform window using the
+/// `minifb` crate, so that the image can be watched as it converges.
+///
+/// Pressing Escape while rendering stops the render early, keeping whatever
+/// has been rendered so far. Pressing S writes the image in the window to the
+/// output file, if there is one. The rendered image is left in
+/// `accumulator`.
+#[cfg(feature = "gui-support")]
+fn gui_render(
+    renderer: &Renderer,
+    accumulator: &Mutex<Accumulator>,
+    tone_mapper: &ToneMapper,
+    output: Option<&Output>,
+) {
+    let Rect { width, height, .. } = renderer.region();
+    let stop = AtomicBool::new(false);
 
     let mut window = Window::new(
-        "weekend-tracer-rs - ESC to exit",
+        "weekend-tracer-rs - rendering - ESC to stop",
         width,
         height,
         WindowOptions::default(),
@@ -201,69 +478,78 @@ fn gui_output(
     // Limit to max ~60 fps update rate
     window.limit_update_rate(Some(std::time::Duration::from_micros(16600)));
 
-    while window.is_open() && !window.is_key_down(Key::Escape) {
-        window.update_with_buffer(&buffer, width, height).unwrap();
-    }
+    std::thread::scope(|s| {
+        let render = s.spawn(|| renderer.render_progressive(accumulator, &stop));
+
+        let mut finished = false;
+        while window.is_open() {
+            if !finished && render.is_finished() {
+                finished = true;
+                let status = if stop.load(Ordering::Relaxed) {
+                    "stopped"
+                } else {
+                    "done"
+                };
+                window.set_title(&format!("weekend-tracer-rs - {} - ESC to exit", status));
+            }
+
+            if window.is_key_pressed(Key::Escape, KeyRepeat::No) {
+                if finished {
+                    break;
+                }
+                stop.store(true, Ordering::Relaxed);
+            }
+
+            let image = accumulator.lock().unwrap().image();
+            let buffer: Vec<u32> =
+                renderer::convert_to_argb(tone_mapper.to_pixels(&image)).collect();
+            window.update_with_buffer(&buffer, width, height).unwrap();
+
+            if let Some(output) = output {
+                if window.is_key_pressed(Key::S, KeyRepeat::No) {
+                    match output.write(&image, width, height) {
+                        Ok(()) => eprintln!("Saved the current image to {}", output.file),
+                        Err(e) => eprintln!("error: could not write {}: {}", output.file, e),
+                    }
+                }
+            }
+        }
+
+        // The window might have been closed mid-render.
+        stop.store(true, Ordering::Relaxed);
+        render.join().unwrap();
+    });
 }
 
-/// Render to an ASCII PPM `.ppm` file.
-#[allow(clippy::too_many_arguments)]
+/// Write a rendered image to an ASCII PPM `.ppm` file.
 fn ppm_output(
     filename: &str,
-    bvh: BVH,
-    lights: Arc<dyn Hittable>,
-    camera: Camera,
+    rendered: &[Pixel],
     width: usize,
     height: usize,
-    samples_per_pixel: usize,
-    max_reflection_depth: usize,
 ) -> std::io::Result<()> {
-    let output = renderer::render(
-        width,
-        height,
-        samples_per_pixel,
-        max_reflection_depth,
-        bvh,
-        lights,
-        camera,
-        BACKGROUND_COLOR,
-    )
-    .into_iter()
-    .map(|(r, g, b)| format!("{} {} {}", r, g, b))
-    .fold(format!("P3\n{} {}\n255\n", width, height), |s, pixel| {
-        s + &pixel + "\n"
-    });
+    let output = rendered
+        .iter()
+        .map(|(r, g, b)| format!("{} {} {}", r, g, b))
+        .fold(format!("P3\n{} {}\n255\n", width, height), |s, pixel| {
+            s + &pixel + "\n"
+        });
 
     std::fs::write(filename, output)
 }
 
-/// Render to some arbritrary image file type. Whatever the `image` crate
-/// supports.
-#[allow(clippy::too_many_arguments)]
+/// Write a rendered image to some arbritrary image file type. Whatever the
+/// `image` crate supports.
 fn image_output(
     filename: &str,
-    bvh: BVH,
-    lights: Arc<dyn Hittable>,
-    camera: Camera,
+    rendered: &[Pixel],
     width: usize,
     height: usize,
-    samples_per_pixel: usize,
-    max_reflection_depth: usize,
-) {
-    let rendered = renderer::render(
-        width,
-        height,
-        samples_per_pixel,
-        max_reflection_depth,
-        bvh,
-        lights,
-        camera,
-        BACKGROUND_COLOR,
-    )
-    .into_iter()
-    .map(|(r, g, b)| vec![r as u8, g as u8, b as u8])
-    .flatten()
-    .collect::<Vec<_>>();
+) -> Result<(), Box<dyn Error>> {
+    let rendered = rendered
+        .iter()
+        .flat_map(|&(r, g, b)| [r as u8, g as u8, b as u8])
+        .collect::<Vec<_>>();
 
     image::save_buffer(
         filename,
@@ -271,8 +557,63 @@ fn image_output(
         width as u32,
         height as u32,
         image::ColorType::Rgb8,
-    )
-    .unwrap();
+    )?;
+
+    Ok(())
+}
+
+/// Write a linear render to a Radiance HDR `.hdr` file.
+fn hdr_output(
+    filename: &str,
+    rendered: &[Vec3],
+    width: usize,
+    height: usize,
+) -> Result<(), Box<dyn Error>> {
+    let pixels = rendered
+        .iter()
+        .map(|&Vec3(r, g, b)| image::Rgb([r, g, b]))
+        .collect::<Vec<_>>();
+
+    let file = std::io::BufWriter::new(std::fs::File::create(filename)?);
+    image::hdr::HDREncoder::new(file).encode(&pixels, width, height)?;
+
+    Ok(())
+}
+
+/// Write a linear render to an OpenEXR `.exr` file, with 32-bit float
+/// channels.
+fn exr_output(
+    filename: &str,
+    rendered: &[Vec3],
+    width: usize,
+    height: usize,
+) -> Result<(), Box<dyn Error>> {
+    exr::prelude::write_rgb_file(filename, width, height, |x, y| {
+        let Vec3(r, g, b) = rendered[y * width + x];
+        (r, g, b)
+    })?;
+
+    Ok(())
+}
+
+/// Print out the names and descriptions of all the built-in scenes.
+fn list_scenes() -> ! {
+    let name_width = scenes::BUILTIN_SCENES
+        .iter()
+        .map(|scene| scene.name.len())
+        .max()
+        .unwrap_or(0);
+
+    for scene in scenes::BUILTIN_SCENES {
+        println!(
+            "{:width$}  {}",
+            scene.name,
+            scene.description,
+            width = name_width
+        );
+    }
+
+    std::process::exit(0)
 }
 
 /// Compute pi (for reasons)
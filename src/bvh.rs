@@ -3,123 +3,738 @@
 //! These allow us to more effeciently compute what a ray might be able to hit
 //! without having to construct a bunch of `HitRecord` structs and do a bunch of
 //! calculations up front.
+//!
+//! The tree is built top-down. By default, each node is split wherever the
+//! surface area heuristic (SAH) says it's cheapest to, out of a handful of
+//! evenly-spaced candidate planes ("bins") along each axis. The finished tree
+//! is flattened into one array of nodes in depth-first order, so a node's
+//! first child is always right after it, and is traversed with a small
+//! stack instead of recursion. The child nearer to the ray's origin is
+//! visited first, so that a close hit can cull the far child.
+//!
+//! If a node's objects move far enough that the box around everything they
+//! sweep through is a lot bigger than the box around them at any one time,
+//! the node keeps boxes for both ends of the shutter interval instead. A ray
+//! is tested against the box in between them at the ray's time. That's exact
+//! as long as things move in straight lines at a constant speed (like a
+//! `MovingSphere`), and keeps a scene full of fast-moving objects from
+//! turning into something close to a linear search. Interpolating isn't
+//! free, so nodes whose objects barely move stick with the one big box.
+//!
+//! Objects without a bounding box (like an infinite `Plane`) can't go in the
+//! tree at all. They're kept in a list on the side, and every ray is tested
+//! against all of them.
 
 use crate::{
     aabb::AABB,
     hittable::{HitRecord, Hittable},
     ray::Ray,
+    vec3::{Axis::*, Vec3},
 };
+use std::time::{Duration, Instant};
+
+/// How many candidate split planes the SAH tries along each axis.
+const SAH_BINS: usize = 12;
+
+/// The cost of visiting a node, relative to hitting one object.
+const TRAVERSAL_COST: f32 = 1.0;
+
+/// The most objects the SAH will put in one leaf.
+const MAX_LEAF_OBJECTS: usize = 4;
+
+/// How much bigger (by area) the box around everything a node's objects sweep
+/// through has to be than its boxes at either end of the shutter interval,
+/// before it's worth interpolating between those instead.
+const MOTION_AREA_RATIO: f32 = 1.25;
+
+/// The deepest a tree can get. Anything past this is put in one big leaf, so
+/// that traversal can use a fixed-size stack.
+const MAX_DEPTH: usize = 64;
+
+/// How a `BVH` decides where to split a node.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SplitMethod {
+    /// Binned surface area heuristic. Slower to build, faster to trace.
+    Sah,
+    /// Split in half at the median object along the longest axis, with one
+    /// object per leaf.
+    Median,
+}
+
+/// Some numbers about a built `BVH`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BVHStats {
+    /// How long it took to build the tree.
+    pub build_time: Duration,
+    /// How many nodes there are, including leaves.
+    pub nodes: usize,
+    /// How many of the nodes are leaves.
+    pub leaves: usize,
+    /// How many nodes there are from the root to the deepest leaf, counting
+    /// both.
+    pub depth: usize,
+}
+
+/// A node in the flattened tree.
+#[derive(Debug, Clone, Copy)]
+struct Node {
+    /// The node's box at the start of the shutter interval if it's
+    /// `moving`, or over the whole interval if not.
+    bbox: AABB,
+    /// The node's box at the end of the shutter interval, if it's `moving`.
+    end: AABB,
+    /// For leaves, the index of the first object. Otherwise, the index of the
+    /// second child (the first child is always the next node).
+    offset: u32,
+    /// How many objects a leaf has. 0 for interior nodes.
+    count: u32,
+    /// The axis an interior node was split along.
+    axis: u8,
+    /// Whether the node's box has to be interpolated by the ray's time.
+    moving: bool,
+}
+
+/// An object being sorted into the tree.
+#[derive(Debug, Clone, Copy)]
+struct BuildItem {
+    index: usize,
+    /// The object's box halfway through the shutter interval, which is what
+    /// it gets sorted by.
+    bbox: AABB,
+    centroid: Vec3,
+    /// The object's boxes at the start and end of the shutter interval.
+    start: AABB,
+    end: AABB,
+}
 
 /// A bounding volume heirarchy.
-///
-/// Mainly based off of [this example](https://github.com/cbiffle/rtiow-rust/blob/master/src/bvh.rs).
 #[derive(Debug, Clone)]
 pub struct BVH {
-    pub bbox: AABB,
-    pub size: usize,
-    pub contents: BVHContents,
+    nodes: Vec<Node>,
+    /// The objects, ordered so that each leaf's objects are next to each
+    /// other.
+    objects: Vec<Box<dyn Hittable>>,
+    /// The objects that don't have a bounding box.
+    unbounded: Vec<Box<dyn Hittable>>,
+    /// Where each of `objects`, then each of `unbounded`, was in the list the
+    /// tree was built from.
+    indices: Vec<u32>,
+    /// Whether any of the nodes are `moving`.
+    moving: bool,
+    time0: f32,
+    /// 1 / (`time1` - `time0`), or 0 if the interval is empty.
+    inverse_duration: f32,
+    stats: BVHStats,
 }
 
-/// A node in a bounding volume heirarchy. Can hold references to left and right
-/// children. The children should implement `Hittable`, which means that they
-/// could either be objects or they could be more `BVHNodes`, fog clouds, etc...
-#[derive(Debug, Clone)]
-pub enum BVHContents {
-    Node { left: Box<BVH>, right: Box<BVH> },
-    Leaf(Box<dyn Hittable>),
+impl BVH {
+    /// Create a new `BVH`, split using the surface area heuristic.
+    pub fn new(objects: Vec<Box<dyn Hittable>>, time0: f32, time1: f32) -> Self {
+        Self::with_split_method(objects, time0, time1, SplitMethod::Sah)
+    }
+
+    /// Create a new `BVH`, choosing how nodes get split.
+    pub fn with_split_method(
+        objects: Vec<Box<dyn Hittable>>,
+        time0: f32,
+        time1: f32,
+        method: SplitMethod,
+    ) -> Self {
+        let start = Instant::now();
+
+        let mut items: Vec<BuildItem> = Vec::with_capacity(objects.len());
+        let mut unbounded_indices = vec![];
+        for (index, obj) in objects.iter().enumerate() {
+            match (
+                obj.bounding_box(time0, time0),
+                obj.bounding_box(time1, time1),
+            ) {
+                (Some(start), Some(end)) => {
+                    let bbox = start.lerp(&end, 0.5);
+                    items.push(BuildItem {
+                        index,
+                        bbox,
+                        centroid: (bbox.min + bbox.max) * 0.5,
+                        start,
+                        end,
+                    });
+                }
+                _ => unbounded_indices.push(index),
+            }
+        }
+
+        let mut builder = Builder {
+            method,
+            nodes: Vec::with_capacity(2 * items.len()),
+            order: Vec::with_capacity(items.len()),
+            leaves: 0,
+            depth: 0,
+        };
+        if !items.is_empty() {
+            builder.build(&mut items, 1);
+        }
+
+        // Put the objects in the same order as the leaves refer to them.
+        let mut objects: Vec<Option<Box<dyn Hittable>>> = objects.into_iter().map(Some).collect();
+        let mut take = |&index: &usize| objects[index].take().unwrap();
+        let unbounded = unbounded_indices.iter().map(&mut take).collect();
+        let objects = builder.order.iter().map(&mut take).collect();
+        let indices = builder
+            .order
+            .iter()
+            .chain(&unbounded_indices)
+            .map(|&index| index as u32)
+            .collect();
+
+        let moving = builder.nodes.iter().any(|node| node.moving);
+        let stats = BVHStats {
+            build_time: start.elapsed(),
+            nodes: builder.nodes.len(),
+            leaves: builder.leaves,
+            depth: builder.depth,
+        };
+
+        let duration = time1 - time0;
+
+        Self {
+            nodes: builder.nodes,
+            objects,
+            unbounded,
+            indices,
+            moving,
+            time0,
+            inverse_duration: if duration > 0.0 { 1.0 / duration } else { 0.0 },
+            stats,
+        }
+    }
+
+    /// How many objects there are, including the unbounded ones.
+    pub fn len(&self) -> usize {
+        self.objects.len() + self.unbounded.len()
+    }
+
+    /// Whether there are no objects at all.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// How many of the objects don't have a bounding box, and so are tested
+    /// against every ray.
+    pub fn unbounded_len(&self) -> usize {
+        self.unbounded.len()
+    }
+
+    /// Some numbers about the tree, like how long it took to build.
+    pub fn stats(&self) -> BVHStats {
+        self.stats
+    }
+
+    /// Whether any of the nodes have boxes for both ends of the shutter
+    /// interval, because their objects move a lot.
+    pub fn is_moving(&self) -> bool {
+        self.moving
+    }
+
+    /// Call `visit` for every object that `ray` might cross between `t_min`
+    /// and `t_max` (all of the unbounded ones, and those in every leaf whose
+    /// box it goes through), along with where the object was in the list the
+    /// tree was built from. Unlike `hit`, nothing gets culled by closer hits,
+    /// so this is for adding up something over everything along a ray.
+    pub fn for_each_along<F: FnMut(usize, &dyn Hittable)>(
+        &self,
+        ray: &Ray,
+        t_min: f32,
+        t_max: f32,
+        mut visit: F,
+    ) {
+        for (i, obj) in self.unbounded.iter().enumerate() {
+            visit(self.indices[self.objects.len() + i] as usize, obj.as_ref());
+        }
+
+        if self.nodes.is_empty() {
+            return;
+        }
+
+        let fraction = (ray.time - self.time0) * self.inverse_duration;
+
+        let mut stack = [0usize; MAX_DEPTH];
+        let mut stack_len = 1;
+        while stack_len > 0 {
+            stack_len -= 1;
+            let current = stack[stack_len];
+            let node = &self.nodes[current];
+            let bbox = if node.moving {
+                node.bbox.lerp(&node.end, fraction)
+            } else {
+                node.bbox
+            };
+            if !bbox.hit(ray, t_min, t_max) {
+                continue;
+            }
+
+            if node.count > 0 {
+                let first = node.offset as usize;
+                for index in first..first + node.count as usize {
+                    visit(self.indices[index] as usize, self.objects[index].as_ref());
+                }
+            } else {
+                stack[stack_len] = current + 1;
+                stack[stack_len + 1] = node.offset as usize;
+                stack_len += 2;
+            }
+        }
+    }
 }
 
-impl BVH {
-    /// Create a new `BVH`.
-    ///
-    /// Largely derived from Peter Shirley's implementation, but doesn't use
-    /// random axis selection, avoiding some pathological cases.
-    pub fn new(mut objects: Vec<Box<dyn Hittable>>, time0: f32, time1: f32) -> Self {
-        // Find the bounding box that encompasses all objects
-        let bbox: AABB = objects
+/// The state of a `BVH` that's being built.
+struct Builder {
+    method: SplitMethod,
+    nodes: Vec<Node>,
+    order: Vec<usize>,
+    leaves: usize,
+    depth: usize,
+}
+
+impl Builder {
+    /// Build the subtree for `items`, at some depth (starting at 1), and
+    /// return the index of its root node.
+    fn build(&mut self, items: &mut [BuildItem], depth: usize) -> usize {
+        self.depth = self.depth.max(depth);
+
+        let bbox = items.iter().skip(1).fold(items[0].bbox, |bb, item| {
+            AABB::surrounding_box(bb, item.bbox)
+        });
+
+        let start = items.iter().skip(1).fold(items[0].start, |bb, item| {
+            AABB::surrounding_box(bb, item.start)
+        });
+        let end = items
             .iter()
             .skip(1)
-            .fold(objects[0].bounding_box(time0, time1).unwrap(), |bb, obj| {
-                AABB::surrounding_box(bb, obj.bounding_box(time0, time1).unwrap())
-            });
+            .fold(items[0].end, |bb, item| AABB::surrounding_box(bb, item.end));
 
-        // Find the biggest axis for this set of objects
-        let axis = bbox.longest_axis();
+        let swept = AABB::surrounding_box(start, end);
+        let moving = swept.area() > MOTION_AREA_RATIO * start.area().max(end.area());
 
-        // Sort objects along longest axis by 2*centroid.
-        objects.sort_unstable_by(|a, b| {
-            let a_bb = a.bounding_box(time0, time1).unwrap();
-            let b_bb = b.bounding_box(time0, time1).unwrap();
-            let a_2centroid = a_bb.min[axis] + a_bb.max[axis];
-            let b_2centroid = b_bb.min[axis] + b_bb.max[axis];
-            a_2centroid.partial_cmp(&b_2centroid).unwrap()
+        let this = self.nodes.len();
+        self.nodes.push(Node {
+            bbox: if moving { start } else { swept },
+            end,
+            offset: 0,
+            count: 0,
+            axis: 0,
+            moving,
         });
 
-        match objects.len() {
-            0 => panic!("Can't create a BVH from zero objects!"),
-            1 => Self {
-                bbox,
-                size: 1,
-                contents: BVHContents::Leaf(objects.pop().unwrap()),
-            },
-            _ => {
-                let right = Box::new(BVH::new(
-                    objects.drain(objects.len() / 2..).collect(),
-                    time0,
-                    time1,
-                ));
-                let left = Box::new(BVH::new(objects, time0, time1));
-
-                Self {
-                    bbox: AABB::surrounding_box(left.bbox, right.bbox),
-                    size: left.size + right.size,
-                    contents: BVHContents::Node { left, right },
-                }
+        let split = if items.len() > 1 && depth < MAX_DEPTH {
+            match self.method {
+                SplitMethod::Sah => sah_split(items, &bbox),
+                SplitMethod::Median => Some(median_split(items, &bbox)),
+            }
+        } else {
+            None
+        };
+
+        match split {
+            Some((axis, mid)) => {
+                let (left, right) = items.split_at_mut(mid);
+                self.build(left, depth + 1);
+                let second = self.build(right, depth + 1);
+                self.nodes[this].offset = second as u32;
+                self.nodes[this].axis = axis as u8;
+            }
+            None => {
+                self.nodes[this].offset = self.order.len() as u32;
+                self.nodes[this].count = items.len() as u32;
+                self.order.extend(items.iter().map(|item| item.index));
+                self.leaves += 1;
+            }
+        }
+
+        this
+    }
+}
+
+/// Sort `items` by their centroids along the longest axis of `bbox`, and
+/// split them in half.
+fn median_split(items: &mut [BuildItem], bbox: &AABB) -> (usize, usize) {
+    let axis = bbox.longest_axis();
+    items.sort_unstable_by(|a, b| a.centroid[axis].partial_cmp(&b.centroid[axis]).unwrap());
+
+    let axis = match axis {
+        X => 0,
+        Y => 1,
+        Z => 2,
+    };
+    (axis, items.len() / 2)
+}
+
+/// Find the cheapest split for `items` according to the binned surface area
+/// heuristic, and partition them around it. Returns the split axis and where
+/// the second half starts, or `None` if the items are better off in a leaf.
+fn sah_split(items: &mut [BuildItem], bbox: &AABB) -> Option<(usize, usize)> {
+    // Bin by centroid, since the centroids are what get partitioned.
+    let centroids = items.iter().skip(1).fold(
+        AABB::new(items[0].centroid, items[0].centroid),
+        |bb, item| AABB::surrounding_box(bb, AABB::new(item.centroid, item.centroid)),
+    );
+
+    let bin_of = |item: &BuildItem, axis: usize| {
+        let extent = centroids.max[axis] - centroids.min[axis];
+        let b = ((item.centroid[axis] - centroids.min[axis]) / extent * SAH_BINS as f32) as usize;
+        b.min(SAH_BINS - 1)
+    };
+
+    // (cost, axis, the first bin on the right)
+    let mut best: Option<(f32, usize, usize)> = None;
+    for axis in 0..3 {
+        if centroids.max[axis] - centroids.min[axis] <= 0.0 {
+            continue;
+        }
+
+        let mut counts = [0usize; SAH_BINS];
+        let mut bounds: [Option<AABB>; SAH_BINS] = [None; SAH_BINS];
+        for item in items.iter() {
+            let b = bin_of(item, axis);
+            counts[b] += 1;
+            bounds[b] = Some(surround(bounds[b], item.bbox));
+        }
+
+        // Sweep from the right, so that the cost of every split can be found
+        // in one more sweep from the left.
+        let mut right_area = [0.0; SAH_BINS];
+        let mut right_count = [0usize; SAH_BINS];
+        let mut acc: Option<AABB> = None;
+        let mut count = 0;
+        for b in (1..SAH_BINS).rev() {
+            if let Some(bb) = bounds[b] {
+                acc = Some(surround(acc, bb));
+            }
+            count += counts[b];
+            right_area[b] = acc.map_or(0.0, |bb| bb.area());
+            right_count[b] = count;
+        }
+
+        let mut acc: Option<AABB> = None;
+        let mut count = 0;
+        for b in 1..SAH_BINS {
+            if let Some(bb) = bounds[b - 1] {
+                acc = Some(surround(acc, bb));
             }
+            count += counts[b - 1];
+            if count == 0 || right_count[b] == 0 {
+                continue;
+            }
+
+            let left_area = acc.map_or(0.0, |bb| bb.area());
+            let cost = left_area * count as f32 + right_area[b] * right_count[b] as f32;
+            if best.is_none_or(|(best_cost, ..)| cost < best_cost) {
+                best = Some((cost, axis, b));
+            }
+        }
+    }
+
+    let area = bbox.area();
+    let leaf_cost = items.len() as f32;
+    let split_cost = best.map(|(cost, ..)| {
+        if area > 0.0 {
+            TRAVERSAL_COST + cost / area
+        } else {
+            // Everything is squashed flat, so the areas don't say much.
+            f32::MAX
+        }
+    });
+
+    match (best, split_cost) {
+        (Some((_, axis, first_right)), Some(split_cost))
+            if split_cost < leaf_cost || items.len() > MAX_LEAF_OBJECTS =>
+        {
+            let mid = partition(items, |item| bin_of(item, axis) < first_right);
+            Some((axis, mid))
+        }
+        _ if items.len() > MAX_LEAF_OBJECTS => {
+            // All of the centroids are in the same place, so there's nothing
+            // to bin. Split in half anyway, to keep the leaves small.
+            Some(median_split(items, bbox))
         }
+        _ => None,
     }
 }
 
+/// Grow a maybe-empty box to fit `bbox`.
+fn surround(acc: Option<AABB>, bbox: AABB) -> AABB {
+    acc.map_or(bbox, |acc| AABB::surrounding_box(acc, bbox))
+}
+
+/// Move the items that `pred` holds for to the front, and return how many
+/// there are.
+fn partition<F: Fn(&BuildItem) -> bool>(items: &mut [BuildItem], pred: F) -> usize {
+    let mut mid = 0;
+    for i in 0..items.len() {
+        if pred(&items[i]) {
+            items.swap(i, mid);
+            mid += 1;
+        }
+    }
+    mid
+}
+
 impl Hittable for BVH {
     fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
-        if self.bbox.hit(ray, t_min, t_max) {
-            match &self.contents {
-                BVHContents::Leaf(obj) => obj.hit(ray, t_min, t_max),
+        let mut closest_so_far = t_max;
+        let mut rec: Option<HitRecord> = None;
 
-                BVHContents::Node { left, right } => {
-                    let hit_left = left.hit(ray, t_min, t_max);
+        // Test the unbounded objects first, so that anything they hide can
+        // be culled from the tree.
+        for obj in &self.unbounded {
+            if let Some(obj_rec) = obj.hit(ray, t_min, closest_so_far) {
+                closest_so_far = obj_rec.t;
+                rec = Some(obj_rec);
+            }
+        }
 
-                    // Don't bother searching past the left hit in the right BVH:
-                    let right_t_max = if let Some(rec) = &hit_left {
-                        rec.t
-                    } else {
-                        t_max
-                    };
+        if self.nodes.is_empty() {
+            return rec;
+        }
+
+        let negative = [
+            ray.direction[X] < 0.0,
+            ray.direction[Y] < 0.0,
+            ray.direction[Z] < 0.0,
+        ];
+
+        let fraction = (ray.time - self.time0) * self.inverse_duration;
 
-                    let hit_right = right.hit(ray, t_min, right_t_max);
+        let mut stack = [0usize; MAX_DEPTH];
+        let mut stack_len = 0;
+        let mut current = 0;
 
-                    match (hit_left, hit_right) {
-                        (h, None) | (None, h) => h,
-                        (Some(hl), Some(hr)) => {
-                            if hl.t < hr.t {
-                                Some(hl)
-                            } else {
-                                Some(hr)
-                            }
+        loop {
+            let node = &self.nodes[current];
+            let interpolated;
+            let bbox = if node.moving {
+                interpolated = node.bbox.lerp(&node.end, fraction);
+                &interpolated
+            } else {
+                &node.bbox
+            };
+
+            if bbox.hit(ray, t_min, closest_so_far) {
+                if node.count > 0 {
+                    let first = node.offset as usize;
+                    for obj in &self.objects[first..first + node.count as usize] {
+                        if let Some(obj_rec) = obj.hit(ray, t_min, closest_so_far) {
+                            closest_so_far = obj_rec.t;
+                            rec = Some(obj_rec);
                         }
                     }
+                } else {
+                    // Visit the near child first, and come back for the far
+                    // one (which might have been culled by then).
+                    let (near, far) = if negative[node.axis as usize] {
+                        (node.offset as usize, current + 1)
+                    } else {
+                        (current + 1, node.offset as usize)
+                    };
+                    stack[stack_len] = far;
+                    stack_len += 1;
+                    current = near;
+                    continue;
                 }
             }
-        } else {
-            None
+
+            if stack_len == 0 {
+                break;
+            }
+            stack_len -= 1;
+            current = stack[stack_len];
         }
+
+        rec
     }
 
     fn bounding_box(&self, _t0: f32, _t1: f32) -> Option<AABB> {
-        Some(self.bbox)
+        if !self.unbounded.is_empty() {
+            return None;
+        }
+        let root = self.nodes.first()?;
+        Some(AABB::surrounding_box(root.bbox, root.end))
     }
 
     fn box_clone(&self) -> Box<dyn Hittable> {
         Box::new(self.clone())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        hittable::{moving_sphere::MovingSphere, plane::Plane, sphere::Sphere, world::World},
+        material::Material,
+        texture, vec3,
+    };
+    use rand::prelude::*;
+    use rand_chacha::ChaCha8Rng;
+
+    fn spheres(rng: &mut ChaCha8Rng, n: usize) -> Vec<Box<dyn Hittable>> {
+        let material = Material::lambertian(texture::constant(Vec3::from(0.5)));
+        (0..n)
+            .map(|_| {
+                Box::new(Sphere::new(
+                    Vec3::random_range(rng, -10.0, 10.0),
+                    rng.gen_range(0.1, 1.0),
+                    material.clone(),
+                )) as Box<dyn Hittable>
+            })
+            .collect()
+    }
+
+    #[test]
+    fn finds_the_same_hits_as_a_plain_list() {
+        let mut rng = ChaCha8Rng::seed_from_u64(15);
+        let objects = spheres(&mut rng, 300);
+        let world = World::new(objects.clone());
+
+        for method in &[SplitMethod::Sah, SplitMethod::Median] {
+            let bvh = BVH::with_split_method(objects.clone(), 0.0, 1.0, *method);
+            assert_eq!(bvh.len(), 300);
+
+            for _ in 0..500 {
+                let ray = Ray::new(
+                    Vec3::random_range(&mut rng, -15.0, 15.0),
+                    Vec3::random_in_unit_sphere(&mut rng),
+                    0.0,
+                );
+                let expected = world.hit(&ray, 0.001, f32::MAX).map(|rec| rec.t);
+                let got = bvh.hit(&ray, 0.001, f32::MAX).map(|rec| rec.t);
+                assert_eq!(expected, got);
+            }
+        }
+    }
+
+    #[test]
+    fn visits_everything_along_a_ray() {
+        let mut rng = ChaCha8Rng::seed_from_u64(11);
+        let mut objects = spheres(&mut rng, 200);
+        objects.push(Box::new(Plane::new(
+            vec3!(0.0, -20.0, 0.0),
+            vec3!(0.0, 1.0, 0.0),
+            Material::lambertian(texture::constant(Vec3::from(0.5))),
+        )));
+        let bvh = BVH::new(objects.clone(), 0.0, 1.0);
+
+        for _ in 0..200 {
+            let ray = Ray::new(
+                Vec3::random_range(&mut rng, -15.0, 15.0),
+                Vec3::random_in_unit_sphere(&mut rng),
+                0.0,
+            );
+            let mut visited = vec![0; objects.len()];
+            bvh.for_each_along(&ray, 0.001, f32::MAX, |index, obj| {
+                visited[index] += 1;
+                // The index is the object's place in the original list.
+                assert_eq!(
+                    obj.bounding_box(0.0, 1.0),
+                    objects[index].bounding_box(0.0, 1.0)
+                );
+            });
+
+            assert!(visited.iter().all(|&count| count <= 1));
+            for (index, object) in objects.iter().enumerate() {
+                if object.hit(&ray, 0.001, f32::MAX).is_some() {
+                    assert_eq!(visited[index], 1);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn moving_objects_are_hit_at_the_ray_time() {
+        let mut rng = ChaCha8Rng::seed_from_u64(16);
+        let material = Material::lambertian(texture::constant(Vec3::from(0.5)));
+
+        // Spheres that streak a long way across the scene.
+        let objects: Vec<Box<dyn Hittable>> = (0..300)
+            .map(|_| {
+                let center = Vec3::random_range(&mut rng, -10.0, 10.0);
+                let motion = Vec3::random_range(&mut rng, -20.0, 20.0);
+                Box::new(MovingSphere::new(
+                    center,
+                    center + motion,
+                    0.0,
+                    1.0,
+                    0.5,
+                    material.clone(),
+                )) as Box<dyn Hittable>
+            })
+            .collect();
+        let world = World::new(objects.clone());
+        let bvh = BVH::new(objects, 0.0, 1.0);
+        assert!(bvh.is_moving());
+
+        for _ in 0..2000 {
+            let ray = Ray::new(
+                Vec3::random_range(&mut rng, -15.0, 15.0),
+                Vec3::random_in_unit_sphere(&mut rng),
+                rng.gen(),
+            );
+            let expected = world.hit(&ray, 0.001, f32::MAX).map(|rec| rec.t);
+            let got = bvh.hit(&ray, 0.001, f32::MAX).map(|rec| rec.t);
+            assert_eq!(expected, got);
+        }
+
+        assert_eq!(bvh.bounding_box(0.0, 1.0), world.bounding_box(0.0, 1.0));
+    }
+
+    #[test]
+    fn unbounded_objects_are_kept_out_of_the_tree() {
+        let mut rng = ChaCha8Rng::seed_from_u64(17);
+        let mut objects = spheres(&mut rng, 100);
+        objects.push(Box::new(Plane::new(
+            vec3!(0.0, -5.0, 0.0),
+            vec3!(0.0, 1.0, 0.0),
+            Material::lambertian(texture::constant(Vec3::from(0.5))),
+        )));
+        let world = World::new(objects.clone());
+        let bvh = BVH::new(objects, 0.0, 1.0);
+
+        assert_eq!(bvh.len(), 101);
+        assert_eq!(bvh.unbounded_len(), 1);
+        assert!(bvh.bounding_box(0.0, 1.0).is_none());
+
+        for _ in 0..500 {
+            let ray = Ray::new(
+                Vec3::random_range(&mut rng, -15.0, 15.0),
+                Vec3::random_in_unit_sphere(&mut rng),
+                0.0,
+            );
+            let expected = world.hit(&ray, 0.001, f32::MAX).map(|rec| rec.t);
+            let got = bvh.hit(&ray, 0.001, f32::MAX).map(|rec| rec.t);
+            assert_eq!(expected, got);
+        }
+    }
+
+    #[test]
+    fn stats_describe_the_tree() {
+        let mut rng = ChaCha8Rng::seed_from_u64(15);
+
+        let median = BVH::with_split_method(spheres(&mut rng, 64), 0.0, 1.0, SplitMethod::Median);
+        let stats = median.stats();
+        assert_eq!(stats.leaves, 64);
+        assert_eq!(stats.nodes, 127);
+        assert_eq!(stats.depth, 7);
+
+        let sah = BVH::new(spheres(&mut rng, 64), 0.0, 1.0);
+        let stats = sah.stats();
+        assert_eq!(stats.nodes, 2 * stats.leaves - 1);
+        assert!(stats.leaves <= 64);
+
+        assert!(!sah.is_moving());
+
+        let empty = BVH::new(vec![], 0.0, 1.0);
+        assert!(empty.is_empty());
+        assert_eq!(empty.stats().nodes, 0);
+        assert!(empty.bounding_box(0.0, 1.0).is_none());
+    }
+}
@@ -26,6 +26,7 @@ pub struct Camera {
     /// Vertical component of orthogonal basis.
     v: Vec3,
     /// Depth-wise component of orthogonal basis.
+    #[allow(dead_code)]
     w: Vec3,
 
     /// The radius of the lens.
@@ -94,22 +95,23 @@ impl Camera {
     /// screen coordinate (s, t). Will change starting location based on
     /// aperture of the camera and focal length.
     pub fn get_ray<R: Rng + ?Sized>(&self, rng: &mut R, s: f32, t: f32) -> Ray {
-        let rd = self.lens_radius * Vec3::random_in_unit_disk(rng);
-        let offset = self.u * rd[X] + self.v * rd[Y];
+        self.get_ray_sampled(s, t, (rng.gen(), rng.gen()), rng.gen())
+    }
 
-        // Send the ray out at a random time between time0 and time1:
-        let time = if (self.time1 - self.time0).abs() < f32::EPSILON {
-            self.time0
-        } else {
-            rng.gen_range(self.time0, self.time1)
-        };
+    /// Like `get_ray()`, but with the random choices made by the caller. `lens`
+    /// is a point in the unit square, which gets mapped onto the lens, and
+    /// `time` (in `[0, 1)`) picks when between `time0` and `time1` the ray is
+    /// sent out.
+    pub fn get_ray_sampled(&self, s: f32, t: f32, lens: (f32, f32), time: f32) -> Ray {
+        let rd = self.lens_radius * Vec3::in_unit_disk(lens);
+        let offset = self.u * rd[X] + self.v * rd[Y];
 
         Ray::new(
             self.origin + offset,
             self.lower_left_corner + (s * self.horizontal) + (t * self.vertical)
                 - self.origin
                 - offset,
-            time,
+            self.time0 + time * (self.time1 - self.time0),
         )
     }
 }
@@ -14,7 +14,6 @@ use crate::{
     vec3,
     vec3::{Axis::*, Vec3},
 };
-use rand::prelude::*;
 use std::sync::Arc;
 
 // A rectangle aligned with the X and Y axises.
@@ -84,7 +83,7 @@ impl Hittable for XYRect {
     }
 
     fn pdf_value(&self, origin: &Vec3, v: &Vec3) -> f32 {
-        if let Some(rec) = self.hit(&Ray::new(*origin, *v, 0.0), 0.001, std::f32::MAX) {
+        if let Some(rec) = self.hit(&Ray::new(*origin, *v, 0.0), 0.001, f32::MAX) {
             let area = (self.x1 - self.x0) * (self.y1 - self.y0);
             let distance_squared = rec.t * rec.t * v.length_squared();
             let cosine = (v.dot(&rec.normal) / v.length()).abs();
@@ -95,13 +94,10 @@ impl Hittable for XYRect {
         }
     }
 
-    fn random(&self, origin: &Vec3) -> Vec3 {
-        // Not ideal. Can't get around weird trait object stuff to just pass
-        // down rng.
-        let mut rng = thread_rng();
+    fn random(&self, origin: &Vec3, (u, v): (f32, f32)) -> Vec3 {
         let random_point = vec3!(
-            rng.gen_range(self.x0, self.x1),
-            rng.gen_range(self.y0, self.y1),
+            self.x0 + u * (self.x1 - self.x0),
+            self.y0 + v * (self.y1 - self.y0),
             self.k
         );
         random_point - *origin
@@ -179,7 +175,7 @@ impl Hittable for XZRect {
     }
 
     fn pdf_value(&self, origin: &Vec3, v: &Vec3) -> f32 {
-        if let Some(rec) = self.hit(&Ray::new(*origin, *v, 0.0), 0.001, std::f32::MAX) {
+        if let Some(rec) = self.hit(&Ray::new(*origin, *v, 0.0), 0.001, f32::MAX) {
             let area = (self.x1 - self.x0) * (self.z1 - self.z0);
             let distance_squared = rec.t * rec.t * v.length_squared();
             let cosine = (v.dot(&rec.normal) / v.length()).abs();
@@ -190,14 +186,11 @@ impl Hittable for XZRect {
         }
     }
 
-    fn random(&self, origin: &Vec3) -> Vec3 {
-        // Not ideal. Can't get around weird trait object stuff to just pass
-        // down rng.
-        let mut rng = thread_rng();
+    fn random(&self, origin: &Vec3, (u, v): (f32, f32)) -> Vec3 {
         let random_point = vec3!(
-            rng.gen_range(self.x0, self.x1),
+            self.x0 + u * (self.x1 - self.x0),
             self.k,
-            rng.gen_range(self.z0, self.z1)
+            self.z0 + v * (self.z1 - self.z0)
         );
         random_point - *origin
     }
@@ -274,7 +267,7 @@ impl Hittable for YZRect {
     }
 
     fn pdf_value(&self, origin: &Vec3, v: &Vec3) -> f32 {
-        if let Some(rec) = self.hit(&Ray::new(*origin, *v, 0.0), 0.001, std::f32::MAX) {
+        if let Some(rec) = self.hit(&Ray::new(*origin, *v, 0.0), 0.001, f32::MAX) {
             let area = (self.z1 - self.z0) * (self.y1 - self.y0);
             let distance_squared = rec.t * rec.t * v.length_squared();
             let cosine = (v.dot(&rec.normal) / v.length()).abs();
@@ -285,14 +278,11 @@ impl Hittable for YZRect {
         }
     }
 
-    fn random(&self, origin: &Vec3) -> Vec3 {
-        // Not ideal. Can't get around weird trait object stuff to just pass
-        // down rng.
-        let mut rng = thread_rng();
+    fn random(&self, origin: &Vec3, (u, v): (f32, f32)) -> Vec3 {
         let random_point = vec3!(
             self.k,
-            rng.gen_range(self.y0, self.y1),
-            rng.gen_range(self.z0, self.z1),
+            self.y0 + u * (self.y1 - self.y0),
+            self.z0 + v * (self.z1 - self.z0),
         );
         random_point - *origin
     }
@@ -0,0 +1,213 @@
+//! Cones, with or without a cap on the base, at any angle.
+
+use crate::{
+    aabb::AABB,
+    hittable::{area_pdf_value, disk::circle_bounding_box, HitRecord, Hittable},
+    material::Material,
+    onb::ONB,
+    ray::Ray,
+    vec3,
+    vec3::{Axis::*, Vec3},
+};
+use std::{f32::consts::PI, sync::Arc};
+
+/// A cone with a round base around `base`, narrowing to a point at its apex.
+/// The front face is the outside.
+#[derive(Debug, Clone)]
+pub struct Cone {
+    pub base: Vec3,
+    pub height: f32,
+    /// The radius of the base.
+    pub radius: f32,
+    /// Whether the base is closed off.
+    pub capped: bool,
+    /// `basis.w` runs along the cone, from the base to the apex.
+    basis: ONB,
+    pub material: Arc<Material>,
+}
+
+impl Cone {
+    /// Create a new cone from `base` up to `apex`, with a cap on the base.
+    pub fn new(base: Vec3, apex: Vec3, radius: f32, material: Material) -> Self {
+        Self {
+            base,
+            height: (apex - base).length(),
+            radius,
+            capped: true,
+            basis: ONB::build_from_w(apex - base),
+            material: Arc::new(material),
+        }
+    }
+
+    /// Leave the base of the cone open.
+    pub fn uncapped(mut self) -> Self {
+        self.capped = false;
+        self
+    }
+
+    /// The point of the cone.
+    pub fn apex(&self) -> Vec3 {
+        self.base + self.height * self.basis.w
+    }
+
+    /// The area of the sloped side.
+    fn side_area(&self) -> f32 {
+        let slant = (self.radius * self.radius + self.height * self.height).sqrt();
+        PI * self.radius * slant
+    }
+
+    /// The surface area of the cone, including the base if it's capped.
+    pub fn area(&self) -> f32 {
+        if self.capped {
+            self.side_area() + PI * self.radius * self.radius
+        } else {
+            self.side_area()
+        }
+    }
+}
+
+impl Hittable for Cone {
+    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
+        // Work in the cone's own space, where its base sits on the origin and
+        // the apex is up the Z axis.
+        let origin = self.basis.coords(&(ray.origin - self.base));
+        let direction = self.basis.coords(&ray.direction);
+
+        let mut closest: Option<(f32, Vec3, (f32, f32))> = None;
+
+        // The side: x² + y² = (k⋅(h - z))², where k is how quickly the radius
+        // shrinks with height.
+        let k = self.radius / self.height;
+        let k2 = k * k;
+        let below_apex = self.height - origin[Z];
+        let a = direction[X] * direction[X] + direction[Y] * direction[Y]
+            - k2 * direction[Z] * direction[Z];
+        let half_b =
+            origin[X] * direction[X] + origin[Y] * direction[Y] + k2 * below_apex * direction[Z];
+        let c = origin[X] * origin[X] + origin[Y] * origin[Y] - k2 * below_apex * below_apex;
+
+        let mut roots = [f32::NAN; 2];
+        if a.abs() < 1e-8 {
+            // The ray runs parallel to the side, so it only crosses it once.
+            if half_b != 0.0 {
+                roots[0] = -c / (2.0 * half_b);
+            }
+        } else {
+            let discriminant = half_b * half_b - a * c;
+            if discriminant >= 0.0 {
+                let root = discriminant.sqrt();
+                roots = [(-half_b - root) / a, (-half_b + root) / a];
+                if roots[0] > roots[1] {
+                    roots.swap(0, 1);
+                }
+            }
+        }
+
+        for &t in &roots {
+            let p = origin + t * direction;
+            // The equation has a second cone above the apex, which doesn't
+            // count.
+            if t > t_min && t < t_max && p[Z] >= 0.0 && p[Z] <= self.height {
+                let phi = p[Y].atan2(p[X]);
+                let uv = ((phi + PI) / (2.0 * PI), p[Z] / self.height);
+                let normal = vec3!(p[X], p[Y], k2 * (self.height - p[Z])).unit_vector();
+                closest = Some((t, normal, uv));
+                break;
+            }
+        }
+
+        // The cap, at z = 0.
+        if self.capped && direction[Z] != 0.0 {
+            let t = -origin[Z] / direction[Z];
+            let limit = closest.map_or(t_max, |(closest, _, _)| closest);
+            let p = origin + t * direction;
+            if t > t_min && t < limit && p[X] * p[X] + p[Y] * p[Y] <= self.radius * self.radius {
+                let uv = (
+                    0.5 + 0.5 * p[X] / self.radius,
+                    0.5 + 0.5 * p[Y] / self.radius,
+                );
+                closest = Some((t, vec3!(0.0, 0.0, -1.0), uv));
+            }
+        }
+
+        let (t, normal, uv) = closest?;
+        Some(HitRecord::new(
+            ray,
+            t,
+            ray.at(t),
+            self.basis.local(&normal),
+            self.material.clone(),
+            uv,
+        ))
+    }
+
+    fn bounding_box(&self, _t0: f32, _t1: f32) -> Option<AABB> {
+        let apex = self.apex();
+        Some(AABB::surrounding_box(
+            circle_bounding_box(self.base, self.basis.w, self.radius),
+            AABB::new(apex, apex),
+        ))
+    }
+
+    fn pdf_value(&self, origin: &Vec3, v: &Vec3) -> f32 {
+        area_pdf_value(self, self.area(), origin, v)
+    }
+
+    fn random(&self, origin: &Vec3, (u, v): (f32, f32)) -> Vec3 {
+        // Pick the side or the base by their share of the area, then reuse
+        // what's left of `u` to pick a point on it.
+        let side = self.side_area() / self.area();
+        let point = if u < side {
+            // There's more of the side further from the apex, in proportion
+            // to the distance.
+            let phi = 2.0 * PI * u / side;
+            let from_apex = v.sqrt();
+            let radius = self.radius * from_apex;
+            vec3!(
+                radius * phi.cos(),
+                radius * phi.sin(),
+                self.height * (1.0 - from_apex)
+            )
+        } else {
+            let u = ((u - side) / (1.0 - side)).min(1.0);
+            Vec3::in_unit_disk((u, v)) * self.radius
+        };
+
+        self.base + self.basis.local(&point) - *origin
+    }
+
+    fn box_clone(&self) -> Box<dyn Hittable> {
+        Box::new(self.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::texture;
+
+    #[test]
+    fn only_the_cone_below_the_apex_is_hit() {
+        let cone = Cone::new(
+            vec3!(0.0, 0.0, 0.0),
+            vec3!(0.0, 1.0, 0.0),
+            1.0,
+            Material::lambertian(texture::constant(Vec3::from(0.5))),
+        );
+
+        // Halfway up, the cone is half as wide, and the normal leans up at
+        // 45 degrees.
+        let ray = Ray::new(vec3!(-5.0, 0.5, 0.0), vec3!(1.0, 0.0, 0.0), 0.0);
+        let rec = cone.hit(&ray, 0.001, f32::MAX).unwrap();
+        assert!((rec.hit_point - vec3!(-0.5, 0.5, 0.0)).length() < 1e-5);
+        assert!((rec.normal - vec3!(-1.0, 1.0, 0.0).unit_vector()).length() < 1e-5);
+
+        let above = Ray::new(vec3!(-5.0, 1.5, 0.0), vec3!(1.0, 0.0, 0.0), 0.0);
+        assert!(cone.hit(&above, 0.001, f32::MAX).is_none());
+
+        let bottom = Ray::new(vec3!(0.25, -1.0, 0.0), vec3!(0.0, 1.0, 0.0), 0.0);
+        let rec = cone.hit(&bottom, 0.001, f32::MAX).unwrap();
+        assert!((rec.t - 1.0).abs() < 1e-5);
+        assert!((rec.normal - vec3!(0.0, -1.0, 0.0)).length() < 1e-5);
+    }
+}
@@ -5,11 +5,11 @@ use crate::{
     hittable::{HitRecord, Hittable},
     material::Material,
     ray::Ray,
+    sampler,
     texture::Texture,
     vec3,
-    vec3::Vec3,
+    vec3::{Axis::*, Vec3},
 };
-use rand::prelude::*;
 use std::sync::Arc;
 
 /// A volume of constant density.
@@ -24,29 +24,36 @@ pub struct ConstantMedium {
     phase_function: Arc<Material>,
     /// == -1/density
     neg_inv_density: f32,
+    /// Mixed into `random_for_ray`, so that media that a ray goes through
+    /// together don't all scatter it at the same point.
+    salt: u64,
 }
 
 impl ConstantMedium {
     /// Create a new constant medium. This is a volume of constant density.
     pub fn new(boundary: Box<dyn Hittable>, density: f32, albedo: Texture) -> Self {
+        let neg_inv_density = -1.0 / density;
+
+        let mut salt = u64::from(neg_inv_density.to_bits());
+        if let Some(bbox) = boundary.bounding_box(0.0, 1.0) {
+            for value in [bbox.min, bbox.max].iter().flat_map(|v| [v[X], v[Y], v[Z]]) {
+                salt = sampler::mix(salt, u64::from(value.to_bits()));
+            }
+        }
+
         Self {
             boundary,
             phase_function: Arc::new(Material::isotropic(albedo)),
-            neg_inv_density: -1.0 / density,
+            neg_inv_density,
+            salt,
         }
     }
 }
 
 impl Hittable for ConstantMedium {
     fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
-        // Not optimal to do this on every hit, but I don't feel like rewriting
-        // everything right now.
-        //
-        // TODO: Rewrite Hittable::hit to take an rng parameter!
-        let mut rng = thread_rng();
-
-        if let Some(mut rec1) = self.boundary.hit(ray, std::f32::MIN, std::f32::MAX) {
-            if let Some(mut rec2) = self.boundary.hit(ray, rec1.t + 0.0001, std::f32::MAX) {
+        if let Some(mut rec1) = self.boundary.hit(ray, f32::MIN, f32::MAX) {
+            if let Some(mut rec2) = self.boundary.hit(ray, rec1.t + 0.0001, f32::MAX) {
                 if rec1.t < t_min {
                     rec1.t = t_min
                 }
@@ -64,7 +71,7 @@ impl Hittable for ConstantMedium {
 
                 let ray_length = ray.direction.length();
                 let distance_inside_boundary = (rec2.t - rec1.t) * ray_length;
-                let hit_distance = self.neg_inv_density * rng.gen::<f32>().ln();
+                let hit_distance = self.neg_inv_density * random_for_ray(ray, self.salt).ln();
 
                 if hit_distance > distance_inside_boundary {
                     return None;
@@ -99,3 +106,79 @@ impl Hittable for ConstantMedium {
         Box::new(self.clone())
     }
 }
+
+/// A random number in `(0, 1]`, made by hashing the ray and `salt`.
+/// `Hittable::hit` doesn't get a random number generator, but every ray is
+/// already random, so this is as good as one (and just as reproducible).
+fn random_for_ray(ray: &Ray, salt: u64) -> f32 {
+    let Ray {
+        origin,
+        direction,
+        time,
+    } = *ray;
+    let hash = [
+        origin[X],
+        origin[Y],
+        origin[Z],
+        direction[X],
+        direction[Y],
+        direction[Z],
+        time,
+    ]
+    .iter()
+    .fold(salt, |hash, value| {
+        sampler::mix(hash, u64::from(value.to_bits()))
+    });
+
+    1.0 - (hash >> 40) as f32 / (1 << 24) as f32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{hittable::sphere::Sphere, texture};
+    use rand::prelude::*;
+    use rand_chacha::ChaCha8Rng;
+
+    #[test]
+    fn overlapping_media_scatter_independently() {
+        let medium = |density| {
+            let boundary = Sphere::new(
+                vec3!(),
+                1.0,
+                Material::lambertian(texture::constant(vec3!())),
+            );
+            ConstantMedium::new(
+                Box::new(boundary),
+                density,
+                texture::constant(Vec3::from(1.0)),
+            )
+        };
+        // With the same random number, the denser one would scatter every
+        // ray that the thinner one does.
+        let thin = medium(0.5);
+        let dense = medium(1.0);
+
+        let mut rng = ChaCha8Rng::seed_from_u64(10);
+        let samples = 100_000;
+        let (mut thin_hits, mut dense_hits, mut both) = (0, 0, 0);
+        for _ in 0..samples {
+            let origin = vec3!(rng.gen::<f32>() - 0.5, rng.gen::<f32>() - 0.5, -2.0);
+            let ray = Ray::new(origin, vec3!(0.0, 0.0, 1.0), rng.gen());
+            let a = thin.hit(&ray, 0.001, f32::INFINITY).is_some();
+            let b = dense.hit(&ray, 0.001, f32::INFINITY).is_some();
+            thin_hits += a as u32;
+            dense_hits += b as u32;
+            both += (a && b) as u32;
+        }
+
+        let chance = |count| count as f32 / samples as f32;
+        let expected = chance(thin_hits) * chance(dense_hits);
+        assert!(
+            (chance(both) - expected).abs() < 0.01,
+            "{} vs {}",
+            chance(both),
+            expected
+        );
+    }
+}
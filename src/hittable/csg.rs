@@ -0,0 +1,250 @@
+//! Constructive solid geometry: shapes made by combining two others, with a
+//! union, an intersection or a difference.
+//!
+//! Both objects need to be closed, so that each one has a well-defined
+//! inside. Walking along the ray, every time it crosses the surface of either
+//! object, it goes into or out of that object (going in if the hit is on the
+//! front face). The combined shape is only hit where the ray goes into or out
+//! of the combination as a whole.
+//!
+//! Whether a ray starts inside an object can be told from the first time it
+//! crosses its surface afterwards: if that's on the way out, it must have
+//! started inside. A closed object that the ray never crosses is one that the
+//! ray is outside of.
+
+use crate::{
+    aabb::AABB,
+    hittable::{HitRecord, Hittable},
+    ray::Ray,
+    vec3::Vec3,
+};
+
+/// How far to step past a crossing to look for the next one.
+const STEP: f32 = 0.0001;
+
+/// How two objects are combined.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Operation {
+    /// Everything inside either object.
+    Union,
+    /// Everything inside both objects.
+    Intersection,
+    /// Everything inside the left object but not inside the right one.
+    Difference,
+}
+
+impl Operation {
+    /// Whether a point is inside the combined shape, given whether it's
+    /// inside each of the objects.
+    fn contains(self, left: bool, right: bool) -> bool {
+        match self {
+            Operation::Union => left || right,
+            Operation::Intersection => left && right,
+            Operation::Difference => left && !right,
+        }
+    }
+}
+
+/// Two closed objects combined into one. Surfaces keep the material of the
+/// object they came from, so the walls of a hole carved out by a difference
+/// have the right object's material.
+#[derive(Debug, Clone)]
+pub struct CSG {
+    pub operation: Operation,
+    left: Box<dyn Hittable>,
+    right: Box<dyn Hittable>,
+}
+
+impl CSG {
+    /// Combine two closed objects.
+    pub fn new(operation: Operation, left: Box<dyn Hittable>, right: Box<dyn Hittable>) -> Self {
+        Self {
+            operation,
+            left,
+            right,
+        }
+    }
+
+    /// Everything inside either object.
+    pub fn union(left: Box<dyn Hittable>, right: Box<dyn Hittable>) -> Self {
+        Self::new(Operation::Union, left, right)
+    }
+
+    /// Everything inside both objects.
+    pub fn intersection(left: Box<dyn Hittable>, right: Box<dyn Hittable>) -> Self {
+        Self::new(Operation::Intersection, left, right)
+    }
+
+    /// `left`, with everything inside `right` carved out of it.
+    pub fn difference(left: Box<dyn Hittable>, right: Box<dyn Hittable>) -> Self {
+        Self::new(Operation::Difference, left, right)
+    }
+}
+
+impl Hittable for CSG {
+    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
+        let mut left = self.left.hit(ray, t_min, f32::MAX);
+        let mut right = self.right.hit(ray, t_min, f32::MAX);
+        let mut inside_left = left.as_ref().is_some_and(|rec| !rec.front_face);
+        let mut inside_right = right.as_ref().is_some_and(|rec| !rec.front_face);
+
+        loop {
+            // Take whichever crossing comes next along the ray.
+            let is_left = match (&left, &right) {
+                (None, None) => return None,
+                (Some(_), None) => true,
+                (None, Some(_)) => false,
+                (Some(l), Some(r)) => l.t <= r.t,
+            };
+            let mut rec = if is_left { left.take() } else { right.take() }?;
+            if rec.t >= t_max {
+                return None;
+            }
+
+            let was_inside = self.operation.contains(inside_left, inside_right);
+            if is_left {
+                inside_left = rec.front_face;
+            } else {
+                inside_right = rec.front_face;
+            }
+            let is_inside = self.operation.contains(inside_left, inside_right);
+
+            if was_inside != is_inside {
+                // The normal already faces against the ray. Which face got
+                // hit depends on the combined shape, though: going into the
+                // carved-out part of a difference means coming out of the
+                // right object.
+                rec.front_face = is_inside;
+                return Some(rec);
+            }
+
+            if is_left {
+                left = self.left.hit(ray, rec.t + STEP, f32::MAX);
+            } else {
+                right = self.right.hit(ray, rec.t + STEP, f32::MAX);
+            }
+        }
+    }
+
+    fn bounding_box(&self, t0: f32, t1: f32) -> Option<AABB> {
+        let left = self.left.bounding_box(t0, t1)?;
+        match self.operation {
+            Operation::Union => Some(AABB::surrounding_box(
+                left,
+                self.right.bounding_box(t0, t1)?,
+            )),
+            Operation::Intersection => {
+                let right = self.right.bounding_box(t0, t1)?;
+                let mut min = Vec3::default();
+                let mut max = Vec3::default();
+                for i in 0..3 {
+                    min[i] = left.min[i].max(right.min[i]);
+                    // If the boxes don't overlap, neither do the objects, so
+                    // there's nothing to hit. Keep the box from turning
+                    // inside out anyway.
+                    max[i] = left.max[i].min(right.max[i]).max(min[i]);
+                }
+                Some(AABB::new(min, max))
+            }
+            Operation::Difference => Some(left),
+        }
+    }
+
+    fn box_clone(&self) -> Box<dyn Hittable> {
+        Box::new(self.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        hittable::{cylinder::Cylinder, sphere::Sphere},
+        material::Material,
+        texture, vec3,
+    };
+
+    fn material() -> Material {
+        Material::lambertian(texture::constant(Vec3::from(0.5)))
+    }
+
+    /// A lens, from two spheres that overlap between x = -0.5 and x = 0.5.
+    fn lens() -> CSG {
+        CSG::intersection(
+            Box::new(Sphere::new(vec3!(-0.5, 0.0, 0.0), 1.0, material())),
+            Box::new(Sphere::new(vec3!(0.5, 0.0, 0.0), 1.0, material())),
+        )
+    }
+
+    #[test]
+    fn lens_is_hit_from_outside_and_inside() {
+        let ray = Ray::new(vec3!(-5.0, 0.0, 0.0), vec3!(1.0, 0.0, 0.0), 0.0);
+        let rec = lens().hit(&ray, 0.001, f32::MAX).unwrap();
+        assert!((rec.t - 4.5).abs() < 1e-5);
+        assert!(rec.front_face);
+        assert!((rec.normal - vec3!(-1.0, 0.0, 0.0)).length() < 1e-5);
+
+        // Starting inside, the ray comes out of the far side.
+        let ray = Ray::new(vec3!(0.0, 0.0, 0.0), vec3!(1.0, 0.0, 0.0), 0.0);
+        let rec = lens().hit(&ray, 0.001, f32::MAX).unwrap();
+        assert!((rec.t - 0.5).abs() < 1e-5);
+        assert!(!rec.front_face);
+        assert!((rec.normal - vec3!(-1.0, 0.0, 0.0)).length() < 1e-5);
+
+        // Inside one sphere but not the other isn't part of the lens.
+        let ray = Ray::new(vec3!(-1.2, -5.0, 0.0), vec3!(0.0, 1.0, 0.0), 0.0);
+        assert!(lens().hit(&ray, 0.001, f32::MAX).is_none());
+    }
+
+    #[test]
+    fn difference_carves_a_hole() {
+        let bead = CSG::difference(
+            Box::new(Sphere::new(vec3!(), 1.0, material())),
+            Box::new(Cylinder::new(
+                vec3!(0.0, -2.0, 0.0),
+                vec3!(0.0, 2.0, 0.0),
+                0.5,
+                material(),
+            )),
+        );
+
+        // Straight down the hole.
+        let ray = Ray::new(vec3!(0.0, 5.0, 0.0), vec3!(0.0, -1.0, 0.0), 0.0);
+        assert!(bead.hit(&ray, 0.001, f32::MAX).is_none());
+
+        // From inside the hole, the ray hits the wall of the hole, which
+        // faces inwards.
+        let ray = Ray::new(vec3!(), vec3!(1.0, 0.0, 0.0), 0.0);
+        let rec = bead.hit(&ray, 0.001, f32::MAX).unwrap();
+        assert!((rec.t - 0.5).abs() < 1e-5);
+        assert!(rec.front_face);
+        assert!((rec.normal - vec3!(-1.0, 0.0, 0.0)).length() < 1e-5);
+
+        // And then it leaves through the outside of the sphere.
+        let rec = bead.hit(&ray, 0.6, f32::MAX).unwrap();
+        assert!((rec.t - 1.0).abs() < 1e-5);
+        assert!(!rec.front_face);
+    }
+
+    #[test]
+    fn union_hides_the_inner_surfaces() {
+        let pair = CSG::union(
+            Box::new(Sphere::new(vec3!(-0.5, 0.0, 0.0), 1.0, material())),
+            Box::new(Sphere::new(vec3!(0.5, 0.0, 0.0), 1.0, material())),
+        );
+
+        let ray = Ray::new(vec3!(-5.0, 0.0, 0.0), vec3!(1.0, 0.0, 0.0), 0.0);
+        let rec = pair.hit(&ray, 0.001, f32::MAX).unwrap();
+        assert!((rec.t - 3.5).abs() < 1e-5);
+
+        // The right sphere starts inside the left one, so that crossing
+        // doesn't count.
+        let rec = pair.hit(&ray, rec.t + 0.001, f32::MAX).unwrap();
+        assert!((rec.t - 6.5).abs() < 1e-5);
+        assert!(!rec.front_face);
+
+        let bbox = pair.bounding_box(0.0, 1.0).unwrap();
+        assert_eq!(bbox.min, vec3!(-1.5, -1.0, -1.0));
+        assert_eq!(bbox.max, vec3!(1.5, 1.0, 1.0));
+    }
+}
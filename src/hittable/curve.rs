@@ -0,0 +1,411 @@
+//! Curves, for thin strands like hair, fur, grass and cables.
+//!
+//! A curve is a cubic Bézier spline with a width that changes linearly along
+//! it. It's hit like a flat ribbon that always faces the ray, but its normal
+//! bends around from one edge to the other, so it's shaded like a round tube.
+//!
+//! To hit one, the control points are moved into a space where the ray starts
+//! at the origin and runs down the Z axis. Then the curve gets cut in half
+//! over and over (the number of times depends on how curvy it is) until each
+//! piece is close enough to a straight line, and pieces whose boxes don't
+//! reach the ray are skipped. See *Physically Based Rendering*, chapter 3.7.
+
+use crate::{
+    aabb::AABB,
+    hittable::{HitRecord, Hittable},
+    material::Material,
+    onb::ONB,
+    ray::Ray,
+    vec3::{Axis::*, Vec3},
+};
+use std::sync::Arc;
+
+/// A cubic Bézier curve with some width.
+#[derive(Debug, Clone)]
+pub struct Curve {
+    /// The control points. The curve starts at the first and ends at the
+    /// last.
+    pub points: [Vec3; 4],
+    /// The width at the start and end of the curve.
+    pub width: (f32, f32),
+    /// Where this curve starts and ends along the whole strand, if it's a
+    /// piece of a longer one. This is the u surface coordinate.
+    u_range: (f32, f32),
+    pub material: Arc<Material>,
+}
+
+/// Where a ray hit the ribbon, in ray space.
+struct RibbonHit {
+    /// The distance along the ray.
+    z: f32,
+    /// How far along the curve.
+    u: f32,
+    /// From one edge of the ribbon (0) to the other (1).
+    v: f32,
+    /// How far from the middle of the ribbon, as a fraction of half its
+    /// width.
+    offset: f32,
+    /// The direction from the middle of the ribbon to the hit, across the
+    /// ray.
+    side: (f32, f32),
+}
+
+impl Curve {
+    /// Create a new curve, with `width` at its start and end.
+    pub fn new(points: [Vec3; 4], width: (f32, f32), material: Material) -> Self {
+        Self {
+            points,
+            width,
+            u_range: (0.0, 1.0),
+            material: Arc::new(material),
+        }
+    }
+
+    /// Cut the curve into some number of shorter curves, with the same shape.
+    /// Long curves should be cut up before they go into a `BVH`, so that each
+    /// piece gets a snug bounding box.
+    pub fn split(&self, pieces: usize) -> Vec<Curve> {
+        (0..pieces)
+            .map(|i| {
+                let u0 = i as f32 / pieces as f32;
+                let u1 = (i + 1) as f32 / pieces as f32;
+                Self {
+                    points: [
+                        blossom(&self.points, u0, u0, u0),
+                        blossom(&self.points, u0, u0, u1),
+                        blossom(&self.points, u0, u1, u1),
+                        blossom(&self.points, u1, u1, u1),
+                    ],
+                    width: (self.width_at(u0), self.width_at(u1)),
+                    u_range: (
+                        lerp(self.u_range.0, self.u_range.1, u0),
+                        lerp(self.u_range.0, self.u_range.1, u1),
+                    ),
+                    material: self.material.clone(),
+                }
+            })
+            .collect()
+    }
+
+    /// The width of the curve some fraction `u` of the way along it.
+    fn width_at(&self, u: f32) -> f32 {
+        lerp(self.width.0, self.width.1, u)
+    }
+
+    /// Look for a hit on the part of the curve between `u.0` and `u.1`, whose
+    /// control points (in ray space) are `points`. `depth` is how many more
+    /// times to cut it in half.
+    fn hit_ribbon(
+        &self,
+        points: &[Vec3; 4],
+        u: (f32, f32),
+        depth: u32,
+        z_min: f32,
+        mut z_max: f32,
+    ) -> Option<RibbonHit> {
+        if depth > 0 {
+            let halves = split_in_half(points);
+            let middle = 0.5 * (u.0 + u.1);
+
+            let mut closest = None;
+            for &(half, range) in &[
+                ([halves[0], halves[1], halves[2], halves[3]], (u.0, middle)),
+                ([halves[3], halves[4], halves[5], halves[6]], (middle, u.1)),
+            ] {
+                let half_width = 0.5 * self.width_at(range.0).max(self.width_at(range.1));
+                if !reaches_ray(&half, half_width, z_min, z_max) {
+                    continue;
+                }
+                if let Some(hit) = self.hit_ribbon(&half, range, depth - 1, z_min, z_max) {
+                    z_max = hit.z;
+                    closest = Some(hit);
+                }
+            }
+
+            return closest;
+        }
+
+        // This piece is about straight. Make sure that the ray passes by
+        // between its ends, rather than off beyond either one. The ends are
+        // cut off perpendicular to the curve, so that neighbouring pieces
+        // meet without gaps.
+        let [p0, p1, p2, p3] = *points;
+        if (p1[Y] - p0[Y]) * -p0[Y] + p0[X] * (p0[X] - p1[X]) < 0.0
+            || (p2[Y] - p3[Y]) * -p3[Y] + p3[X] * (p3[X] - p2[X]) < 0.0
+        {
+            return None;
+        }
+
+        // Find the closest point along the piece to the ray.
+        let segment = (p3[X] - p0[X], p3[Y] - p0[Y]);
+        let length_squared = segment.0 * segment.0 + segment.1 * segment.1;
+        if length_squared == 0.0 {
+            return None;
+        }
+        let w = ((-p0[X] * segment.0 - p0[Y] * segment.1) / length_squared).clamp(0.0, 1.0);
+
+        let hit_u = lerp(u.0, u.1, w);
+        let (center, derivative) = evaluate(points, w);
+        let half_width = 0.5 * self.width_at(hit_u);
+        let distance_squared = center[X] * center[X] + center[Y] * center[Y];
+        if distance_squared > half_width * half_width || center[Z] < z_min || center[Z] > z_max {
+            return None;
+        }
+
+        // Which edge of the ribbon is the hit closer to?
+        let distance = distance_squared.sqrt();
+        let offset = distance / half_width;
+        let edge = derivative[X] * -center[Y] + center[X] * derivative[Y];
+        let v = if edge > 0.0 {
+            0.5 + 0.5 * offset
+        } else {
+            0.5 - 0.5 * offset
+        };
+        let side = if distance > 0.0 {
+            (-center[X] / distance, -center[Y] / distance)
+        } else {
+            (0.0, 0.0)
+        };
+
+        Some(RibbonHit {
+            z: center[Z],
+            u: hit_u,
+            v,
+            offset,
+            side,
+        })
+    }
+}
+
+impl Hittable for Curve {
+    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
+        let length = ray.direction.length();
+        let frame = ONB::build_from_w(ray.direction);
+        let points = [
+            frame.coords(&(self.points[0] - ray.origin)),
+            frame.coords(&(self.points[1] - ray.origin)),
+            frame.coords(&(self.points[2] - ray.origin)),
+            frame.coords(&(self.points[3] - ray.origin)),
+        ];
+
+        let (z_min, z_max) = (t_min * length, t_max.min(f32::MAX / length) * length);
+        let half_width = 0.5 * self.width.0.max(self.width.1);
+        if !reaches_ray(&points, half_width, z_min, z_max) {
+            return None;
+        }
+
+        // Cut the curve up until each piece bends by less than about a
+        // tenth of its half width.
+        let bend = (0..2)
+            .map(|i| {
+                let second_difference = points[i] - 2.0 * points[i + 1] + points[i + 2];
+                second_difference[X]
+                    .abs()
+                    .max(second_difference[Y].abs())
+                    .max(second_difference[Z].abs())
+            })
+            .fold(0.0, f32::max);
+        let tolerance = half_width * 0.1;
+        let depth = if bend > 0.0 && tolerance > 0.0 {
+            let r0 = (std::f32::consts::SQRT_2 * 6.0 * bend / (8.0 * tolerance)).log2() / 2.0;
+            r0.round().clamp(0.0, 10.0) as u32
+        } else {
+            0
+        };
+
+        let hit = self.hit_ribbon(&points, (0.0, 1.0), depth, z_min, z_max)?;
+        let t = hit.z / length;
+
+        // Bend the normal around the strand, from facing the ray in the
+        // middle to facing sideways at the edges, like on a tube.
+        let tangent = evaluate(&self.points, hit.u).1.unit_vector();
+        let towards_ray = -ray.direction / length;
+        let facing = towards_ray - tangent * towards_ray.dot(&tangent);
+        let facing = if facing.length_squared() > 1e-12 {
+            facing.unit_vector()
+        } else {
+            towards_ray
+        };
+        let side = frame.local_coords(hit.side.0, hit.side.1, 0.0);
+        let side = side - tangent * side.dot(&tangent) - facing * side.dot(&facing);
+        let normal = if side.length_squared() > 1e-12 {
+            let offset = hit.offset.min(1.0);
+            facing * (1.0 - offset * offset).sqrt() + side.unit_vector() * offset
+        } else {
+            facing
+        };
+
+        let mut rec = HitRecord::new(
+            ray,
+            t,
+            ray.at(t),
+            normal,
+            self.material.clone(),
+            (lerp(self.u_range.0, self.u_range.1, hit.u), hit.v),
+        );
+        rec.tangent = Some(tangent);
+
+        Some(rec)
+    }
+
+    fn bounding_box(&self, _t0: f32, _t1: f32) -> Option<AABB> {
+        // The curve stays inside the hull of its control points.
+        let mut min = self.points[0];
+        let mut max = self.points[0];
+        for point in &self.points[1..] {
+            for i in 0..3 {
+                min[i] = min[i].min(point[i]);
+                max[i] = max[i].max(point[i]);
+            }
+        }
+
+        let half_width = Vec3::from(0.5 * self.width.0.max(self.width.1) + 0.0001);
+        Some(AABB::new(min - half_width, max + half_width))
+    }
+
+    fn box_clone(&self) -> Box<dyn Hittable> {
+        Box::new(self.clone())
+    }
+}
+
+fn lerp<T>(a: T, b: T, t: f32) -> T
+where
+    T: std::ops::Mul<f32, Output = T> + std::ops::Add<Output = T>,
+{
+    a * (1.0 - t) + b * t
+}
+
+/// The point and the derivative of a cubic Bézier curve at `u`, using de
+/// Casteljau's algorithm.
+fn evaluate(points: &[Vec3; 4], u: f32) -> (Vec3, Vec3) {
+    let a = [
+        lerp(points[0], points[1], u),
+        lerp(points[1], points[2], u),
+        lerp(points[2], points[3], u),
+    ];
+    let b = [lerp(a[0], a[1], u), lerp(a[1], a[2], u)];
+
+    (lerp(b[0], b[1], u), 3.0 * (b[1] - b[0]))
+}
+
+/// The blossom of a cubic Bézier curve. The control points of the part of the
+/// curve between `u0` and `u1` are the blossoms `(u0, u0, u0)`, `(u0, u0,
+/// u1)`, `(u0, u1, u1)` and `(u1, u1, u1)`.
+fn blossom(points: &[Vec3; 4], u0: f32, u1: f32, u2: f32) -> Vec3 {
+    let a = [
+        lerp(points[0], points[1], u0),
+        lerp(points[1], points[2], u0),
+        lerp(points[2], points[3], u0),
+    ];
+    let b = [lerp(a[0], a[1], u1), lerp(a[1], a[2], u1)];
+
+    lerp(b[0], b[1], u2)
+}
+
+/// Cut a cubic Bézier curve in half. The halves share the middle point, so
+/// the first half is `[0..4]` and the second is `[3..7]`.
+fn split_in_half(p: &[Vec3; 4]) -> [Vec3; 7] {
+    [
+        p[0],
+        (p[0] + p[1]) / 2.0,
+        (p[0] + 2.0 * p[1] + p[2]) / 4.0,
+        (p[0] + 3.0 * p[1] + 3.0 * p[2] + p[3]) / 8.0,
+        (p[1] + 2.0 * p[2] + p[3]) / 4.0,
+        (p[2] + p[3]) / 2.0,
+        p[3],
+    ]
+}
+
+/// Whether the box around some control points (in ray space), grown by
+/// `half_width`, reaches the ray between `z_min` and `z_max`.
+fn reaches_ray(points: &[Vec3; 4], half_width: f32, z_min: f32, z_max: f32) -> bool {
+    let range = |axis| {
+        points
+            .iter()
+            .fold((f32::INFINITY, f32::NEG_INFINITY), |(low, high), p| {
+                (low.min(p[axis]), high.max(p[axis]))
+            })
+    };
+    let (x, y, z) = (range(X), range(Y), range(Z));
+
+    x.0 - half_width <= 0.0
+        && x.1 + half_width >= 0.0
+        && y.0 - half_width <= 0.0
+        && y.1 + half_width >= 0.0
+        && z.0 - half_width <= z_max
+        && z.1 + half_width >= z_min
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{texture, vec3};
+
+    fn material() -> Material {
+        Material::lambertian(texture::constant(Vec3::from(0.5)))
+    }
+
+    #[test]
+    fn straight_strand_is_shaded_like_a_tube() {
+        let strand = Curve::new(
+            [
+                vec3!(-1.0, 0.0, 0.0),
+                vec3!(-1.0 / 3.0, 0.0, 0.0),
+                vec3!(1.0 / 3.0, 0.0, 0.0),
+                vec3!(1.0, 0.0, 0.0),
+            ],
+            (0.2, 0.2),
+            material(),
+        );
+
+        let ray = Ray::new(vec3!(0.3, 0.05, 5.0), vec3!(0.0, 0.0, -1.0), 0.0);
+        let rec = strand.hit(&ray, 0.001, f32::MAX).unwrap();
+        assert!((rec.t - 5.0).abs() < 1e-4);
+        assert!(((rec.uv.1 - 0.5).abs() - 0.25).abs() < 1e-4);
+        assert!((rec.uv.0 - 0.65).abs() < 1e-3);
+        assert!((rec.tangent.unwrap() - vec3!(1.0, 0.0, 0.0)).length() < 1e-4);
+
+        // Halfway to the edge, the normal has turned 30 degrees towards it.
+        let expected = vec3!(0.0, 0.5, 0.75_f32.sqrt());
+        assert!((rec.normal - expected).length() < 1e-3);
+
+        let beside = Ray::new(vec3!(0.3, 0.15, 5.0), vec3!(0.0, 0.0, -1.0), 0.0);
+        assert!(strand.hit(&beside, 0.001, f32::MAX).is_none());
+    }
+
+    #[test]
+    fn split_curves_have_the_same_shape() {
+        let strand = Curve::new(
+            [
+                vec3!(0.0, 0.0, 0.0),
+                vec3!(0.0, 2.0, 0.5),
+                vec3!(1.0, 2.0, -0.5),
+                vec3!(2.0, 3.0, 0.0),
+            ],
+            (0.1, 0.02),
+            material(),
+        );
+        let pieces = strand.split(4);
+
+        for i in 0..=20 {
+            let u = i as f32 / 20.0;
+            let (point, _) = evaluate(&strand.points, u);
+            let ray = Ray::new(point + vec3!(0.0, 0.0, 5.0), vec3!(0.0, 0.0, -1.0), 0.0);
+
+            let whole = strand.hit(&ray, 0.001, f32::MAX).map(|rec| rec.t);
+            let split = pieces
+                .iter()
+                .filter_map(|piece| piece.hit(&ray, 0.001, f32::MAX))
+                .map(|rec| rec.t)
+                .fold(None, |closest: Option<f32>, t| {
+                    Some(closest.map_or(t, |c| c.min(t)))
+                });
+
+            assert!(whole.is_some(), "missed the whole curve at u = {}", u);
+            assert!((whole.unwrap() - split.unwrap()).abs() < 1e-3);
+        }
+
+        let bbox = pieces[0].bounding_box(0.0, 1.0).unwrap();
+        assert!(bbox.max[Y] < strand.bounding_box(0.0, 1.0).unwrap().max[Y]);
+    }
+}
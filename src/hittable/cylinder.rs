@@ -0,0 +1,211 @@
+//! Cylinders, with or without caps on the ends, at any angle.
+
+use crate::{
+    aabb::AABB,
+    hittable::{area_pdf_value, disk::circle_bounding_box, HitRecord, Hittable},
+    material::Material,
+    onb::ONB,
+    ray::Ray,
+    vec3,
+    vec3::{Axis::*, Vec3},
+};
+use std::{f32::consts::PI, sync::Arc};
+
+/// A cylinder running from the middle of its `base` to the middle of its top.
+/// The front face is the outside.
+#[derive(Debug, Clone)]
+pub struct Cylinder {
+    pub base: Vec3,
+    pub height: f32,
+    pub radius: f32,
+    /// Whether the ends are closed off. Without caps, the cylinder is a tube.
+    pub capped: bool,
+    /// `basis.w` runs along the cylinder, from the base to the top.
+    basis: ONB,
+    pub material: Arc<Material>,
+}
+
+impl Cylinder {
+    /// Create a new cylinder between `base` and `top`, with caps on the ends.
+    pub fn new(base: Vec3, top: Vec3, radius: f32, material: Material) -> Self {
+        Self {
+            base,
+            height: (top - base).length(),
+            radius,
+            capped: true,
+            basis: ONB::build_from_w(top - base),
+            material: Arc::new(material),
+        }
+    }
+
+    /// Leave the ends of the cylinder open, like a pipe.
+    pub fn uncapped(mut self) -> Self {
+        self.capped = false;
+        self
+    }
+
+    /// The middle of the top of the cylinder.
+    pub fn top(&self) -> Vec3 {
+        self.base + self.height * self.basis.w
+    }
+
+    /// The area of the curved side.
+    fn side_area(&self) -> f32 {
+        2.0 * PI * self.radius * self.height
+    }
+
+    /// The area of one of the caps.
+    fn cap_area(&self) -> f32 {
+        PI * self.radius * self.radius
+    }
+
+    /// The surface area of the cylinder, including the caps if it has them.
+    pub fn area(&self) -> f32 {
+        if self.capped {
+            self.side_area() + 2.0 * self.cap_area()
+        } else {
+            self.side_area()
+        }
+    }
+}
+
+impl Hittable for Cylinder {
+    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
+        // Work in the cylinder's own space, where it stands on the origin and
+        // runs up the Z axis.
+        let origin = self.basis.coords(&(ray.origin - self.base));
+        let direction = self.basis.coords(&ray.direction);
+
+        let mut closest: Option<(f32, Vec3, (f32, f32))> = None;
+
+        // The curved side: x² + y² = r².
+        let a = direction[X] * direction[X] + direction[Y] * direction[Y];
+        let half_b = origin[X] * direction[X] + origin[Y] * direction[Y];
+        let c = origin[X] * origin[X] + origin[Y] * origin[Y] - self.radius * self.radius;
+        let discriminant = half_b * half_b - a * c;
+        if a > 0.0 && discriminant > 0.0 {
+            let root = discriminant.sqrt();
+            for &t in &[(-half_b - root) / a, (-half_b + root) / a] {
+                let p = origin + t * direction;
+                if t > t_min && t < t_max && p[Z] >= 0.0 && p[Z] <= self.height {
+                    let phi = p[Y].atan2(p[X]);
+                    let uv = ((phi + PI) / (2.0 * PI), p[Z] / self.height);
+                    closest = Some((t, vec3!(p[X], p[Y], 0.0) / self.radius, uv));
+                    break;
+                }
+            }
+        }
+
+        // The caps, at z = 0 and z = height.
+        if self.capped && direction[Z] != 0.0 {
+            for &(z, normal) in &[(0.0, -1.0), (self.height, 1.0)] {
+                let t = (z - origin[Z]) / direction[Z];
+                let limit = closest.map_or(t_max, |(closest, _, _)| closest);
+                let p = origin + t * direction;
+                if t > t_min && t < limit && p[X] * p[X] + p[Y] * p[Y] <= self.radius * self.radius
+                {
+                    let uv = (
+                        0.5 + 0.5 * p[X] / self.radius,
+                        0.5 + 0.5 * p[Y] / self.radius,
+                    );
+                    closest = Some((t, vec3!(0.0, 0.0, normal), uv));
+                }
+            }
+        }
+
+        let (t, normal, uv) = closest?;
+        Some(HitRecord::new(
+            ray,
+            t,
+            ray.at(t),
+            self.basis.local(&normal),
+            self.material.clone(),
+            uv,
+        ))
+    }
+
+    fn bounding_box(&self, _t0: f32, _t1: f32) -> Option<AABB> {
+        Some(AABB::surrounding_box(
+            circle_bounding_box(self.base, self.basis.w, self.radius),
+            circle_bounding_box(self.top(), self.basis.w, self.radius),
+        ))
+    }
+
+    fn pdf_value(&self, origin: &Vec3, v: &Vec3) -> f32 {
+        area_pdf_value(self, self.area(), origin, v)
+    }
+
+    fn random(&self, origin: &Vec3, (u, v): (f32, f32)) -> Vec3 {
+        // Pick the side or one of the caps by their share of the area, then
+        // reuse what's left of `u` to pick a point on it.
+        let side = self.side_area() / self.area();
+        let point = if u < side {
+            let phi = 2.0 * PI * u / side;
+            vec3!(
+                self.radius * phi.cos(),
+                self.radius * phi.sin(),
+                v * self.height
+            )
+        } else {
+            let cap = (1.0 - side) / 2.0;
+            let scaled = (u - side) / cap;
+            let (z, u) = if scaled < 1.0 {
+                (0.0, scaled)
+            } else {
+                (self.height, scaled - 1.0)
+            };
+            Vec3::in_unit_disk((u.min(1.0), v)) * self.radius + vec3!(0.0, 0.0, z)
+        };
+
+        self.base + self.basis.local(&point) - *origin
+    }
+
+    fn box_clone(&self) -> Box<dyn Hittable> {
+        Box::new(self.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::texture;
+
+    fn cylinder() -> Cylinder {
+        Cylinder::new(
+            vec3!(0.0, 0.0, 0.0),
+            vec3!(0.0, 2.0, 0.0),
+            1.0,
+            Material::lambertian(texture::constant(Vec3::from(0.5))),
+        )
+    }
+
+    #[test]
+    fn side_and_caps_are_hit() {
+        let side = Ray::new(vec3!(-5.0, 1.0, 0.0), vec3!(1.0, 0.0, 0.0), 0.0);
+        let rec = cylinder().hit(&side, 0.001, f32::MAX).unwrap();
+        assert!((rec.hit_point - vec3!(-1.0, 1.0, 0.0)).length() < 1e-5);
+        assert!((rec.normal - vec3!(-1.0, 0.0, 0.0)).length() < 1e-5);
+
+        let top = Ray::new(vec3!(0.5, 5.0, 0.0), vec3!(0.0, -1.0, 0.0), 0.0);
+        let rec = cylinder().hit(&top, 0.001, f32::MAX).unwrap();
+        assert!((rec.hit_point - vec3!(0.5, 2.0, 0.0)).length() < 1e-5);
+        assert!((rec.normal - vec3!(0.0, 1.0, 0.0)).length() < 1e-5);
+
+        // Without caps, the ray goes down the pipe and out of the bottom.
+        assert!(cylinder().uncapped().hit(&top, 0.001, f32::MAX).is_none());
+    }
+
+    #[test]
+    fn random_points_are_on_the_surface() {
+        let cylinder = cylinder();
+        let origin = vec3!(3.0, 1.0, 0.0);
+        for i in 0..100 {
+            let sample = (i as f32 / 100.0, (i * 37 % 100) as f32 / 100.0);
+            let p = origin + cylinder.random(&origin, sample);
+
+            let on_side = ((p[X] * p[X] + p[Z] * p[Z]).sqrt() - 1.0).abs() < 1e-4;
+            let on_cap = p[Y].abs() < 1e-4 || (p[Y] - 2.0).abs() < 1e-4;
+            assert!(on_side || on_cap, "{:?} isn't on the cylinder", p);
+        }
+    }
+}
@@ -0,0 +1,113 @@
+//! Flat, round disks, at any angle.
+
+use crate::{
+    aabb::AABB,
+    hittable::{area_pdf_value, HitRecord, Hittable},
+    material::Material,
+    onb::ONB,
+    ray::Ray,
+    vec3,
+    vec3::{Axis, Axis::*, Vec3},
+};
+use std::sync::Arc;
+
+/// A disk. The front face is the side that `normal` points to.
+#[derive(Debug, Clone)]
+pub struct Disk {
+    pub center: Vec3,
+    pub radius: f32,
+    /// The disk lies in the plane of `basis.u` and `basis.v`, and `basis.w`
+    /// is the normal.
+    basis: ONB,
+    pub material: Arc<Material>,
+}
+
+impl Disk {
+    /// Create a new disk, facing towards `normal`. `normal` doesn't need to
+    /// be a unit vector.
+    pub fn new(center: Vec3, normal: Vec3, radius: f32, material: Material) -> Self {
+        Self {
+            center,
+            radius,
+            basis: ONB::build_from_w(normal),
+            material: Arc::new(material),
+        }
+    }
+
+    /// The disk's unit normal vector.
+    pub fn normal(&self) -> Vec3 {
+        self.basis.w
+    }
+
+    /// The area of the disk.
+    pub fn area(&self) -> f32 {
+        std::f32::consts::PI * self.radius * self.radius
+    }
+}
+
+/// The bounding box of a circle around `center`, facing `normal` (which
+/// should be a unit vector). Along each axis, the circle reaches out as far as
+/// its radius times the sine of the angle between the axis and the normal.
+pub(crate) fn circle_bounding_box(center: Vec3, normal: Vec3, radius: f32) -> AABB {
+    let extent = |axis: Axis| radius * (1.0 - normal[axis] * normal[axis]).max(0.0).sqrt();
+    let extent = vec3!(extent(X), extent(Y), extent(Z));
+
+    AABB::new(center - extent, center + extent)
+}
+
+impl Hittable for Disk {
+    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
+        let normal = self.basis.w;
+        let denominator = ray.direction.dot(&normal);
+        // Rays running parallel to the disk never hit it.
+        if denominator.abs() < 1e-8 {
+            return None;
+        }
+
+        let t = (self.center - ray.origin).dot(&normal) / denominator;
+        if t < t_min || t > t_max {
+            return None;
+        }
+
+        let hit_point = ray.at(t);
+        let local = self.basis.coords(&(hit_point - self.center));
+        if local[X] * local[X] + local[Y] * local[Y] > self.radius * self.radius {
+            return None;
+        }
+
+        // Lay textures flat across the disk, like on a square around it.
+        let uv = (
+            0.5 + 0.5 * local[X] / self.radius,
+            0.5 + 0.5 * local[Y] / self.radius,
+        );
+
+        Some(HitRecord::new(
+            ray,
+            t,
+            hit_point,
+            normal,
+            self.material.clone(),
+            uv,
+        ))
+    }
+
+    fn bounding_box(&self, _t0: f32, _t1: f32) -> Option<AABB> {
+        // A disk lined up with the axes would have a flat box, so pad it.
+        let bbox = circle_bounding_box(self.center, self.basis.w, self.radius);
+        let padding = Vec3::from(0.0001);
+        Some(AABB::new(bbox.min - padding, bbox.max + padding))
+    }
+
+    fn pdf_value(&self, origin: &Vec3, v: &Vec3) -> f32 {
+        area_pdf_value(self, self.area(), origin, v)
+    }
+
+    fn random(&self, origin: &Vec3, sample: (f32, f32)) -> Vec3 {
+        let point = Vec3::in_unit_disk(sample) * self.radius;
+        self.center + self.basis.local(&point) - *origin
+    }
+
+    fn box_clone(&self) -> Box<dyn Hittable> {
+        Box::new(self.clone())
+    }
+}
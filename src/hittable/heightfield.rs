@@ -0,0 +1,421 @@
+//! Terrain, made from a grid of heights.
+//!
+//! The grid is laid out flat along the X and Z axes, with each height lifting
+//! its point of the grid up along Y. Each square cell between four points is
+//! split into two triangles. Rays walk through the cells they pass over in
+//! order (like a line being drawn on a grid of pixels), and only test the
+//! triangles in cells whose heights overlap the part of the ray above them.
+//! So the first hit that's found is the closest one.
+
+use crate::{
+    aabb::AABB,
+    hittable::{HitRecord, Hittable},
+    material::Material,
+    ray::Ray,
+    vec3,
+    vec3::{Axis::*, Vec3},
+};
+use image as i;
+use std::sync::Arc;
+
+/// A heightfield. The front face is the top.
+#[derive(Debug, Clone)]
+pub struct Heightfield {
+    /// The corner of the grid with the smallest X and Z, at height 0.
+    pub corner: Vec3,
+    /// How far the grid reaches along X and Z, and how high a height of 1
+    /// is.
+    pub size: Vec3,
+    /// The number of points along X.
+    columns: usize,
+    /// The number of points along Z.
+    rows: usize,
+    /// The points of the grid (in world space), row by row.
+    points: Arc<Vec<Vec3>>,
+    /// The normals at each point, for smooth shading.
+    normals: Arc<Vec<Vec3>>,
+    /// The lowest and highest point of each cell, row by row.
+    cell_heights: Arc<Vec<(f32, f32)>>,
+    bbox: AABB,
+    pub material: Arc<Material>,
+}
+
+impl Heightfield {
+    /// Create a new heightfield from a grid of `heights`, given row by row,
+    /// with `columns` heights per row. Row 0 is at the smallest Z. The grid
+    /// is stretched out to cover `size` along X and Z, and each height is
+    /// multiplied by the Y component of `size`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if there's less than 2 rows or columns, or if the number of
+    /// heights isn't a multiple of `columns`.
+    pub fn new(
+        heights: &[f32],
+        columns: usize,
+        corner: Vec3,
+        size: Vec3,
+        material: Material,
+    ) -> Self {
+        assert!(
+            columns >= 2 && heights.len().is_multiple_of(columns) && heights.len() / columns >= 2,
+            "a heightfield needs a grid of at least 2x2 heights"
+        );
+        let rows = heights.len() / columns;
+
+        let spacing = vec3!(
+            size[X] / (columns - 1) as f32,
+            size[Y],
+            size[Z] / (rows - 1) as f32
+        );
+        let points: Vec<Vec3> = (0..rows)
+            .flat_map(|row| (0..columns).map(move |column| (row, column)))
+            .map(|(row, column)| {
+                corner
+                    + vec3!(
+                        column as f32 * spacing[X],
+                        heights[row * columns + column] * spacing[Y],
+                        row as f32 * spacing[Z]
+                    )
+            })
+            .collect();
+
+        // The slope at each point, from its neighbours on either side (or
+        // just one side, along the edges).
+        let height = |row: usize, column: usize| points[row * columns + column][Y];
+        let normals = (0..rows)
+            .flat_map(|row| (0..columns).map(move |column| (row, column)))
+            .map(|(row, column)| {
+                let (left, right) = (column.saturating_sub(1), (column + 1).min(columns - 1));
+                let (back, front) = (row.saturating_sub(1), (row + 1).min(rows - 1));
+                let dx =
+                    (height(row, right) - height(row, left)) / ((right - left) as f32 * spacing[X]);
+                let dz = (height(front, column) - height(back, column))
+                    / ((front - back) as f32 * spacing[Z]);
+
+                vec3!(-dx, 1.0, -dz).unit_vector()
+            })
+            .collect();
+
+        let cell_heights = (0..rows - 1)
+            .flat_map(|row| (0..columns - 1).map(move |column| (row, column)))
+            .map(|(row, column)| {
+                let corners = [
+                    height(row, column),
+                    height(row, column + 1),
+                    height(row + 1, column),
+                    height(row + 1, column + 1),
+                ];
+                corners
+                    .iter()
+                    .fold((f32::INFINITY, f32::NEG_INFINITY), |(low, high), &h| {
+                        (low.min(h), high.max(h))
+                    })
+            })
+            .collect::<Vec<_>>();
+
+        let (low, high) = cell_heights.iter().fold(
+            (f32::INFINITY, f32::NEG_INFINITY),
+            |(low, high), &(l, h)| (low.min(l), high.max(h)),
+        );
+        // Pad the box a little, in case the whole heightfield is flat.
+        let bbox = AABB::new(
+            vec3!(corner[X], low - 0.0001, corner[Z]),
+            vec3!(corner[X] + size[X], high + 0.0001, corner[Z] + size[Z]),
+        );
+
+        Self {
+            corner,
+            size,
+            columns,
+            rows,
+            points: Arc::new(points),
+            normals: Arc::new(normals),
+            cell_heights: Arc::new(cell_heights),
+            bbox,
+            material: Arc::new(material),
+        }
+    }
+
+    /// Create a new heightfield from a greyscale image (colour images are
+    /// turned into greyscale first). Each pixel is a point, with black at
+    /// height 0 and white at height `size[Y]`. The top row of the image is at
+    /// the smallest Z, so that the same image used as a texture lines up with
+    /// the heights.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the image is less than 2x2 pixels.
+    pub fn from_image(img: i::DynamicImage, corner: Vec3, size: Vec3, material: Material) -> Self {
+        // Keep all of the precision of 16-bit height maps.
+        let (columns, heights) = match img {
+            i::DynamicImage::ImageLuma16(img) => (
+                img.width() as usize,
+                img.pixels()
+                    .map(|pixel| f32::from(pixel.0[0]) / f32::from(u16::MAX))
+                    .collect::<Vec<_>>(),
+            ),
+            img => {
+                let img = img.into_luma();
+                (
+                    img.width() as usize,
+                    img.pixels()
+                        .map(|pixel| f32::from(pixel.0[0]) / f32::from(u8::MAX))
+                        .collect(),
+                )
+            }
+        };
+
+        Self::new(&heights, columns, corner, size, material)
+    }
+
+    /// Test the two triangles of a cell, returning the closer hit.
+    fn hit_cell(
+        &self,
+        ray: &Ray,
+        row: usize,
+        column: usize,
+        t_min: f32,
+        t_max: f32,
+    ) -> Option<HitRecord> {
+        let index = row * self.columns + column;
+        let corners = [
+            index,
+            index + self.columns,
+            index + 1,
+            index + self.columns + 1,
+        ];
+
+        // Each triangle's corners go counterclockwise when looking down on
+        // it, so that the triangle faces up.
+        let mut closest: Option<(f32, Vec3, Vec3)> = None;
+        for &[a, b, c] in &[
+            [corners[0], corners[1], corners[2]],
+            [corners[3], corners[2], corners[1]],
+        ] {
+            let limit = closest.map_or(t_max, |(t, _, _)| t);
+            if let Some((t, weights)) = self.hit_triangle(ray, [a, b, c], t_min, limit) {
+                let outward_normal = (self.points[b] - self.points[a])
+                    .cross(&(self.points[c] - self.points[a]))
+                    .unit_vector();
+                let normal = (weights[0] * self.normals[a]
+                    + weights[1] * self.normals[b]
+                    + weights[2] * self.normals[c])
+                    .unit_vector();
+                closest = Some((t, outward_normal, normal));
+            }
+        }
+
+        let (t, outward_normal, normal) = closest?;
+        let hit_point = ray.at(t);
+        let uv = (
+            (hit_point[X] - self.corner[X]) / self.size[X],
+            1.0 - (hit_point[Z] - self.corner[Z]) / self.size[Z],
+        );
+
+        let mut rec = HitRecord::new(ray, t, hit_point, outward_normal, self.material.clone(), uv);
+        rec.normal = if rec.front_face { normal } else { -normal };
+
+        Some(rec)
+    }
+
+    /// The Möller–Trumbore test, like for a `Triangle`. Returns `t` and the
+    /// barycentric weights of the corners.
+    fn hit_triangle(
+        &self,
+        ray: &Ray,
+        [a, b, c]: [usize; 3],
+        t_min: f32,
+        t_max: f32,
+    ) -> Option<(f32, [f32; 3])> {
+        let a = self.points[a];
+        let edge1 = self.points[b] - a;
+        let edge2 = self.points[c] - a;
+        let p = ray.direction.cross(&edge2);
+        let determinant = edge1.dot(&p);
+        if determinant.abs() < 1e-12 {
+            return None;
+        }
+
+        let inverse_determinant = 1.0 / determinant;
+        let s = ray.origin - a;
+        let u = s.dot(&p) * inverse_determinant;
+        if !(0.0..=1.0).contains(&u) {
+            return None;
+        }
+
+        let q = s.cross(&edge1);
+        let v = ray.direction.dot(&q) * inverse_determinant;
+        if v < 0.0 || u + v > 1.0 {
+            return None;
+        }
+
+        let t = edge2.dot(&q) * inverse_determinant;
+        if t < t_min || t > t_max {
+            return None;
+        }
+
+        Some((t, [1.0 - u - v, u, v]))
+    }
+}
+
+impl Hittable for Heightfield {
+    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
+        let (start, end) = self.bbox.clip(ray, t_min, t_max)?;
+
+        // Find the cell that the ray starts over, in grid coordinates.
+        let cells = [self.columns - 1, self.rows - 1];
+        let axes = [X, Z];
+        let cell_size = [
+            self.size[X] / cells[0] as f32,
+            self.size[Z] / cells[1] as f32,
+        ];
+        let entry = ray.at(start);
+        let mut cell = [0; 2];
+        let mut step = [0isize; 2];
+        let mut next_t = [f32::INFINITY; 2];
+        let mut delta_t = [f32::INFINITY; 2];
+        for i in 0..2 {
+            let axis = axes[i];
+            let offset = (entry[axis] - self.corner[axis]) / cell_size[i];
+            cell[i] = (offset.max(0.0) as usize).min(cells[i] - 1);
+
+            // How far along the ray the next cell boundary is, and how far
+            // apart the boundaries are.
+            let direction = ray.direction[axis];
+            if direction > 0.0 {
+                step[i] = 1;
+                let boundary = self.corner[axis] + (cell[i] + 1) as f32 * cell_size[i];
+                next_t[i] = (boundary - ray.origin[axis]) / direction;
+                delta_t[i] = cell_size[i] / direction;
+            } else if direction < 0.0 {
+                step[i] = -1;
+                let boundary = self.corner[axis] + cell[i] as f32 * cell_size[i];
+                next_t[i] = (boundary - ray.origin[axis]) / direction;
+                delta_t[i] = -cell_size[i] / direction;
+            }
+        }
+
+        let mut cell_start = start;
+        loop {
+            let cell_end = next_t[0].min(next_t[1]).min(end);
+
+            // Only bother with the triangles if the ray is at the right
+            // height over this cell.
+            let (low, high) = self.cell_heights[cell[1] * cells[0] + cell[0]];
+            let (y0, y1) = (ray.at(cell_start)[Y], ray.at(cell_end)[Y]);
+            if y0.min(y1) <= high + 0.0001 && y0.max(y1) >= low - 0.0001 {
+                if let Some(rec) = self.hit_cell(ray, cell[1], cell[0], t_min, t_max) {
+                    return Some(rec);
+                }
+            }
+
+            if cell_end >= end {
+                return None;
+            }
+
+            // Move to the next cell along whichever axis has the closer
+            // boundary.
+            let i = if next_t[0] < next_t[1] { 0 } else { 1 };
+            let moved = cell[i] as isize + step[i];
+            if moved < 0 || moved >= cells[i] as isize {
+                return None;
+            }
+            cell[i] = moved as usize;
+            cell_start = next_t[i];
+            next_t[i] += delta_t[i];
+        }
+    }
+
+    fn bounding_box(&self, _t0: f32, _t1: f32) -> Option<AABB> {
+        Some(self.bbox)
+    }
+
+    fn box_clone(&self) -> Box<dyn Hittable> {
+        Box::new(self.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        hittable::{triangle::Triangle, world::World},
+        texture,
+    };
+    use rand::prelude::*;
+    use rand_chacha::ChaCha8Rng;
+
+    fn material() -> Material {
+        Material::lambertian(texture::constant(Vec3::from(0.5)))
+    }
+
+    #[test]
+    fn finds_the_same_hits_as_its_triangles() {
+        let mut rng = ChaCha8Rng::seed_from_u64(21);
+        let (columns, rows) = (9, 7);
+        let heights: Vec<f32> = (0..columns * rows).map(|_| rng.gen()).collect();
+        let field = Heightfield::new(
+            &heights,
+            columns,
+            vec3!(-4.0, 0.0, -3.0),
+            vec3!(8.0, 2.0, 6.0),
+            material(),
+        );
+
+        let triangles = World::new(
+            (0..rows - 1)
+                .flat_map(|row| (0..columns - 1).map(move |column| (row, column)))
+                .flat_map(|(row, column)| {
+                    let index = row * columns + column;
+                    let [a, b, c, d] = [index, index + columns, index + 1, index + columns + 1];
+                    let p = &field.points;
+                    vec![
+                        Box::new(Triangle::new(p[a], p[b], p[c], material())) as Box<dyn Hittable>,
+                        Box::new(Triangle::new(p[d], p[c], p[b], material())),
+                    ]
+                })
+                .collect(),
+        );
+
+        for _ in 0..2000 {
+            let ray = Ray::new(
+                Vec3::random_range(&mut rng, -6.0, 6.0),
+                Vec3::random_in_unit_sphere(&mut rng),
+                0.0,
+            );
+            let expected = triangles.hit(&ray, 0.001, f32::MAX);
+            let got = field.hit(&ray, 0.001, f32::MAX);
+
+            match (expected, got) {
+                (None, None) => (),
+                (Some(expected), Some(got)) => {
+                    assert!((expected.t - got.t).abs() < 1e-4);
+                    assert_eq!(expected.front_face, got.front_face);
+                }
+                (expected, got) => panic!("{:?} != {:?}", expected, got),
+            }
+        }
+    }
+
+    #[test]
+    fn slopes_have_smooth_normals() {
+        // A ramp going up along X, with heights 0, 1, 2.
+        let field = Heightfield::new(
+            &[0.0, 1.0, 2.0, 0.0, 1.0, 2.0],
+            3,
+            vec3!(),
+            vec3!(2.0, 1.0, 1.0),
+            material(),
+        );
+
+        let ray = Ray::new(vec3!(0.5, 10.0, 0.5), vec3!(0.0, -1.0, 0.0), 0.0);
+        let rec = field.hit(&ray, 0.001, f32::MAX).unwrap();
+
+        assert!((rec.hit_point - vec3!(0.5, 0.5, 0.5)).length() < 1e-5);
+        assert!((rec.normal - vec3!(-1.0, 1.0, 0.0).unit_vector()).length() < 1e-5);
+        assert!(rec.front_face);
+        assert!((rec.uv.0 - 0.25).abs() < 1e-5);
+        assert!((rec.uv.1 - 0.5).abs() < 1e-5);
+    }
+}
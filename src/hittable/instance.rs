@@ -0,0 +1,188 @@
+//! Instancing: placing many copies of one piece of geometry around a scene
+//! without copying the geometry itself.
+//!
+//! An `Instance` holds its geometry through an `Arc`, so cloning it (like
+//! `box_clone` does) or making thousands of them only costs a transform each.
+//! Each instance can also swap out the material of whatever it hits, so the
+//! copies don't all have to look the same.
+
+use crate::{
+    aabb::AABB,
+    hittable::{transform::Placement, HitRecord, Hittable},
+    material::Material,
+    matrix::Matrix4,
+    ray::Ray,
+    vec3::Vec3,
+};
+use std::sync::Arc;
+
+/// One placement of some shared geometry.
+#[derive(Clone, Debug)]
+pub struct Instance {
+    geometry: Arc<dyn Hittable>,
+    placement: Placement,
+    /// Replaces the geometry's own materials, if set.
+    material: Option<Arc<Material>>,
+}
+
+impl Instance {
+    /// Place some shared geometry in the world by transforming it by
+    /// `matrix`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `matrix` can't be inverted, like a scaling by 0 along some
+    /// axis.
+    ///
+    /// # Usage
+    ///
+    /// ```
+    /// # use weekend_tracer_rs::{
+    /// #     hittable::{block::Block, instance::Instance, Hittable},
+    /// #     material::Material, matrix::Matrix4, texture, vec3, vec3::Vec3,
+    /// # };
+    /// # use std::sync::Arc;
+    /// let white = Material::lambertian(texture::constant(vec3!(0.73, 0.73, 0.73)));
+    /// let red = Material::lambertian(texture::constant(vec3!(0.65, 0.05, 0.05)));
+    /// let cube: Arc<dyn Hittable> = Arc::new(Block::new(vec3!(), vec3!(1.0, 1.0, 1.0), white));
+    ///
+    /// let copies: Vec<Instance> = (0..1000)
+    ///     .map(|i| Instance::new(cube.clone(), Matrix4::translation(vec3!(2.0 * i as f32, 0.0, 0.0))))
+    ///     .collect();
+    /// let red_copy = Instance::new(cube, Matrix4::identity()).with_material(red);
+    /// ```
+    pub fn new(geometry: Arc<dyn Hittable>, matrix: Matrix4) -> Self {
+        Self {
+            placement: Placement::new(
+                geometry.as_ref(),
+                matrix,
+                "can't instance an object with a matrix that has no inverse",
+            ),
+            geometry,
+            material: None,
+        }
+    }
+
+    /// Use `material` for everything this instance's geometry hits, instead of
+    /// the geometry's own materials.
+    pub fn with_material(mut self, material: Material) -> Self {
+        self.material = Some(Arc::new(material));
+        self
+    }
+
+    /// The matrix that takes the geometry into world space.
+    pub fn matrix(&self) -> Matrix4 {
+        self.placement.matrix
+    }
+}
+
+impl Hittable for Instance {
+    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
+        let mut rec = self
+            .placement
+            .hit(self.geometry.as_ref(), ray, t_min, t_max)?;
+        if let Some(material) = &self.material {
+            rec.material = material.clone();
+        }
+
+        Some(rec)
+    }
+
+    fn bounding_box(&self, t0: f32, t1: f32) -> Option<AABB> {
+        self.placement.bounding_box(self.geometry.as_ref(), t0, t1)
+    }
+
+    fn box_clone(&self) -> Box<dyn Hittable> {
+        Box::new(self.clone())
+    }
+
+    fn pdf_value(&self, origin: &Vec3, v: &Vec3) -> f32 {
+        self.placement.pdf_value(self.geometry.as_ref(), origin, v)
+    }
+
+    fn random(&self, origin: &Vec3, sample: (f32, f32)) -> Vec3 {
+        self.placement
+            .random(self.geometry.as_ref(), origin, sample)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        hittable::{block::Block, quad::Quad},
+        texture, vec3,
+    };
+    use rand::prelude::*;
+    use rand_chacha::ChaCha8Rng;
+
+    fn lambertian(albedo: f32) -> Material {
+        Material::lambertian(texture::constant(Vec3::from(albedo)))
+    }
+
+    #[test]
+    fn copies_share_geometry_and_can_override_materials() {
+        let cube: Arc<dyn Hittable> =
+            Arc::new(Block::new(vec3!(), vec3!(1.0, 1.0, 1.0), lambertian(0.5)));
+        let plain = Instance::new(cube.clone(), Matrix4::translation(vec3!(0.0, 0.0, -5.0)));
+        let tinted = Instance::new(cube.clone(), Matrix4::scaling(vec3!(1.0, 1.0, 2.0)))
+            .with_material(lambertian(0.25));
+        assert_eq!(Arc::strong_count(&cube), 3);
+
+        let ray = Ray::new(vec3!(0.5, 0.5, 10.0), vec3!(0.0, 0.0, -1.0), 0.0);
+
+        let rec = plain.hit(&ray, 0.001, f32::MAX).unwrap();
+        assert!((rec.t - 14.0).abs() < 1e-4);
+        assert!(matches!(*rec.material, Material::Lambertian(_)));
+        assert!(!Arc::ptr_eq(
+            &rec.material,
+            tinted.material.as_ref().unwrap()
+        ));
+
+        let rec = tinted.hit(&ray, 0.001, f32::MAX).unwrap();
+        assert!((rec.t - 8.0).abs() < 1e-4);
+        assert!((rec.normal - vec3!(0.0, 0.0, 1.0)).length() < 1e-5);
+        assert!(Arc::ptr_eq(
+            &rec.material,
+            tinted.material.as_ref().unwrap()
+        ));
+
+        let bbox = tinted.bounding_box(0.0, 1.0).unwrap();
+        assert_eq!(bbox.max, vec3!(1.0, 1.0, 2.0));
+    }
+
+    #[test]
+    fn instanced_lights_can_be_sampled() {
+        let quad: Arc<dyn Hittable> = Arc::new(Quad::new(
+            vec3!(),
+            vec3!(1.0, 0.0, 0.0),
+            vec3!(0.0, 0.0, 2.0),
+            lambertian(0.5),
+        ));
+        let light = Instance::new(
+            quad,
+            Matrix4::translation(vec3!(0.0, 2.0, 0.0))
+                * Matrix4::rotation(vec3!(1.0, 0.0, 1.0), 40.0)
+                * Matrix4::scaling(vec3!(1.5, 1.0, 0.5)),
+        );
+        let origin = vec3!(3.0, -1.0, 2.0);
+
+        let mut rng = ChaCha8Rng::seed_from_u64(14);
+        let samples = 200_000;
+        let total: f32 = (0..samples)
+            .map(|_| {
+                let direction = Vec3::on_unit_sphere((rng.gen(), rng.gen()));
+                light.pdf_value(&origin, &direction)
+            })
+            .sum();
+        let integral = total * 4.0 * std::f32::consts::PI / samples as f32;
+        assert!((integral - 1.0).abs() < 0.05, "{}", integral);
+
+        for _ in 0..100 {
+            let direction = light.random(&origin, (rng.gen(), rng.gen()));
+            assert!(light
+                .hit(&Ray::new(origin, direction, 0.0), 0.001, f32::MAX)
+                .is_some());
+        }
+    }
+}
@@ -2,16 +2,29 @@
 
 pub mod aa_rect;
 pub mod block;
+pub mod cone;
 pub mod constant_medium;
+pub mod csg;
+pub mod curve;
+pub mod cylinder;
+pub mod disk;
 pub mod flip_face;
+pub mod heightfield;
+pub mod instance;
 pub mod moving_sphere;
-pub mod rotate;
+pub mod plane;
+pub mod quad;
+pub mod sdf;
 pub mod sphere;
-pub mod translate;
+pub mod torus;
+pub mod transform;
+pub mod triangle;
 pub mod world;
 
 use crate::aabb::AABB;
 use crate::material::Material;
+use crate::matrix::Matrix4;
+use crate::quaternion::{EulerOrder, Quaternion};
 use crate::ray::Ray;
 use crate::vec3::{Axis, Axis::*, Vec3};
 use std::sync::Arc;
@@ -33,6 +46,34 @@ pub fn get_sphere_uv(point: Vec3) -> UVCoord {
     )
 }
 
+/// Gets the value of the PDF for a direction `v` from `origin`, for an object
+/// whose `random` picks points evenly spread over its surface, with a total
+/// area of `area`.
+///
+/// A ray can cross a curved surface more than once, and each of those points
+/// could have been picked, so the PDF adds up every crossing (up to four of
+/// them, which is as many as a torus has).
+pub fn area_pdf_value(object: &dyn Hittable, area: f32, origin: &Vec3, v: &Vec3) -> f32 {
+    let ray = Ray::new(*origin, *v, 0.0);
+    let mut t_min = 0.001;
+    let mut pdf = 0.0;
+
+    for _ in 0..4 {
+        let rec = match object.hit(&ray, t_min, f32::INFINITY) {
+            Some(rec) => rec,
+            None => break,
+        };
+
+        let distance_squared = rec.t * rec.t * v.length_squared();
+        let cosine = (v.dot(&rec.normal) / v.length()).abs();
+        pdf += distance_squared / (cosine * area);
+        // Step past this crossing by a small distance, not a small `t`.
+        t_min = rec.t + 0.001 / v.length();
+    }
+
+    pdf
+}
+
 /// A utility struct for recording that a ray hit a point on a `Hittable` object.
 #[derive(Clone, Debug)]
 pub struct HitRecord {
@@ -48,6 +89,9 @@ pub struct HitRecord {
     pub material: Arc<Material>,
     /// The (u, v) surface coordinates of the hit point.
     pub uv: UVCoord,
+    /// The direction that the surface runs along, for surfaces that have one
+    /// (like the strands of a `Curve`). A unit vector.
+    pub tangent: Option<Vec3>,
 }
 
 impl HitRecord {
@@ -77,6 +121,7 @@ impl HitRecord {
             front_face,
             material,
             uv,
+            tangent: None,
         }
     }
 }
@@ -113,15 +158,46 @@ pub trait Hittable: Send + Sync + core::fmt::Debug {
         flip_face::FlipFace::new(self.box_clone())
     }
 
-    /// Translates the object by some offset using a `Translate` instance, cloning it.
-    fn translate(&self, displacement: Vec3) -> translate::Translate {
-        translate::Translate::new(self.box_clone(), displacement)
+    /// Transforms the object by some matrix using a `Transform` instance,
+    /// cloning it. Transforming a `Transform` folds the matrices together
+    /// instead of wrapping it again.
+    fn transform(&self, matrix: Matrix4) -> transform::Transform {
+        transform::Transform::new(self.box_clone(), matrix)
+    }
+
+    /// Translates the object by some offset, cloning it.
+    fn translate(&self, displacement: Vec3) -> transform::Transform {
+        self.transform(Matrix4::translation(displacement))
     }
 
     /// Rotates the object by θ degrees counterclockwise about some `vec3::Axis`, cloning it.
-    /// Uses a `Rotate` instance.
-    fn rotate(&self, axis: Axis, angle: f32) -> rotate::Rotate {
-        rotate::Rotate::new(self.box_clone(), axis, angle)
+    fn rotate(&self, axis: Axis, angle: f32) -> transform::Transform {
+        let mut direction = Vec3::default();
+        direction[axis] = 1.0;
+        self.rotate_about(direction, angle)
+    }
+
+    /// Rotates the object by θ degrees counterclockwise about some arbitrary
+    /// axis through the origin, cloning it.
+    fn rotate_about(&self, axis: Vec3, angle: f32) -> transform::Transform {
+        self.transform(Matrix4::rotation(axis, angle))
+    }
+
+    /// Rotates the object by a quaternion, cloning it.
+    fn rotate_quaternion(&self, rotation: Quaternion) -> transform::Transform {
+        self.transform(rotation.into())
+    }
+
+    /// Rotates the object by Euler angles (in degrees, counterclockwise)
+    /// about the X, Y and Z axes, in some order, cloning it.
+    fn rotate_euler(&self, order: EulerOrder, angles: Vec3) -> transform::Transform {
+        self.rotate_quaternion(Quaternion::from_euler(order, angles))
+    }
+
+    /// Scales the object about the origin by the matching component of
+    /// `factor` along each axis, cloning it.
+    fn scale(&self, factor: Vec3) -> transform::Transform {
+        self.transform(Matrix4::scaling(factor))
     }
 
     /// Get a value of the hittable's PDF given some origin and some vector.
@@ -129,8 +205,9 @@ pub trait Hittable: Send + Sync + core::fmt::Debug {
         0.0
     }
 
-    /// Get a random vector for PDF use.
-    fn random(&self, _origin: &Vec3) -> Vec3 {
+    /// Get a random vector for PDF use, from a point `sample` in the unit
+    /// square.
+    fn random(&self, _origin: &Vec3, _sample: (f32, f32)) -> Vec3 {
         Vec3::new(1.0, 0.0, 0.0)
     }
 }
@@ -140,3 +217,49 @@ impl Clone for Box<dyn Hittable> {
         self.box_clone()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        hittable::{cone::Cone, cylinder::Cylinder, disk::Disk, quad::Quad, torus::Torus},
+        texture, vec3,
+    };
+    use rand::prelude::*;
+    use rand_chacha::ChaCha8Rng;
+
+    #[test]
+    fn area_light_pdfs_integrate_to_one() {
+        let material = Material::lambertian(texture::constant(Vec3::from(0.5)));
+        let axis = vec3!(1.0, 2.0, 0.5);
+        let shapes: Vec<Box<dyn Hittable>> = vec![
+            Box::new(Disk::new(vec3!(), axis, 1.0, material.clone())),
+            Box::new(Quad::new(
+                vec3!(),
+                vec3!(1.0, 0.0, 1.0),
+                vec3!(0.0, 2.0, 0.0),
+                material.clone(),
+            )),
+            Box::new(Cylinder::new(vec3!(), axis, 0.5, material.clone())),
+            Box::new(Cone::new(vec3!(), axis, 1.0, material.clone())),
+            Box::new(Torus::new(vec3!(), axis, 1.0, 0.3, material)),
+        ];
+        let origin = vec3!(3.0, -1.0, 2.0);
+
+        // Evenly spread directions have a PDF of 1/4π, so the average of
+        // `pdf_value / (1/4π)` over them should come out close to 1.
+        let mut rng = ChaCha8Rng::seed_from_u64(18);
+        for shape in &shapes {
+            let samples = 200_000;
+            let total: f32 = (0..samples)
+                .map(|_| {
+                    let direction = Vec3::on_unit_sphere((rng.gen(), rng.gen()));
+                    shape.pdf_value(&origin, &direction)
+                })
+                .sum();
+            let integral = total * 4.0 * std::f32::consts::PI / samples as f32;
+
+            assert!((integral - 1.0).abs() < 0.05, "{:?}: {}", shape, integral);
+        }
+    }
+}
@@ -0,0 +1,82 @@
+//! Infinite planes.
+//!
+//! A plane goes on forever, so it doesn't have a bounding box. The `BVH`
+//! keeps objects like this in a separate list that every ray gets tested
+//! against.
+
+use crate::aabb::AABB;
+use crate::hittable::{HitRecord, Hittable};
+use crate::material::Material;
+use crate::onb::ONB;
+use crate::ray::Ray;
+use crate::vec3::Vec3;
+use std::sync::Arc;
+
+/// An infinite plane. The front face is the side that `normal` points to.
+#[derive(Debug, Clone)]
+pub struct Plane {
+    pub point: Vec3,
+    /// The (u, v) surface coordinates go along `basis.u` and `basis.v`, and
+    /// `basis.w` is the normal.
+    basis: ONB,
+    pub material: Arc<Material>,
+}
+
+impl Plane {
+    /// Create a new plane through `point`, facing towards `normal`. `normal`
+    /// doesn't need to be a unit vector.
+    pub fn new(point: Vec3, normal: Vec3, material: Material) -> Self {
+        Self {
+            point,
+            basis: ONB::build_from_w(normal),
+            material: Arc::new(material),
+        }
+    }
+
+    /// The plane's unit normal vector.
+    pub fn normal(&self) -> Vec3 {
+        self.basis.w
+    }
+}
+
+impl Hittable for Plane {
+    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
+        let normal = self.basis.w;
+        let denominator = ray.direction.dot(&normal);
+        // Rays running parallel to the plane never hit it.
+        if denominator.abs() < 1e-8 {
+            return None;
+        }
+
+        let t = (self.point - ray.origin).dot(&normal) / denominator;
+        if t < t_min || t > t_max {
+            return None;
+        }
+
+        // The (u, v) coordinates repeat every unit, so that image textures
+        // tile across the plane.
+        let hit_point = ray.at(t);
+        let offset = hit_point - self.point;
+        let uv = (
+            offset.dot(&self.basis.u).rem_euclid(1.0),
+            offset.dot(&self.basis.v).rem_euclid(1.0),
+        );
+
+        Some(HitRecord::new(
+            ray,
+            t,
+            hit_point,
+            normal,
+            self.material.clone(),
+            uv,
+        ))
+    }
+
+    fn bounding_box(&self, _t0: f32, _t1: f32) -> Option<AABB> {
+        None
+    }
+
+    fn box_clone(&self) -> Box<dyn Hittable> {
+        Box::new(self.clone())
+    }
+}
@@ -0,0 +1,142 @@
+//! Parallelograms, at any angle.
+
+use crate::{
+    aabb::AABB,
+    hittable::{area_pdf_value, HitRecord, Hittable},
+    material::Material,
+    ray::Ray,
+    vec3::Vec3,
+};
+use std::sync::Arc;
+
+/// A parallelogram with one corner at `corner`, and sides along `u` and `v`.
+/// The front face is the side that `u × v` points to.
+#[derive(Debug, Clone)]
+pub struct Quad {
+    pub corner: Vec3,
+    pub u: Vec3,
+    pub v: Vec3,
+    /// `u × v`, scaled so that dotting it with a point on the plane of the
+    /// quad (relative to `corner`) gives the point's `u` and `v` coordinates
+    /// through cross products.
+    w: Vec3,
+    normal: Vec3,
+    area: f32,
+    pub material: Arc<Material>,
+}
+
+impl Quad {
+    /// Create a new parallelogram. For a rectangle, `u` and `v` should be
+    /// perpendicular.
+    pub fn new(corner: Vec3, u: Vec3, v: Vec3, material: Material) -> Self {
+        let n = u.cross(&v);
+
+        Self {
+            corner,
+            u,
+            v,
+            w: n / n.length_squared(),
+            normal: n.unit_vector(),
+            area: n.length(),
+            material: Arc::new(material),
+        }
+    }
+
+    /// The area of the parallelogram.
+    pub fn area(&self) -> f32 {
+        self.area
+    }
+}
+
+impl Hittable for Quad {
+    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
+        let denominator = ray.direction.dot(&self.normal);
+        // Rays running parallel to the quad never hit it.
+        if denominator.abs() < 1e-8 {
+            return None;
+        }
+
+        let t = (self.corner - ray.origin).dot(&self.normal) / denominator;
+        if t < t_min || t > t_max {
+            return None;
+        }
+
+        // Work out where the hit point is in terms of `u` and `v`.
+        let hit_point = ray.at(t);
+        let p = hit_point - self.corner;
+        let alpha = self.w.dot(&p.cross(&self.v));
+        let beta = self.w.dot(&self.u.cross(&p));
+        if !(0.0..=1.0).contains(&alpha) || !(0.0..=1.0).contains(&beta) {
+            return None;
+        }
+
+        Some(HitRecord::new(
+            ray,
+            t,
+            hit_point,
+            self.normal,
+            self.material.clone(),
+            (alpha, beta),
+        ))
+    }
+
+    fn bounding_box(&self, _t0: f32, _t1: f32) -> Option<AABB> {
+        let corners = [
+            self.corner,
+            self.corner + self.u,
+            self.corner + self.v,
+            self.corner + self.u + self.v,
+        ];
+
+        let mut min = corners[0];
+        let mut max = corners[0];
+        for corner in &corners[1..] {
+            for i in 0..3 {
+                min[i] = min[i].min(corner[i]);
+                max[i] = max[i].max(corner[i]);
+            }
+        }
+
+        // A quad lined up with the axes would have a flat box, so pad it.
+        let padding = Vec3::from(0.0001);
+        Some(AABB::new(min - padding, max + padding))
+    }
+
+    fn pdf_value(&self, origin: &Vec3, v: &Vec3) -> f32 {
+        area_pdf_value(self, self.area, origin, v)
+    }
+
+    fn random(&self, origin: &Vec3, (a, b): (f32, f32)) -> Vec3 {
+        self.corner + a * self.u + b * self.v - *origin
+    }
+
+    fn box_clone(&self) -> Box<dyn Hittable> {
+        Box::new(self.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{texture, vec3};
+
+    #[test]
+    fn tilted_quad_is_hit_inside_its_sides() {
+        let quad = Quad::new(
+            vec3!(0.0, 0.0, 0.0),
+            vec3!(2.0, 0.0, 2.0),
+            vec3!(0.0, 1.0, 0.0),
+            Material::lambertian(texture::constant(Vec3::from(0.5))),
+        );
+        assert!((quad.area() - 8.0_f32.sqrt()).abs() < 1e-5);
+
+        let ray = Ray::new(vec3!(1.5, 0.25, -5.0), vec3!(0.0, 0.0, 1.0), 0.0);
+        let rec = quad.hit(&ray, 0.001, f32::MAX).unwrap();
+        assert!((rec.hit_point - vec3!(1.5, 0.25, 1.5)).length() < 1e-5);
+        assert!((rec.uv.0 - 0.75).abs() < 1e-5);
+        assert!((rec.uv.1 - 0.25).abs() < 1e-5);
+
+        let outside = Ray::new(vec3!(1.5, 1.25, -5.0), vec3!(0.0, 0.0, 1.0), 0.0);
+        assert!(quad.hit(&outside, 0.001, f32::MAX).is_none());
+    }
+}
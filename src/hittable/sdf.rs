@@ -0,0 +1,364 @@
+//! Signed distance fields: shapes described by a function that gives the
+//! distance from any point to the nearest surface (negative inside).
+//!
+//! They get hit by *sphere tracing*: nothing is closer to a point than its
+//! distance, so the ray can safely step forward by that much, again and again,
+//! until it crosses the surface. The normal is the gradient of the distance
+//! function, found by sampling it around the hit point.
+//!
+//! Like textures, distance functions are just closures, and this module
+//! mostly has functions that make them. Besides simple shapes, there are
+//! combinators for putting them together (including smoothly blending them
+//! into each other), and a Mandelbulb fractal.
+
+use crate::{
+    aabb::AABB,
+    hittable::{get_sphere_uv, HitRecord, Hittable},
+    material::Material,
+    ray::Ray,
+    vec3,
+    vec3::{Axis::*, Vec3},
+};
+use std::sync::Arc;
+
+/// How close to the surface a point has to be to count as being on it.
+const SURFACE_EPSILON: f32 = 1e-4;
+
+/// How many steps a ray gets before giving up. Rays that skim along a surface
+/// without hitting it can take a lot of small steps.
+const MAX_STEPS: usize = 512;
+
+/// The function behind an `Sdf`.
+pub type DistanceFn = dyn Fn(Vec3) -> f32 + Send + Sync;
+
+/// A signed distance function. Takes in a point, and outputs how far it is
+/// from the surface of the shape: positive outside, negative inside.
+///
+/// It's fine if the distance is an underestimate (like with fractals), as
+/// long as it's never more than the real distance.
+#[derive(Clone)]
+pub struct Sdf(pub Arc<DistanceFn>);
+
+/// Allows `Sdf` to implement `Debug`.
+impl std::fmt::Debug for Sdf {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("Sdf")
+            .field(&String::from("Arc<dyn Fn(Vec3) -> f32 + Send + Sync>"))
+            .finish()
+    }
+}
+
+impl Sdf {
+    /// Wrap a distance function.
+    pub fn new<F: Fn(Vec3) -> f32 + Send + Sync + 'static>(f: F) -> Self {
+        Self(Arc::new(f))
+    }
+
+    /// The signed distance from `p` to the surface.
+    #[inline]
+    pub fn distance(&self, p: Vec3) -> f32 {
+        (self.0)(p)
+    }
+
+    /// The direction that the distance grows fastest in at `p`, which is the
+    /// outward normal on the surface. Uses four samples at the corners of a
+    /// tetrahedron around `p`.
+    pub fn gradient(&self, p: Vec3) -> Vec3 {
+        let h = SURFACE_EPSILON;
+        [
+            vec3!(1.0, -1.0, -1.0),
+            vec3!(-1.0, -1.0, 1.0),
+            vec3!(-1.0, 1.0, -1.0),
+            vec3!(1.0, 1.0, 1.0),
+        ]
+        .iter()
+        .fold(Vec3::default(), |sum, &k| {
+            sum + k * self.distance(p + k * h)
+        })
+        .unit_vector()
+    }
+}
+
+/// A sphere.
+pub fn sphere(center: Vec3, radius: f32) -> Sdf {
+    Sdf::new(move |p| (p - center).length() - radius)
+}
+
+/// A box around `center`, reaching out by `half_size` along each axis, with
+/// its edges rounded off by `rounding`.
+pub fn rounded_box(center: Vec3, half_size: Vec3, rounding: f32) -> Sdf {
+    Sdf::new(move |p| {
+        let d = p - center;
+        let q = vec3!(
+            d[X].abs() - half_size[X] + rounding,
+            d[Y].abs() - half_size[Y] + rounding,
+            d[Z].abs() - half_size[Z] + rounding
+        );
+        let outside = vec3!(q[X].max(0.0), q[Y].max(0.0), q[Z].max(0.0)).length();
+        let inside = q[X].max(q[Y]).max(q[Z]).min(0.0);
+
+        outside + inside - rounding
+    })
+}
+
+/// A torus lying flat around the Y axis through `center`.
+pub fn torus(center: Vec3, major_radius: f32, minor_radius: f32) -> Sdf {
+    Sdf::new(move |p| {
+        let d = p - center;
+        let ring = (d[X] * d[X] + d[Z] * d[Z]).sqrt() - major_radius;
+        (ring * ring + d[Y] * d[Y]).sqrt() - minor_radius
+    })
+}
+
+/// A capsule: a cylinder from `a` to `b` with rounded ends.
+pub fn capsule(a: Vec3, b: Vec3, radius: f32) -> Sdf {
+    Sdf::new(move |p| {
+        let pa = p - a;
+        let ba = b - a;
+        let h = (pa.dot(&ba) / ba.length_squared()).clamp(0.0, 1.0);
+        (pa - ba * h).length() - radius
+    })
+}
+
+/// The Mandelbulb fractal, around the origin and about 1.1 units across.
+/// The classic one has a `power` of 8. More `iterations` give more detail.
+pub fn mandelbulb(power: f32, iterations: usize) -> Sdf {
+    Sdf::new(move |p| {
+        let mut z = p;
+        let mut dr = 1.0;
+        let mut r = z.length();
+
+        for _ in 0..iterations {
+            if r > 2.0 {
+                break;
+            }
+
+            // Raise z to the power in spherical coordinates, and add p.
+            let theta = (z[Z] / r.max(f32::MIN_POSITIVE)).acos() * power;
+            let phi = z[Y].atan2(z[X]) * power;
+            dr = r.powf(power - 1.0) * power * dr + 1.0;
+
+            let zr = r.powf(power);
+            z =
+                zr * vec3!(
+                    theta.sin() * phi.cos(),
+                    theta.sin() * phi.sin(),
+                    theta.cos()
+                ) + p;
+            r = z.length();
+        }
+
+        let r = r.max(f32::MIN_POSITIVE);
+        0.5 * r.ln() * r / dr
+    })
+}
+
+/// Everything inside either shape.
+pub fn union(a: Sdf, b: Sdf) -> Sdf {
+    Sdf::new(move |p| a.distance(p).min(b.distance(p)))
+}
+
+/// Everything inside both shapes.
+pub fn intersection(a: Sdf, b: Sdf) -> Sdf {
+    Sdf::new(move |p| a.distance(p).max(b.distance(p)))
+}
+
+/// `a`, with everything inside `b` carved out of it.
+pub fn difference(a: Sdf, b: Sdf) -> Sdf {
+    Sdf::new(move |p| a.distance(p).max(-b.distance(p)))
+}
+
+/// Like `union`, but the shapes melt into each other where they're within
+/// about `k` of each other, using a polynomial smooth minimum.
+pub fn smooth_union(a: Sdf, b: Sdf, k: f32) -> Sdf {
+    Sdf::new(move |p| {
+        let (da, db) = (a.distance(p), b.distance(p));
+        let h = (0.5 + 0.5 * (db - da) / k).clamp(0.0, 1.0);
+        db + (da - db) * h - k * h * (1.0 - h)
+    })
+}
+
+/// Morphs between two shapes: `a` when `t` is 0, and `b` when it's 1.
+pub fn blend(a: Sdf, b: Sdf, t: f32) -> Sdf {
+    Sdf::new(move |p| a.distance(p) * (1.0 - t) + b.distance(p) * t)
+}
+
+/// Moves a shape by `offset`.
+pub fn translate(sdf: Sdf, offset: Vec3) -> Sdf {
+    Sdf::new(move |p| sdf.distance(p - offset))
+}
+
+/// Scales a shape about the origin by `factor`, the same along every axis.
+/// (Scaling each axis differently would stretch the distances, which breaks
+/// sphere tracing; use a `Transform` for that instead.)
+pub fn scale(sdf: Sdf, factor: f32) -> Sdf {
+    Sdf::new(move |p| sdf.distance(p / factor) * factor)
+}
+
+/// A `Hittable` shape made from a signed distance function.
+///
+/// Rays are only traced inside `bbox`, which has to hold the whole surface.
+/// The surface coordinates come from the direction of the normal, as if the
+/// shape was a sphere.
+#[derive(Debug, Clone)]
+pub struct SdfObject {
+    pub sdf: Sdf,
+    pub bbox: AABB,
+    pub material: Arc<Material>,
+}
+
+impl SdfObject {
+    /// Create a new object from a distance function, and a box around its
+    /// surface.
+    pub fn new(sdf: Sdf, bbox: AABB, material: Material) -> Self {
+        Self {
+            sdf,
+            bbox,
+            material: Arc::new(material),
+        }
+    }
+}
+
+impl Hittable for SdfObject {
+    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
+        let (start, end) = self.bbox.clip(ray, t_min, t_max)?;
+        let length = ray.direction.length();
+
+        // From outside, step along the ray until it's close enough to the
+        // surface. Rays that start inside (like refracted ones) look for the
+        // way out instead, which is where the distance changes sign. Stepping
+        // by at least `SURFACE_EPSILON` keeps them from creeping up to the
+        // surface forever.
+        let mut before = start;
+        let mut t = start;
+        let mut distance = self.sdf.distance(ray.at(t));
+        let outside = distance >= 0.0;
+        let arrived = |distance: f32| {
+            if outside {
+                distance < SURFACE_EPSILON
+            } else {
+                distance >= 0.0
+            }
+        };
+
+        let mut steps = 0;
+        while !arrived(distance) {
+            steps += 1;
+            if steps > MAX_STEPS {
+                return None;
+            }
+
+            before = t;
+            t += distance.abs().max(SURFACE_EPSILON) / length;
+            if t > end {
+                return None;
+            }
+            distance = self.sdf.distance(ray.at(t));
+        }
+
+        // If the last step went right through the surface, narrow down where
+        // the crossing is. Keep the point on the side that the ray came from,
+        // so that rays leaving the surface start on the right side of it.
+        if (distance >= 0.0) != outside {
+            let mut after = t;
+            for _ in 0..16 {
+                let middle = 0.5 * (before + after);
+                if (self.sdf.distance(ray.at(middle)) >= 0.0) == outside {
+                    before = middle;
+                } else {
+                    after = middle;
+                }
+            }
+            t = before;
+        }
+        if t <= t_min {
+            return None;
+        }
+
+        let hit_point = ray.at(t);
+        let normal = self.sdf.gradient(hit_point);
+
+        Some(HitRecord::new(
+            ray,
+            t,
+            hit_point,
+            normal,
+            self.material.clone(),
+            get_sphere_uv(normal),
+        ))
+    }
+
+    fn bounding_box(&self, _t0: f32, _t1: f32) -> Option<AABB> {
+        Some(self.bbox)
+    }
+
+    fn box_clone(&self) -> Box<dyn Hittable> {
+        Box::new(self.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{hittable::sphere::Sphere, texture};
+
+    fn material() -> Material {
+        Material::lambertian(texture::constant(Vec3::from(0.5)))
+    }
+
+    fn unit_box() -> AABB {
+        AABB::new(Vec3::from(-1.0), Vec3::from(1.0))
+    }
+
+    #[test]
+    fn traced_sphere_matches_the_analytic_one() {
+        let traced = SdfObject::new(sphere(vec3!(), 1.0), unit_box(), material());
+        let analytic = Sphere::new(vec3!(), 1.0, material());
+
+        for (origin, direction) in &[
+            (vec3!(-5.0, 0.3, 0.1), vec3!(1.0, 0.0, 0.0)),
+            (vec3!(2.0, 3.0, -4.0), vec3!(-2.0, -3.0, 4.1)),
+            // From the inside, out.
+            (vec3!(0.2, 0.0, 0.0), vec3!(0.0, 0.0, 3.0)),
+        ] {
+            let ray = Ray::new(*origin, *direction, 0.0);
+            let expected = analytic.hit(&ray, 0.001, f32::MAX).unwrap();
+            let rec = traced.hit(&ray, 0.001, f32::MAX).unwrap();
+
+            assert!((rec.hit_point - expected.hit_point).length() < 1e-3);
+            assert!((rec.normal - expected.normal).length() < 1e-3);
+            assert_eq!(rec.front_face, expected.front_face);
+        }
+
+        let miss = Ray::new(vec3!(-5.0, 1.2, 0.0), vec3!(1.0, 0.0, 0.0), 0.0);
+        assert!(traced.hit(&miss, 0.001, f32::MAX).is_none());
+    }
+
+    #[test]
+    fn smooth_union_fills_the_gap() {
+        let a = sphere(vec3!(-0.6, 0.0, 0.0), 0.5);
+        let b = sphere(vec3!(0.6, 0.0, 0.0), 0.5);
+
+        // Halfway between the spheres is outside both of them...
+        assert!(union(a.clone(), b.clone()).distance(vec3!()) > 0.0);
+        // ...but inside the blob made by melting them together.
+        assert!(smooth_union(a.clone(), b.clone(), 0.5).distance(vec3!()) < 0.0);
+        // Far away from the gap, the blend doesn't change anything.
+        let p = vec3!(-1.5, 0.0, 0.0);
+        assert!((smooth_union(a.clone(), b, 0.5).distance(p) - a.distance(p)).abs() < 1e-6);
+    }
+
+    #[test]
+    fn mandelbulb_is_hit() {
+        let bulb = SdfObject::new(
+            mandelbulb(8.0, 8),
+            AABB::new(Vec3::from(-1.2), Vec3::from(1.2)),
+            material(),
+        );
+        let ray = Ray::new(vec3!(0.0, 0.0, -3.0), vec3!(0.0, 0.0, 1.0), 0.0);
+        let rec = bulb.hit(&ray, 0.001, f32::MAX).unwrap();
+
+        assert!(rec.front_face);
+        assert!(rec.hit_point.length() > 0.5 && rec.hit_point.length() < 1.2);
+    }
+}
@@ -8,7 +8,6 @@ use crate::pdf::random_to_sphere;
 use crate::ray::Ray;
 use crate::vec3;
 use crate::vec3::Vec3;
-use rand::prelude::*;
 use std::sync::Arc;
 
 /// A sphere. Can be hit with rays.
@@ -82,7 +81,7 @@ impl Hittable for Sphere {
 
     fn pdf_value(&self, origin: &Vec3, v: &Vec3) -> f32 {
         if self
-            .hit(&Ray::new(*origin, *v, 0.0), 0.001, std::f32::MAX)
+            .hit(&Ray::new(*origin, *v, 0.0), 0.001, f32::MAX)
             .is_some()
         {
             let cos_theta_max =
@@ -95,15 +94,12 @@ impl Hittable for Sphere {
         }
     }
 
-    fn random(&self, origin: &Vec3) -> Vec3 {
-        // Not ideal, but eh...
-        let mut rng = thread_rng();
-
+    fn random(&self, origin: &Vec3, sample: (f32, f32)) -> Vec3 {
         let direction = self.center - *origin;
         let distance_squared = direction.length_squared();
         let uvw = ONB::build_from_w(direction);
 
-        uvw.local(&random_to_sphere(&mut rng, self.radius, distance_squared))
+        uvw.local(&random_to_sphere(sample, self.radius, distance_squared))
     }
 
     fn box_clone(&self) -> Box<dyn Hittable> {
@@ -0,0 +1,337 @@
+//! Tori (donuts), at any angle.
+//!
+//! A ray can cross a torus up to four times, so hitting one means solving a
+//! quartic equation. That's done in `f64` with Ferrari's method, and the roots
+//! get touched up with a few steps of Newton's method afterwards.
+
+use crate::{
+    aabb::AABB,
+    hittable::{area_pdf_value, disk::circle_bounding_box, HitRecord, Hittable},
+    material::Material,
+    onb::ONB,
+    ray::Ray,
+    vec3,
+    vec3::{Axis::*, Vec3},
+};
+use std::{f32::consts::PI, sync::Arc};
+
+/// A torus around `center`. The tube of radius `minor_radius` runs in a
+/// circle of radius `major_radius` around the axis. The front face is the
+/// outside.
+#[derive(Debug, Clone)]
+pub struct Torus {
+    pub center: Vec3,
+    pub major_radius: f32,
+    pub minor_radius: f32,
+    /// `basis.w` is the axis that the torus goes around.
+    basis: ONB,
+    pub material: Arc<Material>,
+}
+
+impl Torus {
+    /// Create a new torus, going around `axis`. `axis` doesn't need to be a
+    /// unit vector.
+    pub fn new(
+        center: Vec3,
+        axis: Vec3,
+        major_radius: f32,
+        minor_radius: f32,
+        material: Material,
+    ) -> Self {
+        Self {
+            center,
+            major_radius,
+            minor_radius,
+            basis: ONB::build_from_w(axis),
+            material: Arc::new(material),
+        }
+    }
+
+    /// The surface area of the torus.
+    pub fn area(&self) -> f32 {
+        4.0 * PI * PI * self.major_radius * self.minor_radius
+    }
+}
+
+impl Hittable for Torus {
+    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
+        // Work in the torus' own space, where it lies flat around the Z axis,
+        // with a unit direction so the roots are distances.
+        let length = ray.direction.length() as f64;
+        let origin = self.basis.coords(&(ray.origin - self.center));
+        let direction = self.basis.coords(&ray.direction);
+        let o = [origin[X] as f64, origin[Y] as f64, origin[Z] as f64];
+        let d = [
+            direction[X] as f64 / length,
+            direction[Y] as f64 / length,
+            direction[Z] as f64 / length,
+        ];
+        let (major, minor) = (self.major_radius as f64, self.minor_radius as f64);
+
+        // The quartic gets badly conditioned when the ray starts far away, so
+        // start it where it meets the sphere around the torus instead.
+        let bound = major + minor;
+        let od = o[0] * d[0] + o[1] * d[1] + o[2] * d[2];
+        let oo = o[0] * o[0] + o[1] * o[1] + o[2] * o[2];
+        let discriminant = od * od - (oo - bound * bound);
+        if discriminant <= 0.0 {
+            return None;
+        }
+        let skip = (-od - discriminant.sqrt()).max(0.0);
+        let o = [o[0] + skip * d[0], o[1] + skip * d[1], o[2] + skip * d[2]];
+
+        // (|p|² + R² - r²)² = 4R²(x² + y²), with p = o + s⋅d.
+        let od = o[0] * d[0] + o[1] * d[1] + o[2] * d[2];
+        let oo = o[0] * o[0] + o[1] * o[1] + o[2] * o[2];
+        let k = oo + major * major - minor * minor;
+        let four_r2 = 4.0 * major * major;
+        let coefficients = [
+            1.0,
+            4.0 * od,
+            2.0 * k + 4.0 * od * od - four_r2 * (d[0] * d[0] + d[1] * d[1]),
+            4.0 * od * k - 2.0 * four_r2 * (o[0] * d[0] + o[1] * d[1]),
+            k * k - four_r2 * (o[0] * o[0] + o[1] * o[1]),
+        ];
+
+        let t = solve_quartic(coefficients)
+            .into_iter()
+            .map(|s| ((s + skip) / length) as f32)
+            .filter(|&t| t > t_min && t < t_max)
+            .min_by(|a, b| a.total_cmp(b))?;
+
+        // The normal points away from the middle of the tube.
+        let hit_point = ray.at(t);
+        let p = self.basis.coords(&(hit_point - self.center));
+        let around = p[Y].atan2(p[X]);
+        let ring = vec3!(around.cos(), around.sin(), 0.0) * self.major_radius;
+        let normal = (p - ring) / self.minor_radius;
+
+        let across = p[Z].atan2((p[X] * p[X] + p[Y] * p[Y]).sqrt() - self.major_radius);
+        let uv = ((around + PI) / (2.0 * PI), (across + PI) / (2.0 * PI));
+
+        Some(HitRecord::new(
+            ray,
+            t,
+            hit_point,
+            self.basis.local(&normal),
+            self.material.clone(),
+            uv,
+        ))
+    }
+
+    fn bounding_box(&self, _t0: f32, _t1: f32) -> Option<AABB> {
+        let ring = circle_bounding_box(self.center, self.basis.w, self.major_radius);
+        let tube = Vec3::from(self.minor_radius);
+        Some(AABB::new(ring.min - tube, ring.max + tube))
+    }
+
+    fn pdf_value(&self, origin: &Vec3, v: &Vec3) -> f32 {
+        area_pdf_value(self, self.area(), origin, v)
+    }
+
+    fn random(&self, origin: &Vec3, (u, v): (f32, f32)) -> Vec3 {
+        let (major, minor) = (self.major_radius, self.minor_radius);
+        let around = 2.0 * PI * u;
+
+        // There's more surface on the outside of the tube than the inside, in
+        // proportion to R + r⋅cos(θ). So invert the CDF of that,
+        // (R⋅θ + r⋅sin(θ)) / 2πR, with Newton's method.
+        let target = 2.0 * PI * major * v;
+        let mut across = 2.0 * PI * v;
+        for _ in 0..8 {
+            let error = major * across + minor * across.sin() - target;
+            let slope = major + minor * across.cos();
+            across = (across - error / slope).clamp(0.0, 2.0 * PI);
+        }
+
+        let radius = major + minor * across.cos();
+        let point = vec3!(
+            radius * around.cos(),
+            radius * around.sin(),
+            minor * across.sin()
+        );
+
+        self.center + self.basis.local(&point) - *origin
+    }
+
+    fn box_clone(&self) -> Box<dyn Hittable> {
+        Box::new(self.clone())
+    }
+}
+
+/// Values this close to 0 count as 0 while solving polynomials.
+const EPSILON: f64 = 1e-9;
+
+/// Real roots of `a⋅x² + b⋅x + c = 0`, with `a` not 0.
+fn solve_quadratic(a: f64, b: f64, c: f64) -> Vec<f64> {
+    let discriminant = b * b - 4.0 * a * c;
+    if discriminant.abs() < EPSILON {
+        vec![-b / (2.0 * a)]
+    } else if discriminant < 0.0 {
+        vec![]
+    } else {
+        let root = discriminant.sqrt();
+        vec![(-b - root) / (2.0 * a), (-b + root) / (2.0 * a)]
+    }
+}
+
+/// Real roots of `x³ + a⋅x² + b⋅x + c = 0`, with Cardano's method.
+fn solve_cubic(a: f64, b: f64, c: f64) -> Vec<f64> {
+    // Substitute x = y - a/3 to get y³ + p⋅y + q = 0.
+    let p = b - a * a / 3.0;
+    let q = 2.0 * a * a * a / 27.0 - a * b / 3.0 + c;
+    let shift = -a / 3.0;
+
+    let half_q = q / 2.0;
+    let third_p = p / 3.0;
+    let discriminant = half_q * half_q + third_p * third_p * third_p;
+
+    let roots = if discriminant.abs() < EPSILON {
+        if half_q.abs() < EPSILON {
+            vec![0.0]
+        } else {
+            let u = (-half_q).cbrt();
+            vec![2.0 * u, -u]
+        }
+    } else if discriminant < 0.0 {
+        // Three real roots, which are easier to find with trigonometry.
+        let phi = (-half_q / (-third_p * third_p * third_p).sqrt()).acos() / 3.0;
+        let m = 2.0 * (-third_p).sqrt();
+        let third = 2.0 * std::f64::consts::PI / 3.0;
+        vec![
+            m * phi.cos(),
+            -m * (phi + third).cos(),
+            -m * (phi - third).cos(),
+        ]
+    } else {
+        let root = discriminant.sqrt();
+        vec![(root - half_q).cbrt() - (root + half_q).cbrt()]
+    };
+
+    roots.into_iter().map(|y| y + shift).collect()
+}
+
+/// Real roots of `c[0]⋅x⁴ + c[1]⋅x³ + c[2]⋅x² + c[3]⋅x + c[4] = 0`, with
+/// Ferrari's method, in no particular order.
+fn solve_quartic(c: [f64; 5]) -> Vec<f64> {
+    let (a, b, c1, d) = (c[1] / c[0], c[2] / c[0], c[3] / c[0], c[4] / c[0]);
+
+    // Substitute x = y - a/4 to get y⁴ + p⋅y² + q⋅y + r = 0.
+    let a2 = a * a;
+    let p = -3.0 * a2 / 8.0 + b;
+    let q = a2 * a / 8.0 - a * b / 2.0 + c1;
+    let r = -3.0 * a2 * a2 / 256.0 + a2 * b / 16.0 - a * c1 / 4.0 + d;
+    let shift = -a / 4.0;
+
+    let roots = if r.abs() < EPSILON {
+        // y⋅(y³ + p⋅y + q) = 0
+        let mut roots = solve_cubic(0.0, p, q);
+        roots.push(0.0);
+        roots
+    } else {
+        // Use one root of the resolvent cubic to split the quartic into two
+        // quadratics.
+        let z = solve_cubic(-p / 2.0, -r, r * p / 2.0 - q * q / 8.0)[0];
+
+        let u = z * z - r;
+        let v = 2.0 * z - p;
+        let u = if u.abs() < EPSILON {
+            0.0
+        } else if u > 0.0 {
+            u.sqrt()
+        } else {
+            return vec![];
+        };
+        let v = if v.abs() < EPSILON {
+            0.0
+        } else if v > 0.0 {
+            v.sqrt()
+        } else {
+            return vec![];
+        };
+        let v = if q < 0.0 { -v } else { v };
+
+        let mut roots = solve_quadratic(1.0, v, z - u);
+        roots.extend(solve_quadratic(1.0, -v, z + u));
+        roots
+    };
+
+    let polynomial = |x: f64| (((x + a) * x + b) * x + c1) * x + d;
+    let derivative = |x: f64| ((4.0 * x + 3.0 * a) * x + 2.0 * b) * x + c1;
+
+    roots
+        .into_iter()
+        .map(|y| {
+            // Polish the root a little, since the steps above lose precision.
+            let mut x = y + shift;
+            for _ in 0..2 {
+                let slope = derivative(x);
+                if slope.abs() > EPSILON {
+                    x -= polynomial(x) / slope;
+                }
+            }
+            x
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::texture;
+
+    #[test]
+    fn quartic_roots() {
+        // (x - 1)(x - 2)(x + 3)(x - 0.5)
+        let mut roots = solve_quartic([1.0, -0.5, -7.0, 9.5, -3.0]);
+        roots.sort_by(|a, b| a.total_cmp(b));
+
+        assert_eq!(roots.len(), 4);
+        for (root, expected) in roots.iter().zip(&[-3.0, 0.5, 1.0, 2.0]) {
+            assert!((root - expected).abs() < 1e-9, "{:?}", roots);
+        }
+    }
+
+    #[test]
+    fn rays_through_the_hole_miss() {
+        let torus = Torus::new(
+            vec3!(0.0, 0.0, 0.0),
+            vec3!(0.0, 1.0, 0.0),
+            2.0,
+            0.5,
+            Material::lambertian(texture::constant(Vec3::from(0.5))),
+        );
+
+        let through_the_hole = Ray::new(vec3!(0.0, 10.0, 0.0), vec3!(0.0, -1.0, 0.0), 0.0);
+        assert!(torus.hit(&through_the_hole, 0.001, f32::MAX).is_none());
+
+        // From far off to the side, the ray goes through both sides of the
+        // ring, and hits the outside of the near side first.
+        let across = Ray::new(vec3!(-100.0, 0.0, 0.0), vec3!(2.0, 0.0, 0.0), 0.0);
+        let rec = torus.hit(&across, 0.001, f32::MAX).unwrap();
+        assert!((rec.hit_point - vec3!(-2.5, 0.0, 0.0)).length() < 1e-4);
+        assert!((rec.normal - vec3!(-1.0, 0.0, 0.0)).length() < 1e-4);
+        assert!((rec.t - 48.75).abs() < 1e-4);
+    }
+
+    #[test]
+    fn random_points_are_on_the_tube() {
+        let axis = vec3!(0.0, 1.0, 1.0).unit_vector();
+        let torus = Torus::new(
+            vec3!(),
+            axis,
+            2.0,
+            0.5,
+            Material::lambertian(texture::constant(Vec3::from(0.5))),
+        );
+        let origin = vec3!(0.0, 10.0, 0.0);
+
+        for i in 0..100 {
+            let sample = (i as f32 / 100.0, (i * 61 % 100) as f32 / 100.0);
+            let p = origin + torus.random(&origin, sample);
+            let ring = (p - p.dot(&axis) * axis).unit_vector() * 2.0;
+
+            assert!(((p - ring).length() - 0.5).abs() < 1e-4);
+        }
+    }
+}
@@ -0,0 +1,399 @@
+//! Affine transformations of `Hittable` objects: any mix of translation,
+//! rotation, scaling and shearing.
+//!
+//! Rays are moved into the object's own space with the inverse matrix, and the
+//! hit is moved back out with the matrix itself. Since the ray direction isn't
+//! normalized in between, `t` is the same in both spaces. Normals have to be
+//! transformed by the inverse transpose, so that they stay perpendicular to
+//! the surface after a non-uniform scale or a shear.
+//!
+//! The bounding box in world space has to be axis-aligned for the `BVH`, so
+//! it's the box around the transformed box, which can be much bigger after a
+//! rotation. The object's own box is kept too: in world space it's an
+//! oriented bounding box, and rays that miss it are turned away before they
+//! get to the object.
+//!
+//! Transformed objects can still be used as lights: their `random` directions
+//! get mapped out of object space, and `pdf_value` accounts for how the
+//! transformation stretches solid angles.
+
+use crate::{
+    aabb::AABB,
+    hittable::{HitRecord, Hittable},
+    matrix::Matrix4,
+    ray::Ray,
+    vec3::Vec3,
+};
+
+/// A transformation instance. Holds a `Hittable` object and transforms it by
+/// some 4x4 matrix.
+#[derive(Clone, Debug)]
+pub struct Transform {
+    obj: Box<dyn Hittable>,
+    placement: Placement,
+}
+
+impl Transform {
+    /// Create a new transformation instance for some `Hittable` object.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `matrix` can't be inverted, like a scaling by 0 along some
+    /// axis.
+    pub fn new(obj: Box<dyn Hittable>, matrix: Matrix4) -> Self {
+        Self {
+            placement: Placement::new(
+                obj.as_ref(),
+                matrix,
+                "can't transform an object by a matrix with no inverse",
+            ),
+            obj,
+        }
+    }
+
+    /// The matrix that takes the object into world space.
+    pub fn matrix(&self) -> Matrix4 {
+        self.placement.matrix
+    }
+}
+
+impl Hittable for Transform {
+    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
+        self.placement.hit(self.obj.as_ref(), ray, t_min, t_max)
+    }
+
+    fn bounding_box(&self, t0: f32, t1: f32) -> Option<AABB> {
+        self.placement.bounding_box(self.obj.as_ref(), t0, t1)
+    }
+
+    fn box_clone(&self) -> Box<dyn Hittable> {
+        Box::new(self.clone())
+    }
+
+    fn transform(&self, matrix: Matrix4) -> Transform {
+        // Fold the new matrix into this one instead of wrapping it again.
+        Transform::new(self.obj.clone(), matrix * self.placement.matrix)
+    }
+
+    fn pdf_value(&self, origin: &Vec3, v: &Vec3) -> f32 {
+        self.placement.pdf_value(self.obj.as_ref(), origin, v)
+    }
+
+    fn random(&self, origin: &Vec3, sample: (f32, f32)) -> Vec3 {
+        self.placement.random(self.obj.as_ref(), origin, sample)
+    }
+}
+
+/// Where some object sits in the world: the matrix that takes it there, and
+/// the inverse that brings rays back. Shared by `Transform` and `Instance`.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct Placement {
+    /// Object space to world space.
+    pub matrix: Matrix4,
+    /// World space to object space.
+    pub inverse: Matrix4,
+    /// The object's bounding box in its own space, which is an oriented
+    /// bounding box in world space. `None` if the object doesn't have one, or
+    /// if it moves, since then the box depends on the time.
+    pub local_bbox: Option<AABB>,
+}
+
+impl Placement {
+    /// Place `obj` in the world with `matrix`.
+    ///
+    /// # Panics
+    ///
+    /// Panics with `message` if `matrix` can't be inverted.
+    pub fn new(obj: &dyn Hittable, matrix: Matrix4, message: &str) -> Self {
+        let start = obj.bounding_box(0.0, 0.0);
+        let local_bbox = if start.is_some() && start == obj.bounding_box(1.0, 1.0) {
+            start
+        } else {
+            None
+        };
+
+        Self {
+            matrix,
+            inverse: matrix.inverse().expect(message),
+            local_bbox,
+        }
+    }
+
+    /// Hit `obj`, placed in the world.
+    pub fn hit(&self, obj: &dyn Hittable, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
+        let local_ray = self.inverse.transform_ray(ray);
+
+        // The slab test against the oriented box is much cheaper than most
+        // objects' own hit tests.
+        if let Some(bbox) = &self.local_bbox {
+            if !bbox.hit(&local_ray, t_min, t_max) {
+                return None;
+            }
+        }
+
+        let mut rec = obj.hit(&local_ray, t_min, t_max)?;
+
+        // The normal already faces against the ray, and the inverse transpose
+        // keeps it on the same side, so `front_face` is still right.
+        rec.hit_point = self.matrix.transform_point(rec.hit_point);
+        rec.normal = self.inverse.transform_normal(rec.normal).unit_vector();
+        rec.tangent = rec
+            .tangent
+            .map(|tangent| self.matrix.transform_vector(tangent).unit_vector());
+
+        Some(rec)
+    }
+
+    /// The bounding box of `obj`, placed in the world.
+    pub fn bounding_box(&self, obj: &dyn Hittable, t0: f32, t1: f32) -> Option<AABB> {
+        obj.bounding_box(t0, t1)
+            .map(|bbox| self.matrix.transform_aabb(&bbox))
+    }
+
+    /// The PDF of `obj`'s `random` directions, placed in the world.
+    ///
+    /// Directions from `origin` are mapped into object space by the linear
+    /// part of the inverse matrix, L. That doesn't keep solid angles the same
+    /// unless it's a rotation, so the object's PDF gets scaled by the
+    /// Jacobian of the map, |det L| / |L v̂|³ for a unit vector v̂.
+    pub fn pdf_value(&self, obj: &dyn Hittable, origin: &Vec3, v: &Vec3) -> f32 {
+        let local_v = self.inverse.transform_vector(*v);
+        let stretch = local_v.length() / v.length();
+
+        obj.pdf_value(&self.inverse.transform_point(*origin), &local_v)
+            * self.inverse.determinant3().abs()
+            / (stretch * stretch * stretch)
+    }
+
+    /// A random direction from `origin` towards `obj`, placed in the world.
+    pub fn random(&self, obj: &dyn Hittable, origin: &Vec3, sample: (f32, f32)) -> Vec3 {
+        let local_origin = self.inverse.transform_point(*origin);
+        self.matrix
+            .transform_vector(obj.random(&local_origin, sample))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        hittable::{block::Block, quad::Quad, sphere::Sphere},
+        material::Material,
+        quaternion::{EulerOrder, Quaternion},
+        texture, vec3,
+        vec3::{Axis::*, Vec3},
+    };
+    use rand::prelude::*;
+    use rand_chacha::ChaCha8Rng;
+    use std::sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    };
+
+    fn material() -> Material {
+        Material::lambertian(texture::constant(Vec3::from(0.5)))
+    }
+
+    fn unit_block() -> Block {
+        Block::new(vec3!(0.0, 0.0, 0.0), vec3!(1.0, 1.0, 1.0), material())
+    }
+
+    #[test]
+    fn chained_transforms_fold_into_one() {
+        let moved = unit_block()
+            .rotate(Y, 30.0)
+            .scale(vec3!(2.0, 1.0, 1.0))
+            .translate(vec3!(1.0, 2.0, 3.0));
+
+        let expected = Matrix4::translation(vec3!(1.0, 2.0, 3.0))
+            * Matrix4::scaling(vec3!(2.0, 1.0, 1.0))
+            * Matrix4::rotation(vec3!(0.0, 1.0, 0.0), 30.0);
+
+        assert_eq!(moved.matrix(), expected);
+        // The block itself is inside, not a chain of other transforms, which
+        // would have moved its bounding box.
+        assert_eq!(
+            moved.obj.bounding_box(0.0, 1.0),
+            unit_block().bounding_box(0.0, 1.0)
+        );
+    }
+
+    #[test]
+    fn translated_and_rotated_bounding_box() {
+        let bbox = unit_block()
+            .rotate(Z, 90.0)
+            .translate(vec3!(10.0, 0.0, 0.0))
+            .bounding_box(0.0, 1.0)
+            .unwrap();
+
+        assert!((bbox.min - vec3!(9.0, 0.0, 0.0)).length() < 1e-5);
+        assert!((bbox.max - vec3!(10.0, 1.0, 1.0)).length() < 1e-5);
+    }
+
+    #[test]
+    fn rotations_match_rotating_the_rays() {
+        let mut rng = ChaCha8Rng::seed_from_u64(23);
+        let block = Block::new(vec3!(-1.0, -0.5, -0.25), vec3!(1.0, 0.5, 0.25), material());
+
+        for _ in 0..20 {
+            let rotation = Quaternion::new(rng.gen(), rng.gen(), rng.gen(), rng.gen::<f32>() - 0.5)
+                .unit_quaternion();
+            let angles = Vec3::random_range(&mut rng, -180.0, 180.0);
+            let rotations = [
+                (block.rotate_quaternion(rotation), rotation),
+                (
+                    block.rotate_euler(EulerOrder::ZXY, angles),
+                    Quaternion::from_euler(EulerOrder::ZXY, angles),
+                ),
+            ];
+
+            for (rotated, rotation) in &rotations {
+                for _ in 0..50 {
+                    let target = Vec3::random_range(&mut rng, -1.0, 1.0);
+                    let origin = Vec3::random_unit_vector(&mut rng) * 5.0;
+                    let ray = Ray::new(origin, target - origin, 0.0);
+
+                    // Rotate the ray the other way with the quaternion
+                    // itself, instead of a matrix, and hit the plain block.
+                    let inverse = rotation.conjugate();
+                    let local_ray = Ray::new(
+                        inverse.rotate(ray.origin),
+                        inverse.rotate(ray.direction),
+                        0.0,
+                    );
+                    let expected = block.hit(&local_ray, 0.001, f32::MAX);
+                    let actual = rotated.hit(&ray, 0.001, f32::MAX);
+
+                    match (expected, actual) {
+                        (None, None) => {}
+                        (Some(expected), Some(actual)) => {
+                            assert!((expected.t - actual.t).abs() < 1e-4);
+                            assert!(
+                                (rotation.rotate(expected.hit_point) - actual.hit_point).length()
+                                    < 1e-4
+                            );
+                            assert!(
+                                (rotation.rotate(expected.normal) - actual.normal).length() < 1e-4
+                            );
+                            assert_eq!(expected.front_face, actual.front_face);
+                        }
+                        (expected, actual) => panic!(
+                            "{:?} hit {:?}, but the reference hit {:?}",
+                            ray,
+                            actual.map(|rec| rec.t),
+                            expected.map(|rec| rec.t)
+                        ),
+                    }
+                }
+            }
+        }
+    }
+
+    /// Counts how many rays get as far as the block inside it.
+    #[derive(Clone, Debug)]
+    struct Counting(Block, Arc<AtomicUsize>);
+
+    impl Hittable for Counting {
+        fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
+            self.1.fetch_add(1, Ordering::Relaxed);
+            self.0.hit(ray, t_min, t_max)
+        }
+
+        fn bounding_box(&self, t0: f32, t1: f32) -> Option<AABB> {
+            self.0.bounding_box(t0, t1)
+        }
+
+        fn box_clone(&self) -> Box<dyn Hittable> {
+            Box::new(self.clone())
+        }
+    }
+
+    #[test]
+    fn rays_that_miss_the_oriented_box_skip_the_object() {
+        // A long thin block, turned 45 degrees so that its axis-aligned box
+        // is mostly empty.
+        let count = Arc::new(AtomicUsize::new(0));
+        let block = Block::new(vec3!(-2.0, -0.1, -0.1), vec3!(2.0, 0.1, 0.1), material());
+        let rotated = Counting(block, count.clone()).rotate(Z, 45.0);
+
+        let bbox = rotated.bounding_box(0.0, 1.0).unwrap();
+        assert!(bbox.max[X] > 1.4 && bbox.max[Y] > 1.4);
+
+        // Through a corner of the axis-aligned box, far from the block.
+        let corner = Ray::new(vec3!(1.2, -1.2, 5.0), vec3!(0.0, 0.0, -1.0), 0.0);
+        assert!(bbox.hit(&corner, 0.001, f32::MAX));
+        assert!(rotated.hit(&corner, 0.001, f32::MAX).is_none());
+        assert_eq!(count.load(Ordering::Relaxed), 0);
+
+        // Along the block's diagonal, which does hit it.
+        let diagonal = Ray::new(vec3!(1.0, 1.0, 5.0), vec3!(0.0, 0.0, -1.0), 0.0);
+        let rec = rotated.hit(&diagonal, 0.001, f32::MAX).unwrap();
+        assert!((rec.t - 4.9).abs() < 1e-4);
+        assert_eq!(count.load(Ordering::Relaxed), 1);
+    }
+
+    #[test]
+    fn posed_lights_integrate_to_one() {
+        let quad = || {
+            Quad::new(
+                vec3!(),
+                vec3!(1.0, 0.0, 0.0),
+                vec3!(0.0, 0.0, 2.0),
+                material(),
+            )
+        };
+        let lights = [
+            quad()
+                .rotate_about(vec3!(1.0, 1.0, 0.0), 50.0)
+                .translate(vec3!(0.5, 1.0, -1.0)),
+            quad()
+                .scale(vec3!(2.0, 1.0, 0.5))
+                .transform(Matrix4::shearing(0.3, 0.0, 0.0, 0.2, 0.0, 0.0))
+                .rotate(X, 30.0),
+            Sphere::new(vec3!(), 0.5, material())
+                .scale(vec3!(1.0, 3.0, 0.5))
+                .translate(vec3!(0.0, 1.0, 0.0)),
+        ];
+        let origin = vec3!(3.0, -1.0, 2.0);
+
+        let mut rng = ChaCha8Rng::seed_from_u64(13);
+        for light in &lights {
+            let samples = 200_000;
+            let total: f32 = (0..samples)
+                .map(|_| {
+                    let direction = Vec3::on_unit_sphere((rng.gen(), rng.gen()));
+                    light.pdf_value(&origin, &direction)
+                })
+                .sum();
+            let integral = total * 4.0 * std::f32::consts::PI / samples as f32;
+            assert!((integral - 1.0).abs() < 0.05, "{:?}: {}", light, integral);
+
+            // Sampled directions head towards the light.
+            for _ in 0..100 {
+                let direction = light.random(&origin, (rng.gen(), rng.gen()));
+                assert!(light
+                    .hit(&Ray::new(origin, direction, 0.0), 0.001, f32::MAX)
+                    .is_some());
+                assert!(light.pdf_value(&origin, &direction) > 0.0);
+            }
+        }
+    }
+
+    #[test]
+    fn scaled_sphere_normals_stay_perpendicular() {
+        // A sphere squashed into an ellipsoid: x²/4 + y² + z² = 1.
+        let ellipsoid = Sphere::new(vec3!(), 1.0, material()).scale(vec3!(2.0, 1.0, 1.0));
+
+        let p = vec3!(2.0_f32.sqrt(), 0.5_f32.sqrt(), 0.0);
+        let ray = Ray::new(p * 2.0, -p, 0.0);
+        let rec = ellipsoid.hit(&ray, 0.001, 10.0).unwrap();
+
+        assert!((rec.hit_point - p).length() < 1e-4);
+        assert!((rec.t - 1.0).abs() < 1e-4);
+        assert!(rec.front_face);
+
+        // The gradient of the implicit surface.
+        let gradient = vec3!(p[X] / 4.0, p[Y], p[Z]).unit_vector();
+        assert!((rec.normal - gradient).length() < 1e-4);
+    }
+}
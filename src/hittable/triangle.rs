@@ -0,0 +1,492 @@
+//! Triangles, and meshes made out of lots of them.
+//!
+//! A `TriangleMesh` keeps its vertex positions, normals and texture
+//! coordinates in one set of buffers (a `MeshData`), shared by all of its
+//! triangles. Each `Triangle` just refers to the buffers and the index of its
+//! face. The triangles get their own `BVH`, so that a mesh with lots of
+//! triangles can be hit about as quickly as a single object.
+
+use crate::{
+    aabb::AABB,
+    bvh::BVH,
+    hittable::{HitRecord, Hittable, UVCoord},
+    material::Material,
+    ray::Ray,
+    vec3,
+    vec3::Vec3,
+};
+use std::sync::Arc;
+
+/// One corner of a triangle, as indices into a `MeshData`'s buffers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Vertex {
+    pub position: u32,
+    /// Without normals, the triangle is flat shaded.
+    pub normal: Option<u32>,
+    /// Without texture coordinates, the triangle's barycentric coordinates
+    /// are used instead.
+    pub uv: Option<u32>,
+}
+
+impl Vertex {
+    /// A vertex with only a position.
+    pub fn new(position: u32) -> Self {
+        Self {
+            position,
+            normal: None,
+            uv: None,
+        }
+    }
+}
+
+/// A triangle in a mesh. The vertices are ordered counterclockwise when
+/// looking at the front of the triangle.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Face {
+    pub vertices: [Vertex; 3],
+    /// An index into `MeshData::materials`.
+    pub material: u32,
+}
+
+/// The buffers behind a triangle mesh.
+#[derive(Debug, Clone, Default)]
+pub struct MeshData {
+    pub positions: Vec<Vec3>,
+    pub normals: Vec<Vec3>,
+    pub uvs: Vec<UVCoord>,
+    pub faces: Vec<Face>,
+    pub materials: Vec<Arc<Material>>,
+    /// The running total of the faces' areas, for picking faces with a
+    /// chance in proportion to their area. `TriangleMesh::new` fills it in.
+    pub area_cdf: Vec<f32>,
+}
+
+impl MeshData {
+    /// The corner positions of a face.
+    pub fn face_positions(&self, face: &Face) -> [Vec3; 3] {
+        let [a, b, c] = face.vertices;
+        [
+            self.positions[a.position as usize],
+            self.positions[b.position as usize],
+            self.positions[c.position as usize],
+        ]
+    }
+
+    /// The area of a face.
+    pub fn face_area(&self, face: &Face) -> f32 {
+        let [a, b, c] = self.face_positions(face);
+        0.5 * (b - a).cross(&(c - a)).length()
+    }
+
+    /// The corner normals of a face, if all of its corners have them.
+    fn face_normals(&self, face: &Face) -> Option<[Vec3; 3]> {
+        let [a, b, c] = face.vertices;
+        Some([
+            self.normals[a.normal? as usize],
+            self.normals[b.normal? as usize],
+            self.normals[c.normal? as usize],
+        ])
+    }
+
+    /// The corner texture coordinates of a face, if all of its corners have
+    /// them.
+    fn face_uvs(&self, face: &Face) -> Option<[UVCoord; 3]> {
+        let [a, b, c] = face.vertices;
+        Some([
+            self.uvs[a.uv? as usize],
+            self.uvs[b.uv? as usize],
+            self.uvs[c.uv? as usize],
+        ])
+    }
+}
+
+/// A single triangle of a mesh.
+#[derive(Debug, Clone)]
+pub struct Triangle {
+    mesh: Arc<MeshData>,
+    face: usize,
+}
+
+impl Triangle {
+    /// A standalone triangle, with corners `a`, `b` and `c` in
+    /// counterclockwise order when looking at its front.
+    pub fn new(a: Vec3, b: Vec3, c: Vec3, material: Material) -> Self {
+        let mesh = MeshData {
+            positions: vec![a, b, c],
+            faces: vec![Face {
+                vertices: [Vertex::new(0), Vertex::new(1), Vertex::new(2)],
+                material: 0,
+            }],
+            materials: vec![Arc::new(material)],
+            ..MeshData::default()
+        };
+
+        Self {
+            mesh: Arc::new(mesh),
+            face: 0,
+        }
+    }
+
+    /// Face number `face` of `mesh`.
+    pub fn in_mesh(mesh: Arc<MeshData>, face: usize) -> Self {
+        Self { mesh, face }
+    }
+
+    fn positions(&self) -> [Vec3; 3] {
+        self.mesh.face_positions(&self.mesh.faces[self.face])
+    }
+
+    /// The area of the triangle.
+    pub fn area(&self) -> f32 {
+        self.mesh.face_area(&self.mesh.faces[self.face])
+    }
+}
+
+impl Hittable for Triangle {
+    /// Uses the Möller–Trumbore algorithm.
+    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
+        let face = &self.mesh.faces[self.face];
+        let [a, b, c] = self.mesh.face_positions(face);
+
+        let edge1 = b - a;
+        let edge2 = c - a;
+        let p = ray.direction.cross(&edge2);
+        let determinant = edge1.dot(&p);
+
+        // The ray is parallel to the triangle.
+        if determinant.abs() < 1e-12 {
+            return None;
+        }
+
+        // Find the barycentric coordinates (u, v) of the hit point, bailing
+        // out as soon as it's clear that it's outside the triangle.
+        let inverse_determinant = 1.0 / determinant;
+        let s = ray.origin - a;
+        let u = s.dot(&p) * inverse_determinant;
+        if !(0.0..=1.0).contains(&u) {
+            return None;
+        }
+
+        let q = s.cross(&edge1);
+        let v = ray.direction.dot(&q) * inverse_determinant;
+        if v < 0.0 || u + v > 1.0 {
+            return None;
+        }
+
+        let t = edge2.dot(&q) * inverse_determinant;
+        if t < t_min || t > t_max {
+            return None;
+        }
+
+        let w = 1.0 - u - v;
+        let mut outward_normal = edge1.cross(&edge2).unit_vector();
+
+        // Interpolate the vertex normals, if there are any, for smooth
+        // shading. The outside of the triangle is whichever side the vertex
+        // normals point to.
+        let shading_normal = self.mesh.face_normals(face).map(|[na, nb, nc]| {
+            let normal = (w * na + u * nb + v * nc).unit_vector();
+            if normal.dot(&outward_normal) < 0.0 {
+                outward_normal = -outward_normal;
+            }
+            normal
+        });
+
+        let uv = match self.mesh.face_uvs(face) {
+            Some([uva, uvb, uvc]) => (
+                w * uva.0 + u * uvb.0 + v * uvc.0,
+                w * uva.1 + u * uvb.1 + v * uvc.1,
+            ),
+            None => (u, v),
+        };
+
+        let mut rec = HitRecord::new(
+            ray,
+            t,
+            ray.at(t),
+            outward_normal,
+            self.mesh.materials[face.material as usize].clone(),
+            uv,
+        );
+
+        if let Some(normal) = shading_normal {
+            rec.normal = if rec.front_face { normal } else { -normal };
+        }
+
+        Some(rec)
+    }
+
+    fn bounding_box(&self, _t0: f32, _t1: f32) -> Option<AABB> {
+        let [a, b, c] = self.positions();
+
+        // Pad the box a little, in case the triangle lies flat along an axis.
+        let padding = Vec3::from(0.0001);
+        let min = vec3!(
+            a[0].min(b[0]).min(c[0]),
+            a[1].min(b[1]).min(c[1]),
+            a[2].min(b[2]).min(c[2]),
+        );
+        let max = vec3!(
+            a[0].max(b[0]).max(c[0]),
+            a[1].max(b[1]).max(c[1]),
+            a[2].max(b[2]).max(c[2]),
+        );
+
+        Some(AABB::new(min - padding, max + padding))
+    }
+
+    fn box_clone(&self) -> Box<dyn Hittable> {
+        Box::new(self.clone())
+    }
+
+    fn pdf_value(&self, origin: &Vec3, v: &Vec3) -> f32 {
+        if let Some(rec) = self.hit(&Ray::new(*origin, *v, 0.0), 0.001, f32::INFINITY) {
+            let distance_squared = rec.t * rec.t * v.length_squared();
+            // Points are spread evenly over the flat triangle, so this needs
+            // its real normal, not the interpolated shading normal.
+            let [a, b, c] = self.positions();
+            let normal = (b - a).cross(&(c - a)).unit_vector();
+            let cosine = (v.dot(&normal) / v.length()).abs();
+
+            distance_squared / (cosine * self.area())
+        } else {
+            0.0
+        }
+    }
+
+    fn random(&self, origin: &Vec3, (u, v): (f32, f32)) -> Vec3 {
+        // Turn the unit square into barycentric coordinates, evenly spread
+        // over the triangle.
+        let [a, b, c] = self.positions();
+        let root_u = u.sqrt();
+        let (wa, wb) = (1.0 - root_u, v * root_u);
+
+        wa * a + wb * b + (1.0 - wa - wb) * c - *origin
+    }
+}
+
+/// A mesh of triangles, with its own `BVH`.
+#[derive(Debug, Clone)]
+pub struct TriangleMesh {
+    pub data: Arc<MeshData>,
+    bvh: Arc<BVH>,
+}
+
+impl TriangleMesh {
+    /// Build the `BVH` for a mesh.
+    pub fn new(mut data: MeshData) -> Self {
+        let mut total = 0.0;
+        data.area_cdf = data
+            .faces
+            .iter()
+            .map(|face| {
+                total += data.face_area(face);
+                total
+            })
+            .collect();
+
+        let data = Arc::new(data);
+        let triangles = (0..data.faces.len())
+            .map(|face| Box::new(Triangle::in_mesh(data.clone(), face)) as Box<dyn Hittable>)
+            .collect();
+
+        Self {
+            bvh: Arc::new(BVH::new(triangles, 0.0, 0.0)),
+            data,
+        }
+    }
+
+    /// The number of triangles in the mesh.
+    pub fn len(&self) -> usize {
+        self.data.faces.len()
+    }
+
+    /// Whether the mesh has no triangles. An empty mesh never gets hit.
+    pub fn is_empty(&self) -> bool {
+        self.data.faces.is_empty()
+    }
+
+    /// The total area of the mesh's triangles.
+    pub fn area(&self) -> f32 {
+        self.data.area_cdf.last().copied().unwrap_or(0.0)
+    }
+}
+
+impl Hittable for TriangleMesh {
+    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
+        self.bvh.hit(ray, t_min, t_max)
+    }
+
+    fn bounding_box(&self, t0: f32, t1: f32) -> Option<AABB> {
+        self.bvh.bounding_box(t0, t1)
+    }
+
+    fn box_clone(&self) -> Box<dyn Hittable> {
+        Box::new(self.clone())
+    }
+
+    /// Faces are picked by their share of the area, so each face's PDF counts
+    /// for that much.
+    fn pdf_value(&self, origin: &Vec3, v: &Vec3) -> f32 {
+        let area = self.area();
+        if area <= 0.0 {
+            return 0.0;
+        }
+
+        let mut pdf = 0.0;
+        let ray = Ray::new(*origin, *v, 0.0);
+        self.bvh
+            .for_each_along(&ray, 0.001, f32::INFINITY, |face, triangle| {
+                let share = self.data.face_area(&self.data.faces[face]) / area;
+                pdf += share * triangle.pdf_value(origin, v);
+            });
+        pdf
+    }
+
+    fn random(&self, origin: &Vec3, (u, v): (f32, f32)) -> Vec3 {
+        let cdf = &self.data.area_cdf;
+        if cdf.is_empty() {
+            return vec3!(1.0, 0.0, 0.0);
+        }
+
+        // Pick a face, and reuse what's left of `u` to pick a point on it.
+        let target = u * self.area();
+        let face = cdf
+            .partition_point(|&total| total <= target)
+            .min(cdf.len() - 1);
+        let before = if face == 0 { 0.0 } else { cdf[face - 1] };
+        let u = ((target - before) / (cdf[face] - before)).clamp(0.0, 1.0);
+
+        Triangle::in_mesh(self.data.clone(), face).random(origin, (u, v))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::texture;
+    use rand::prelude::*;
+    use rand_chacha::ChaCha8Rng;
+
+    fn material() -> Material {
+        Material::lambertian(texture::constant(Vec3::from(0.5)))
+    }
+
+    #[test]
+    fn hits_inside_and_misses_outside() {
+        let triangle = Triangle::new(
+            vec3!(0.0, 0.0, 0.0),
+            vec3!(1.0, 0.0, 0.0),
+            vec3!(0.0, 1.0, 0.0),
+            material(),
+        );
+
+        let towards = |x, y| Ray::new(vec3!(x, y, 1.0), vec3!(0.0, 0.0, -1.0), 0.0);
+
+        let rec = triangle.hit(&towards(0.25, 0.5), 0.0, 10.0).unwrap();
+        assert!((rec.t - 1.0).abs() < 1e-6);
+        assert!(rec.front_face);
+        assert_eq!(rec.normal, vec3!(0.0, 0.0, 1.0));
+        assert_eq!(rec.uv, (0.25, 0.5));
+
+        assert!(triangle.hit(&towards(0.6, 0.6), 0.0, 10.0).is_none());
+        assert!(triangle.hit(&towards(-0.1, 0.5), 0.0, 10.0).is_none());
+        assert!(triangle.hit(&towards(0.25, 0.5), 0.0, 0.5).is_none());
+    }
+
+    #[test]
+    fn interpolates_normals_and_uvs() {
+        let vertex = |i| Vertex {
+            position: i,
+            normal: Some(i),
+            uv: Some(i),
+        };
+        let mesh = MeshData {
+            positions: vec![
+                vec3!(0.0, 0.0, 0.0),
+                vec3!(1.0, 0.0, 0.0),
+                vec3!(0.0, 1.0, 0.0),
+            ],
+            // Pointing away from the winding order's normal, so the back of
+            // the triangle becomes its front.
+            normals: vec![
+                vec3!(0.0, 0.0, -1.0),
+                vec3!(1.0, 0.0, -1.0).unit_vector(),
+                vec3!(0.0, 0.0, -1.0),
+            ],
+            uvs: vec![(0.5, 0.5), (1.0, 0.5), (0.5, 1.0)],
+            faces: vec![Face {
+                vertices: [vertex(0), vertex(1), vertex(2)],
+                material: 0,
+            }],
+            materials: vec![Arc::new(material())],
+            ..MeshData::default()
+        };
+        let mesh = TriangleMesh::new(mesh);
+
+        let ray = Ray::new(vec3!(0.25, 0.25, -1.0), vec3!(0.0, 0.0, 1.0), 0.0);
+        let rec = mesh.hit(&ray, 0.0, 10.0).unwrap();
+
+        assert!(rec.front_face);
+        assert!(rec.normal[0] > 0.0 && rec.normal[2] < 0.0);
+        assert_eq!(rec.uv, (0.625, 0.625));
+    }
+
+    #[test]
+    fn mesh_lights_integrate_to_one() {
+        let vertex = |i| Vertex {
+            position: i,
+            normal: Some(i),
+            uv: None,
+        };
+        let face = |a, b, c| Face {
+            vertices: [vertex(a), vertex(b), vertex(c)],
+            material: 0,
+        };
+        let mesh = MeshData {
+            // A big triangle under a small one, so some directions cross
+            // both, and a tilted one off to the side.
+            positions: vec![
+                vec3!(-2.0, -2.0, 0.0),
+                vec3!(2.0, -2.0, 0.0),
+                vec3!(0.0, 2.0, 0.0),
+                vec3!(-0.5, -0.5, 1.0),
+                vec3!(0.5, -0.5, 1.0),
+                vec3!(0.0, 0.5, 1.0),
+                vec3!(3.0, 0.0, 0.0),
+                vec3!(4.0, 0.0, 1.0),
+                vec3!(3.0, 1.0, 0.5),
+            ],
+            // Shading normals far from the real ones, which the PDF mustn't
+            // use.
+            normals: (0..9)
+                .map(|i| vec3!(1.0, i as f32 - 4.0, 0.2).unit_vector())
+                .collect(),
+            faces: vec![face(0, 1, 2), face(3, 4, 5), face(6, 7, 8)],
+            materials: vec![Arc::new(material())],
+            ..MeshData::default()
+        };
+        let light = TriangleMesh::new(mesh);
+        let origin = vec3!(0.5, 0.0, 3.0);
+
+        let mut rng = ChaCha8Rng::seed_from_u64(11);
+        let samples = 200_000;
+        let total: f32 = (0..samples)
+            .map(|_| {
+                let direction = Vec3::on_unit_sphere((rng.gen(), rng.gen()));
+                light.pdf_value(&origin, &direction)
+            })
+            .sum();
+        let integral = total * 4.0 * std::f32::consts::PI / samples as f32;
+        assert!((integral - 1.0).abs() < 0.05, "{}", integral);
+
+        // Sampled directions head towards the mesh.
+        for _ in 0..100 {
+            let direction = light.random(&origin, (rng.gen(), rng.gen()));
+            assert!(light
+                .hit(&Ray::new(origin, direction, 0.0), 0.001, f32::MAX)
+                .is_some());
+            assert!(light.pdf_value(&origin, &direction) > 0.0);
+        }
+    }
+}
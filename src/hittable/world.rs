@@ -5,7 +5,6 @@ use crate::hittable::{HitRecord, Hittable};
 use crate::ray::Ray;
 use crate::vec3;
 use crate::vec3::Vec3;
-use rand::prelude::*;
 
 /// The world that needs to be rendered, with all of its objects. Every object
 /// needs to implement `Hittable`. Coincidentally, this struct *also* implements
@@ -82,13 +81,14 @@ impl Hittable for World {
         sum * weight
     }
 
-    fn random(&self, origin: &Vec3) -> Vec3 {
-        // AAHHHH THIS IS BAD
-        let mut rng = thread_rng();
+    fn random(&self, origin: &Vec3, (u, v): (f32, f32)) -> Vec3 {
+        // Use the first coordinate to pick an object, then stretch what's left
+        // of it back out to [0, 1) for the object to use.
+        let count = self.objects.len();
+        let scaled = u * count as f32;
+        let index = (scaled as usize).min(count - 1);
 
-        let index = rng.gen_range(0_u64, self.objects.len() as u64);
-
-        self.objects[index as usize].random(origin)
+        self.objects[index].random(origin, (scaled - index as f32, v))
     }
 
     fn box_clone(&self) -> Box<dyn Hittable> {
@@ -3,11 +3,17 @@ pub mod bvh;
 pub mod camera;
 pub mod hittable;
 pub mod material;
+pub mod matrix;
+pub mod mesh;
 pub mod onb;
 pub mod pdf;
+pub mod quaternion;
 pub mod ray;
 pub mod renderer;
+pub mod sampler;
+pub mod scene_file;
 pub mod scenes;
 pub mod texture;
+pub mod tonemap;
 pub mod util;
 pub mod vec3;
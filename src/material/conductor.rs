@@ -0,0 +1,289 @@
+//! A physically based metal: a rough conductor, with GGX microfacet
+//! reflections.
+//!
+//! The surface is made of tiny mirror facets, spread out according to the
+//! GGX (Trowbridge-Reitz) distribution, which can be rougher along one
+//! direction than the other. How much light each facet reflects comes from
+//! the Fresnel equations for a conductor, with a complex refractive index
+//! (`eta` and `k`) for each colour channel. Metals get brighter and whiter
+//! towards grazing angles because of this.
+//!
+//! Unlike the books' fuzzy metal, this reflects a PDF, so light sampling
+//! works with it, and it never reflects more light than comes in.
+
+use crate::{
+    hittable::HitRecord,
+    material::{Scatter, ScatterType},
+    onb::ONB,
+    pdf::{ggx_pdf::TrowbridgeReitz, PDF},
+    ray::Ray,
+    sampler::SampleStream,
+    vec3,
+    vec3::{Axis::*, Vec3},
+};
+use serde::Deserialize;
+
+/// Microfacet roughnesses (alpha) below this are treated as a perfect mirror.
+pub(crate) const SMOOTH: f32 = 1e-3;
+
+/// The complex refractive index of a conductor.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+pub enum Ior {
+    Gold,
+    Copper,
+    Aluminium,
+    Silver,
+    /// Any refractive index `eta` and extinction coefficient `k`, per colour
+    /// channel.
+    Custom {
+        eta: Vec3,
+        k: Vec3,
+    },
+    /// Some conductor that reflects this much of each colour channel, head
+    /// on. Handy for turning a colour into a metal.
+    Reflectance(Vec3),
+}
+
+impl Ior {
+    /// The refractive index and extinction coefficient, per colour channel.
+    pub fn eta_k(&self) -> (Vec3, Vec3) {
+        // Measured values, at about 650, 550 and 450 nm.
+        match *self {
+            Ior::Gold => (vec3!(0.143, 0.374, 1.442), vec3!(3.983, 2.385, 1.603)),
+            Ior::Copper => (vec3!(0.200, 0.924, 1.102), vec3!(3.912, 2.452, 2.142)),
+            Ior::Aluminium => (vec3!(1.657, 0.880, 0.521), vec3!(9.224, 6.270, 4.837)),
+            Ior::Silver => (vec3!(0.155, 0.117, 0.138), vec3!(4.828, 3.122, 2.147)),
+            Ior::Custom { eta, k } => (eta, k),
+            Ior::Reflectance(reflectance) => {
+                // Gulbrandsen's "Artist Friendly Metallic Fresnel" (2014),
+                // with white edges.
+                let mut eta = Vec3::default();
+                let mut k = Vec3::default();
+                for i in 0..3 {
+                    let r = reflectance[i].clamp(0.0, 0.999);
+                    eta[i] = (1.0 - r) / (1.0 + r);
+                    k[i] = ((r * (eta[i] + 1.0).powi(2) - (eta[i] - 1.0).powi(2)) / (1.0 - r))
+                        .max(0.0)
+                        .sqrt();
+                }
+                (eta, k)
+            }
+        }
+    }
+}
+
+/// A rough conductor.
+#[derive(Debug, Clone, Copy)]
+pub struct Conductor {
+    pub eta: Vec3,
+    pub k: Vec3,
+    /// The GGX roughness (alpha) along the surface's tangent and across it.
+    pub alpha: (f32, f32),
+}
+
+impl Conductor {
+    /// Create a new conductor. `roughness` goes from 0 (a mirror) to 1, and
+    /// is squared to get the GGX alpha, so that it looks about linear.
+    pub fn new(ior: Ior, roughness: f32) -> Self {
+        Self::anisotropic(ior, (roughness, roughness))
+    }
+
+    /// Create a new conductor that's rougher along one direction than the
+    /// other, like brushed metal. The first roughness is along the hit
+    /// record's `tangent`, or along some direction picked from the normal if
+    /// the surface doesn't have one.
+    pub fn anisotropic(ior: Ior, (roughness_u, roughness_v): (f32, f32)) -> Self {
+        let (eta, k) = ior.eta_k();
+        let alpha = |roughness: f32| roughness.clamp(0.0, 1.0).powi(2);
+
+        Self {
+            eta,
+            k,
+            alpha: (alpha(roughness_u), alpha(roughness_v)),
+        }
+    }
+
+    /// How much of each colour channel gets reflected, for light hitting a
+    /// facet at an angle with cosine `cos`.
+    pub fn fresnel(&self, cos: f32) -> Vec3 {
+        let cos = cos.clamp(0.0, 1.0);
+        let mut reflectance = Vec3::default();
+        for i in 0..3 {
+            reflectance[i] = fresnel_conductor(cos, self.eta[i], self.k[i]);
+        }
+        reflectance
+    }
+
+    pub fn scatter(
+        &self,
+        _samples: &mut SampleStream,
+        ray_in: &Ray,
+        rec: &HitRecord,
+    ) -> Option<Scatter> {
+        let direction = ray_in.direction.unit_vector();
+
+        if self.alpha.0 < SMOOTH && self.alpha.1 < SMOOTH {
+            let reflected = Ray::new(rec.hit_point, direction.reflect(&rec.normal), ray_in.time);
+            return Some(Scatter::new(
+                self.fresnel(-direction.dot(&rec.normal)),
+                ScatterType::Specular(reflected),
+            ));
+        }
+
+        // The Fresnel term depends on the reflected direction, so it's all
+        // in `scattering_pdf`.
+        Some(Scatter::new(
+            Vec3::from(1.0),
+            ScatterType::PDF(PDF::ggx(frame(rec), -direction, self.rough_alpha())),
+        ))
+    }
+
+    /// The BRDF times the cosine of the reflected direction:
+    /// `F(wo⋅h) D(h) G(wo, wi) / (4 cos(wo))`.
+    pub fn scattering_pdf(&self, ray_in: &Ray, rec: &HitRecord, scattered: &Ray) -> Vec3 {
+        let uvw = frame(rec);
+        let wo = uvw.coords(&-ray_in.direction.unit_vector());
+        let wi = uvw.coords(&scattered.direction.unit_vector());
+        if wo[Z] <= 0.0 || wi[Z] <= 0.0 {
+            return vec3!(0.0);
+        }
+
+        let h = (wo + wi).unit_vector();
+        let distribution = TrowbridgeReitz::new(self.rough_alpha());
+        self.fresnel(wo.dot(&h))
+            * (distribution.distribution(&h) * distribution.masking_shadowing(&wo, &wi)
+                / (4.0 * wo[Z]))
+    }
+
+    /// The alphas, kept away from 0 so that the distribution stays finite.
+    fn rough_alpha(&self) -> (f32, f32) {
+        (self.alpha.0.max(SMOOTH), self.alpha.1.max(SMOOTH))
+    }
+}
+
+/// A frame around the normal, lined up with the tangent if there is one.
+fn frame(rec: &HitRecord) -> ONB {
+    let w = rec.normal;
+    let tangent = rec
+        .tangent
+        .map(|tangent| tangent - w * w.dot(&tangent))
+        .filter(|tangent| tangent.length_squared() > 1e-12);
+
+    match tangent {
+        Some(tangent) => {
+            let u = tangent.unit_vector();
+            ONB {
+                u,
+                v: w.cross(&u),
+                w,
+            }
+        }
+        None => ONB::build_from_w(w),
+    }
+}
+
+/// The Fresnel reflectance of a conductor with a complex refractive index
+/// `eta + ik`, for unpolarized light at an angle with cosine `cos`.
+fn fresnel_conductor(cos: f32, eta: f32, k: f32) -> f32 {
+    let cos2 = cos * cos;
+    let sin2 = 1.0 - cos2;
+    let eta2 = eta * eta;
+    let k2 = k * k;
+
+    let t0 = eta2 - k2 - sin2;
+    let a2_plus_b2 = (t0 * t0 + 4.0 * eta2 * k2).sqrt();
+    let t1 = a2_plus_b2 + cos2;
+    let a = (0.5 * (a2_plus_b2 + t0)).max(0.0).sqrt();
+    let t2 = 2.0 * cos * a;
+    let rs = (t1 - t2) / (t1 + t2);
+
+    let t3 = cos2 * a2_plus_b2 + sin2 * sin2;
+    let t4 = t2 * sin2;
+    let rp = rs * (t3 - t4) / (t3 + t4);
+
+    0.5 * (rp + rs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{material::Material, sampler::SampleStream};
+    use rand::prelude::*;
+    use rand_chacha::ChaCha8Rng;
+    use std::sync::Arc;
+
+    #[test]
+    fn fresnel_goes_white_at_grazing_angles() {
+        for &ior in &[Ior::Gold, Ior::Copper, Ior::Aluminium, Ior::Silver] {
+            let conductor = Conductor::new(ior, 0.0);
+            let (eta, k) = ior.eta_k();
+            let head_on = conductor.fresnel(1.0);
+            for i in 0..3 {
+                let expected =
+                    ((eta[i] - 1.0).powi(2) + k[i] * k[i]) / ((eta[i] + 1.0).powi(2) + k[i] * k[i]);
+                assert!((head_on[i] - expected).abs() < 1e-5);
+            }
+            assert!((conductor.fresnel(0.0) - Vec3::from(1.0)).length() < 1e-5);
+        }
+
+        let reflectance = vec3!(0.9, 0.6, 0.2);
+        let head_on = Conductor::new(Ior::Reflectance(reflectance), 0.5).fresnel(1.0);
+        assert!((head_on - reflectance).length() < 1e-4);
+    }
+
+    #[test]
+    fn rough_conductors_dont_make_light() {
+        // A furnace test: with a perfect reflector, the reflected light
+        // should add up to at most what came in, and close to it when
+        // smooth enough that little gets shadowed.
+        let mut rng = ChaCha8Rng::seed_from_u64(24);
+        let white = Ior::Custom {
+            eta: Vec3::from(0.0),
+            k: Vec3::from(1e6),
+        };
+        let normal = vec3!(0.0, 1.0, 0.0);
+        let mut stream_rng = ChaCha8Rng::seed_from_u64(0);
+        let mut stream = SampleStream::independent(&mut stream_rng);
+
+        let head_on = vec3!(0.0, -1.0, 0.0);
+        let glancing = vec3!(1.0, -0.3, 0.5);
+        for &(roughness, incoming, min) in &[
+            ((0.1, 0.1), head_on, 0.99),
+            ((0.2, 0.5), head_on, 0.93),
+            ((0.2, 0.5), glancing, 0.8),
+        ] {
+            let material = Arc::new(Material::anisotropic_conductor(white, roughness));
+            let rec = HitRecord::new(
+                &Ray::new(normal, -normal, 0.0),
+                1.0,
+                vec3!(),
+                normal,
+                material.clone(),
+                (0.0, 0.0),
+            );
+            let ray_in = Ray::new(-incoming, incoming, 0.0);
+
+            let samples = 100_000;
+            let mut total = 0.0;
+            for _ in 0..samples {
+                let scatter = material.scatter(&mut stream, &ray_in, &rec).unwrap();
+                let pdf = match scatter.scattered {
+                    ScatterType::PDF(pdf) => pdf,
+                    _ => panic!("rough conductors should scatter a PDF"),
+                };
+                let scattered = Ray::new(vec3!(), pdf.generate((rng.gen(), rng.gen())), 0.0);
+                let value = material.scattering_pdf(&mut rng, &ray_in, &rec, &scattered);
+                total += value[0] / pdf.value(&scattered.direction);
+            }
+            let albedo = total / samples as f32;
+
+            assert!(
+                albedo <= 1.0 && albedo > min,
+                "{:?}, {:?}: {}",
+                roughness,
+                incoming,
+                albedo
+            );
+        }
+    }
+}
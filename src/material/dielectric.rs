@@ -4,10 +4,10 @@ use crate::{
     hittable::HitRecord,
     material::{Scatter, ScatterType},
     ray::Ray,
+    sampler::SampleStream,
     vec3,
     vec3::{Channel::*, Vec3},
 };
-use rand::Rng;
 
 /// A dielectric material. Has some refraction index. Will refract or reflect
 /// rays, based on Snell's law and the incident ray.
@@ -37,9 +37,9 @@ impl Dielectric {
         }
     }
 
-    pub fn scatter<R: Rng + ?Sized>(
+    pub fn scatter(
         &self,
-        rng: &mut R,
+        samples: &mut SampleStream,
         ray_in: &Ray,
         rec: &HitRecord,
     ) -> Option<Scatter> {
@@ -69,7 +69,7 @@ impl Dielectric {
         // approximation, and then compare it to a random f32.
         let reflect_prob = schlick(cos_theta, etai_over_etat);
 
-        let scatter = if (etai_over_etat * sin_theta > 1.0) || (rng.gen::<f32>() < reflect_prob) {
+        let scatter = if (etai_over_etat * sin_theta > 1.0) || (samples.next_1d() < reflect_prob) {
             // Ray must reflect.
             let reflected = unit_direction.reflect(&rec.normal);
             let scattered = Ray::new(rec.hit_point, reflected, ray_in.time);
@@ -82,28 +82,33 @@ impl Dielectric {
             let refracted = unit_direction.refract(&rec.normal, etai_over_etat);
             let scattered = Ray::new(rec.hit_point, refracted, ray_in.time);
 
-            // Air doesn't absorb light. So, if the ray is hitting the surface
-            // from air, then the absorbance is 0.0. However, if the ray hit the
-            // surface from inside the object, then calculate the absorbance
-            // based on the path length of the ray, the object's albedo, and the
-            // object's density (i.e. using Beer's law).
-            let absorbance = if rec.front_face {
-                vec3!()
-            } else {
-                (Vec3::from(1.0) - self.albedo) * self.density * -rec.t
-            };
-
-            let transparency = vec3!(
-                absorbance[R].exp(),
-                absorbance[G].exp(),
-                absorbance[B].exp(),
-            );
-
-            Scatter::new(transparency, ScatterType::Specular(scattered))
+            Scatter::new(self.transparency(rec), ScatterType::Specular(scattered))
         };
 
         Some(scatter)
     }
+
+    /// How much of each colour channel makes it through the object to the
+    /// hit point `rec`.
+    ///
+    /// Air doesn't absorb light. So, if the ray is hitting the surface from
+    /// air, then the absorbance is 0.0. However, if the ray hit the surface
+    /// from inside the object, then calculate the absorbance based on the path
+    /// length of the ray, the object's albedo, and the object's density (i.e.
+    /// using Beer's law).
+    pub fn transparency(&self, rec: &HitRecord) -> Vec3 {
+        let absorbance = if rec.front_face {
+            vec3!()
+        } else {
+            (Vec3::from(1.0) - self.albedo) * self.density * -rec.t
+        };
+
+        vec3!(
+            absorbance[R].exp(),
+            absorbance[G].exp(),
+            absorbance[B].exp(),
+        )
+    }
 }
 
 /// Helps us get the angle at which the dielectric becomes a mirror.
@@ -113,3 +118,30 @@ fn schlick(cosine: f32, refractive_index: f32) -> f32 {
     r0 = r0 * r0;
     r0 + (1.0 - r0) * (1.0 - cosine).powf(5.0)
 }
+
+/// The exact Fresnel reflectance of a dielectric, for unpolarized light
+/// hitting it at an angle with cosine `cos_i`, going from a refractive index
+/// of η into one of η′, where `eta` is η′/η. Returns 1 for total internal
+/// reflection.
+///
+/// # Usage
+///
+/// ```
+/// # use weekend_tracer_rs::material::dielectric::fresnel;
+/// // Head on, glass reflects about 4% of the light.
+/// assert!((fresnel(1.0, 1.5) - 0.04).abs() < 1e-6);
+/// // And from inside, past the critical angle, it reflects all of it.
+/// assert_eq!(fresnel(0.5, 1.0 / 1.5), 1.0);
+/// ```
+pub fn fresnel(cos_i: f32, eta: f32) -> f32 {
+    let cos_i = cos_i.clamp(0.0, 1.0);
+    let sin2_t = (1.0 - cos_i * cos_i) / (eta * eta);
+    if sin2_t >= 1.0 {
+        return 1.0;
+    }
+    let cos_t = (1.0 - sin2_t).sqrt();
+
+    let parallel = (eta * cos_i - cos_t) / (eta * cos_i + cos_t);
+    let perpendicular = (cos_i - eta * cos_t) / (cos_i + eta * cos_t);
+    0.5 * (parallel * parallel + perpendicular * perpendicular)
+}
@@ -3,11 +3,11 @@ use crate::{
     hittable::{HitRecord, UVCoord},
     material::Scatter,
     ray::Ray,
+    sampler::SampleStream,
     texture::Texture,
     vec3,
     vec3::Vec3,
 };
-use rand::prelude::*;
 
 /// A light-emitting material. Can hold any texture. Will not reflect rays.
 #[derive(Debug, Clone)]
@@ -22,9 +22,9 @@ impl DiffuseLight {
         Self { emit }
     }
 
-    pub fn scatter<R: Rng + ?Sized>(
+    pub fn scatter(
         &self,
-        _rng: &mut R,
+        _samples: &mut SampleStream,
         _ray: &Ray,
         _rec: &HitRecord,
     ) -> Option<Scatter> {
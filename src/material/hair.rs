@@ -0,0 +1,120 @@
+//! A material for hair and other fibers.
+
+use crate::{
+    hittable::HitRecord,
+    material::{Scatter, ScatterType},
+    onb::ONB,
+    ray::Ray,
+    sampler::SampleStream,
+    texture::Texture,
+    vec3::Vec3,
+};
+
+/// A simple hair material, loosely after Marschner et al.
+///
+/// Light leaves a fiber in a cone around it: it keeps going along the fiber
+/// at about the same angle that it came in at, give or take `roughness`. Some
+/// fraction of it (`shine`) bounces straight off the surface without picking
+/// up any colour, which is what gives hair its highlight. The rest goes into
+/// the fiber, gets tinted by the `albedo`, and comes back out in any
+/// direction around it.
+///
+/// Fibers are found from the hit record's `tangent`, so this works best on
+/// `Curve`s. On other surfaces it picks some direction along the surface.
+#[derive(Clone, Debug)]
+pub struct Hair {
+    pub albedo: Texture,
+    pub shine: f32,
+    pub roughness: f32,
+}
+
+impl Hair {
+    /// Create a new hair material. `shine` and `roughness` are clamped to
+    /// [0, 1].
+    pub fn new(albedo: Texture, shine: f32, roughness: f32) -> Self {
+        Self {
+            albedo,
+            shine: shine.clamp(0.0, 1.0),
+            roughness: roughness.clamp(0.0, 1.0),
+        }
+    }
+
+    /// Scatter a ray into the cone around the fiber.
+    pub fn scatter(
+        &self,
+        samples: &mut SampleStream,
+        ray_in: &Ray,
+        rec: &HitRecord,
+    ) -> Option<Scatter> {
+        let direction = ray_in.direction.unit_vector();
+        let tangent = rec
+            .tangent
+            .unwrap_or_else(|| ONB::build_from_w(rec.normal).u);
+        // The normal and the binormal span the plane across the fiber.
+        let normal = (rec.normal - tangent * rec.normal.dot(&tangent)).unit_vector();
+        let binormal = tangent.cross(&normal);
+
+        let lobe = samples.next_1d();
+        let (u, v) = samples.next_2d();
+
+        // Keep about the same angle along the fiber.
+        let sin_theta =
+            (direction.dot(&tangent) + self.roughness * (2.0 * u - 1.0)).clamp(-1.0, 1.0);
+        let cos_theta = (1.0 - sin_theta * sin_theta).sqrt();
+
+        let (phi, attenuation) = if lobe < self.shine {
+            // Bounce off the surface, mirroring the way the ray came in
+            // around the normal.
+            let phi = direction.dot(&binormal).atan2(-direction.dot(&normal));
+            let jitter = self.roughness * std::f32::consts::PI * (v - 0.5);
+            (phi + jitter, Vec3::from(1.0))
+        } else {
+            (
+                2.0 * std::f32::consts::PI * v,
+                self.albedo.0(rec.uv, &rec.hit_point),
+            )
+        };
+
+        let scattered =
+            tangent * sin_theta + cos_theta * (normal * phi.cos() + binormal * phi.sin());
+        Some(Scatter::new(
+            attenuation,
+            ScatterType::Specular(Ray::new(rec.hit_point, scattered, ray_in.time)),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{hittable::HitRecord, material::Material, texture, vec3};
+    use rand::prelude::*;
+    use rand_chacha::ChaCha8Rng;
+    use std::sync::Arc;
+
+    #[test]
+    fn highlights_are_white_and_the_rest_is_tinted() {
+        let albedo = vec3!(0.6, 0.3, 0.1);
+        let mut rng = ChaCha8Rng::seed_from_u64(22);
+        let mut stream = SampleStream::independent(&mut rng);
+        let ray = Ray::new(vec3!(0.0, 1.0, 1.0), vec3!(0.0, -1.0, -1.0), 0.0);
+
+        for &(shine, expected) in &[(1.0, Vec3::from(1.0)), (0.0, albedo)] {
+            let material = Arc::new(Material::hair(texture::constant(albedo), shine, 0.3));
+            let mut rec = HitRecord::new(
+                &ray,
+                1.0,
+                vec3!(),
+                vec3!(0.0, 1.0, 0.0),
+                material.clone(),
+                (0.0, 0.0),
+            );
+            rec.tangent = Some(vec3!(1.0, 0.0, 0.0));
+
+            for _ in 0..100 {
+                let scatter = material.scatter(&mut stream, &ray, &rec).unwrap();
+                assert_eq!(scatter.attenuation, expected);
+            }
+        }
+    }
+}
@@ -4,10 +4,10 @@ use crate::{
     hittable::HitRecord,
     material::{Scatter, ScatterType},
     ray::Ray,
+    sampler::SampleStream,
     texture::Texture,
     vec3::Vec3,
 };
-use rand::prelude::*;
 
 /// An isotropic scattering function. Rays have a chance of scattering, and will
 /// scatter in a uniform random direction.
@@ -24,13 +24,17 @@ impl Isotropic {
     }
 
     /// Scatter a ray randomly in a uniform direction.
-    pub fn scatter<R: Rng + ?Sized>(
+    pub fn scatter(
         &self,
-        rng: &mut R,
+        samples: &mut SampleStream,
         ray: &Ray,
         rec: &HitRecord,
     ) -> Option<Scatter> {
-        let scattered = Ray::new(rec.hit_point, Vec3::random_in_unit_sphere(rng), ray.time);
+        let scattered = Ray::new(
+            rec.hit_point,
+            Vec3::on_unit_sphere(samples.next_2d()),
+            ray.time,
+        );
         let attenutation = self.albedo.0(rec.uv, &rec.hit_point);
         Some(Scatter::new(attenutation, ScatterType::Specular(scattered)))
     }
@@ -5,6 +5,7 @@ use crate::{
     material::{Scatter, ScatterType},
     pdf::PDF,
     ray::Ray,
+    sampler::SampleStream,
     texture::Texture,
 };
 use rand::Rng;
@@ -21,9 +22,9 @@ impl Lambertian {
         Self { albedo }
     }
 
-    pub fn scatter<R: Rng + ?Sized>(
+    pub fn scatter(
         &self,
-        _rng: &mut R,
+        _samples: &mut SampleStream,
         _ray_in: &Ray,
         rec: &HitRecord,
     ) -> Option<Scatter> {
@@ -1,16 +1,19 @@
 //! Materials. Allows for easy interchangibility between material types on
 //! different objects.
 
+pub mod conductor;
 pub mod dielectric;
 pub mod diffuse_light;
+pub mod hair;
 pub mod isotropic;
 pub mod lambertian;
-pub mod metal;
+pub mod rough_dielectric;
 
 use crate::{
     hittable::{HitRecord, UVCoord},
     pdf::PDF,
     ray::Ray,
+    sampler::SampleStream,
     texture::Texture,
     vec3,
     vec3::Vec3,
@@ -46,10 +49,12 @@ impl Scatter {
 #[derive(Clone, Debug)]
 pub enum Material {
     Lambertian(lambertian::Lambertian),
-    Metal(metal::Metal),
+    Conductor(conductor::Conductor),
     Dielectric(dielectric::Dielectric),
+    RoughDielectric(rough_dielectric::RoughDielectric),
     DiffuseLight(diffuse_light::DiffuseLight),
     Isotropic(isotropic::Isotropic),
+    Hair(hair::Hair),
 }
 
 impl Material {
@@ -58,9 +63,16 @@ impl Material {
         Self::Lambertian(lambertian::Lambertian::new(albedo))
     }
 
-    /// Create a new metallic material.
-    pub fn metal(albedo: Vec3, fuzz: f32) -> Self {
-        Self::Metal(metal::Metal::new(albedo, fuzz))
+    /// Create a new rough conductor (a physically based metal). See
+    /// `conductor::Conductor::new`.
+    pub fn conductor(ior: conductor::Ior, roughness: f32) -> Self {
+        Self::Conductor(conductor::Conductor::new(ior, roughness))
+    }
+
+    /// Create a new rough conductor that's rougher along one direction than
+    /// the other. See `conductor::Conductor::anisotropic`.
+    pub fn anisotropic_conductor(ior: conductor::Ior, roughness: (f32, f32)) -> Self {
+        Self::Conductor(conductor::Conductor::anisotropic(ior, roughness))
     }
 
     /// Create a new dielectric material.
@@ -77,6 +89,22 @@ impl Material {
         ))
     }
 
+    /// Create a new rough dielectric, like frosted glass. See
+    /// `rough_dielectric::RoughDielectric::new`.
+    pub fn rough_dielectric(
+        albedo: Vec3,
+        refractive_index: f32,
+        density: f32,
+        roughness: f32,
+    ) -> Self {
+        Self::RoughDielectric(rough_dielectric::RoughDielectric::new(
+            albedo,
+            refractive_index,
+            density,
+            roughness,
+        ))
+    }
+
     /// Create a new diffuse light.
     pub fn diffuse_light(emit: Texture) -> Self {
         Self::DiffuseLight(diffuse_light::DiffuseLight::new(emit))
@@ -88,36 +116,50 @@ impl Material {
         Self::Isotropic(isotropic::Isotropic::new(albedo))
     }
 
+    /// Create a new hair material, for `Curve`s.
+    pub fn hair(albedo: Texture, shine: f32, roughness: f32) -> Self {
+        Self::Hair(hair::Hair::new(albedo, shine, roughness))
+    }
+
     /// Scatter a ray off a material. Will delegate to the material's
     /// implementation of `scatter()`. Returns `Some(Scatter)` if the ray is
-    /// scattered, `None` if it isn't.
-    pub fn scatter<R: Rng + ?Sized>(
+    /// scattered, `None` if it isn't. Any random choices are made with the
+    /// next values of `samples`.
+    pub fn scatter(
         &self,
-        rng: &mut R,
+        samples: &mut SampleStream,
         ray: &Ray,
         rec: &HitRecord,
     ) -> Option<Scatter> {
         match rec.material.as_ref() {
-            Material::Lambertian(l) => l.scatter(rng, ray, rec),
-            Material::Metal(m) => m.scatter(rng, ray, rec),
-            Material::Dielectric(d) => d.scatter(rng, ray, rec),
-            Material::DiffuseLight(dl) => dl.scatter(rng, ray, rec),
-            Material::Isotropic(i) => i.scatter(rng, ray, rec),
+            Material::Lambertian(l) => l.scatter(samples, ray, rec),
+            Material::Conductor(c) => c.scatter(samples, ray, rec),
+            Material::Dielectric(d) => d.scatter(samples, ray, rec),
+            Material::RoughDielectric(d) => d.scatter(samples, ray, rec),
+            Material::DiffuseLight(dl) => dl.scatter(samples, ray, rec),
+            Material::Isotropic(i) => i.scatter(samples, ray, rec),
+            Material::Hair(h) => h.scatter(samples, ray, rec),
         }
     }
 
-    /// Samples a PDF for some ray, its scattered counterpart, and a hit record.
-    /// Allows for things like biasing rays towards light sources.
+    /// How much light gets scattered from `scattered` back along `ray_in`,
+    /// for materials that scatter a PDF: the BRDF (or BSDF, for materials
+    /// that let light through) times the cosine of the angle between
+    /// `scattered` and the normal. It's the same for each colour channel,
+    /// except on materials like `Conductor` whose colour depends on the
+    /// angle.
     pub fn scattering_pdf<R: Rng + ?Sized>(
         &self,
         rng: &mut R,
         ray_in: &Ray,
         rec: &HitRecord,
         scattered: &Ray,
-    ) -> f32 {
+    ) -> Vec3 {
         match rec.material.as_ref() {
-            Material::Lambertian(l) => l.scattering_pdf(rng, ray_in, rec, scattered),
-            _ => 0.0,
+            Material::Lambertian(l) => Vec3::from(l.scattering_pdf(rng, ray_in, rec, scattered)),
+            Material::Conductor(c) => c.scattering_pdf(ray_in, rec, scattered),
+            Material::RoughDielectric(d) => d.scattering_pdf(ray_in, rec, scattered),
+            _ => vec3!(0.0),
         }
     }
 
@@ -0,0 +1,232 @@
+//! A rough dielectric, like frosted glass, with GGX microfacet reflections
+//! and refractions.
+//!
+//! Each microfacet is a tiny piece of smooth glass. How much light it
+//! reflects, rather than lets through, comes from the exact Fresnel equations
+//! for a dielectric. Light that travels through the object gets absorbed
+//! according to its `albedo` and `density`, just like with `Dielectric`.
+//!
+//! Unlike `Dielectric`, this scatters a PDF, so light sampling works with it:
+//! lights can be seen through frosted glass, not just hit by chance.
+
+use crate::{
+    hittable::HitRecord,
+    material::{
+        conductor::SMOOTH, dielectric::fresnel, dielectric::Dielectric, Scatter, ScatterType,
+    },
+    onb::ONB,
+    pdf::{
+        ggx_pdf::{refraction_half_vector, TrowbridgeReitz},
+        PDF,
+    },
+    ray::Ray,
+    sampler::SampleStream,
+    vec3,
+    vec3::{Axis::*, Vec3},
+};
+
+/// A rough dielectric.
+#[derive(Debug, Clone, Copy)]
+pub struct RoughDielectric {
+    /// The refractive index, albedo and density, which work the same as for
+    /// a smooth dielectric.
+    pub dielectric: Dielectric,
+    /// The GGX roughness (alpha).
+    pub alpha: f32,
+}
+
+impl RoughDielectric {
+    /// Create a new rough dielectric. `roughness` goes from 0 (smooth glass)
+    /// to 1, and is squared to get the GGX alpha, like for a `Conductor`.
+    pub fn new(albedo: Vec3, refractive_index: f32, density: f32, roughness: f32) -> Self {
+        Self {
+            dielectric: Dielectric::new_with_albedo(albedo, refractive_index, density),
+            alpha: roughness.clamp(0.0, 1.0).powi(2),
+        }
+    }
+
+    pub fn scatter(
+        &self,
+        samples: &mut SampleStream,
+        ray_in: &Ray,
+        rec: &HitRecord,
+    ) -> Option<Scatter> {
+        if self.alpha < SMOOTH {
+            return self.dielectric.scatter(samples, ray_in, rec);
+        }
+
+        // Reflecting and refracting both happen in `scattering_pdf`, so only
+        // the light absorbed on the way here is left for the attenuation.
+        let pdf = PDF::ggx_refraction(
+            ONB::build_from_w(rec.normal),
+            -ray_in.direction,
+            self.eta(rec),
+            (self.alpha, self.alpha),
+            samples.next_1d(),
+        );
+        Some(Scatter::new(
+            self.dielectric.transparency(rec),
+            ScatterType::PDF(pdf),
+        ))
+    }
+
+    /// The BSDF times the cosine of the scattered direction. For reflections,
+    /// that's `F(wo⋅h) D(h) G(wo, wi) / (4 cos(wo))`. For refractions, it's
+    /// `(1 - F(wo⋅h)) D(h) G(wo, wi) η² |wi⋅h| (wo⋅h) / (cos(wo) (wo⋅h + η wi⋅h)²)`.
+    ///
+    /// Like `Dielectric`, this doesn't scale the light by 1/η² as it goes
+    /// through the surface. For objects surrounded by air, that would cancel
+    /// out on the way back out anyway.
+    pub fn scattering_pdf(&self, ray_in: &Ray, rec: &HitRecord, scattered: &Ray) -> Vec3 {
+        let uvw = ONB::build_from_w(rec.normal);
+        let wo = uvw.coords(&-ray_in.direction.unit_vector());
+        let wi = uvw.coords(&scattered.direction.unit_vector());
+        if wo[Z] <= 0.0 || wi[Z] == 0.0 {
+            return vec3!(0.0);
+        }
+
+        let eta = self.eta(rec);
+        let distribution = TrowbridgeReitz::new((self.alpha, self.alpha));
+
+        let value = if wi[Z] > 0.0 {
+            let h = (wo + wi).unit_vector();
+            fresnel(wo.dot(&h), eta)
+                * distribution.distribution(&h)
+                * distribution.masking_shadowing(&wo, &wi)
+                / (4.0 * wo[Z])
+        } else {
+            let h = match refraction_half_vector(&wo, &wi, eta) {
+                Some(h) => h,
+                None => return vec3!(0.0),
+            };
+            let (cos_o, cos_i) = (wo.dot(&h), wi.dot(&h));
+            let denominator = cos_o + eta * cos_i;
+            (1.0 - fresnel(cos_o, eta))
+                * distribution.distribution(&h)
+                * distribution.masking_shadowing(&wo, &wi)
+                * eta
+                * eta
+                * cos_i.abs()
+                * cos_o
+                / (wo[Z] * denominator * denominator)
+        };
+
+        Vec3::from(value)
+    }
+
+    /// The refractive index on the far side of the surface, over the one on
+    /// the side the ray came from. The object is always surrounded by air.
+    fn eta(&self, rec: &HitRecord) -> f32 {
+        if rec.front_face {
+            self.dielectric.refractive_index
+        } else {
+            1.0 / self.dielectric.refractive_index
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{material::Material, sampler::SampleStream};
+    use rand::prelude::*;
+    use rand_chacha::ChaCha8Rng;
+    use std::sync::Arc;
+
+    /// Scatter lots of rays coming along `incoming` off a rough dielectric,
+    /// and add up how much light gets reflected and how much refracted.
+    fn furnace(roughness: f32, incoming: Vec3, inside: bool) -> (f32, f32) {
+        let mut rng = ChaCha8Rng::seed_from_u64(25);
+        let mut stream_rng = ChaCha8Rng::seed_from_u64(0);
+        let mut stream = SampleStream::independent(&mut stream_rng);
+
+        let material = Arc::new(Material::rough_dielectric(
+            Vec3::from(1.0),
+            1.5,
+            0.0,
+            roughness,
+        ));
+        let normal = vec3!(0.0, 1.0, 0.0);
+        let outward_normal = if inside { -normal } else { normal };
+        let rec = HitRecord::new(
+            &Ray::new(normal, -normal, 0.0),
+            1.0,
+            vec3!(),
+            outward_normal,
+            material.clone(),
+            (0.0, 0.0),
+        );
+        let ray_in = Ray::new(-incoming, incoming, 0.0);
+
+        let samples = 100_000;
+        let (mut reflected, mut refracted) = (0.0, 0.0);
+        for _ in 0..samples {
+            let scatter = material.scatter(&mut stream, &ray_in, &rec).unwrap();
+            let pdf = match scatter.scattered {
+                ScatterType::PDF(pdf) => pdf,
+                _ => panic!("rough dielectrics should scatter a PDF"),
+            };
+            let scattered = Ray::new(vec3!(), pdf.generate((rng.gen(), rng.gen())), 0.0);
+            let value = material.scattering_pdf(&mut rng, &ray_in, &rec, &scattered)[0]
+                / pdf.value(&scattered.direction);
+            if scattered.direction.dot(&normal) > 0.0 {
+                reflected += value;
+            } else {
+                refracted += value;
+            }
+        }
+
+        (reflected / samples as f32, refracted / samples as f32)
+    }
+
+    #[test]
+    fn rough_glass_doesnt_make_light() {
+        // A furnace test, without any absorption. Light that bounces off more
+        // than one microfacet is lost, which adds up when it's rough and
+        // glancing, especially with total internal reflection from inside.
+        let head_on = vec3!(0.0, -1.0, 0.0);
+        let glancing = vec3!(1.0, -0.5, 0.5);
+        for &(roughness, incoming, inside, min) in &[
+            (0.3, head_on, false, 0.98),
+            (0.3, head_on, true, 0.98),
+            (0.6, glancing, false, 0.88),
+            (0.6, glancing, true, 0.7),
+        ] {
+            let (reflected, refracted) = furnace(roughness, incoming, inside);
+            let total = reflected + refracted;
+            assert!(
+                total <= 1.0 && total > min,
+                "{}, {:?}, {}: {}",
+                roughness,
+                incoming,
+                inside,
+                total
+            );
+        }
+    }
+
+    #[test]
+    fn nearly_smooth_glass_reflects_like_smooth_glass() {
+        for &(incoming, inside) in &[
+            (vec3!(0.0, -1.0, 0.0), false),
+            (vec3!(1.0, -1.0, 0.0), false),
+            (vec3!(0.3, -1.0, 0.0), true),
+        ] {
+            let cos = -incoming.unit_vector()[Y];
+            let eta = if inside { 1.0 / 1.5 } else { 1.5 };
+            let (reflected, _) = furnace(0.05, incoming, inside);
+            assert!(
+                (reflected - fresnel(cos, eta)).abs() < 0.01,
+                "{:?}, {}: {} vs {}",
+                incoming,
+                inside,
+                reflected,
+                fresnel(cos, eta)
+            );
+        }
+
+        // Past the critical angle, everything gets reflected.
+        let (reflected, refracted) = furnace(0.05, vec3!(1.0, -0.5, 0.0), true);
+        assert!(reflected > 0.98 && refracted < 0.01);
+    }
+}
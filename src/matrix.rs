@@ -0,0 +1,278 @@
+//! 4x4 matrices, for affine transformations of points, vectors and normals.
+//!
+//! The matrices are row-major, and they multiply column vectors. So, in
+//! `b * a`, `a` happens first. Points get the translation part of a matrix,
+//! but vectors (like directions) don't.
+
+use crate::{aabb::AABB, ray::Ray, util::deg_to_rad, vec3, vec3::Vec3};
+use std::ops::Mul;
+
+/// A 4x4 matrix. `self.0[row][column]`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Matrix4(pub [[f32; 4]; 4]);
+
+impl Default for Matrix4 {
+    fn default() -> Self {
+        Self::identity()
+    }
+}
+
+impl Matrix4 {
+    /// The matrix that doesn't do anything.
+    pub fn identity() -> Self {
+        Self([
+            [1.0, 0.0, 0.0, 0.0],
+            [0.0, 1.0, 0.0, 0.0],
+            [0.0, 0.0, 1.0, 0.0],
+            [0.0, 0.0, 0.0, 1.0],
+        ])
+    }
+
+    /// Moves points by `offset`.
+    pub fn translation(offset: Vec3) -> Self {
+        let mut m = Self::identity();
+        for i in 0..3 {
+            m.0[i][3] = offset[i];
+        }
+        m
+    }
+
+    /// Scales each axis by the matching component of `factor`.
+    pub fn scaling(factor: Vec3) -> Self {
+        let mut m = Self::identity();
+        for i in 0..3 {
+            m.0[i][i] = factor[i];
+        }
+        m
+    }
+
+    /// Rotates counterclockwise by `angle` degrees about `axis` (when looking
+    /// down the axis towards the origin), using Rodrigues' rotation formula.
+    /// `axis` doesn't need to be a unit vector.
+    ///
+    /// # Usage
+    ///
+    /// ```
+    /// # use weekend_tracer_rs::{matrix::Matrix4, vec3::Vec3};
+    /// let quarter_turn = Matrix4::rotation(Vec3::new(0.0, 0.0, 1.0), 90.0);
+    /// let y = quarter_turn.transform_vector(Vec3::new(1.0, 0.0, 0.0));
+    ///
+    /// assert!((y - Vec3::new(0.0, 1.0, 0.0)).length() < 1e-6);
+    /// ```
+    pub fn rotation(axis: Vec3, angle: f32) -> Self {
+        let k = axis.unit_vector();
+        let radians = deg_to_rad(angle);
+        let (sin, cos) = radians.sin_cos();
+
+        // R = cos(θ)⋅I + sin(θ)⋅[k]ₓ + (1 - cos(θ))⋅k⋅kᵀ
+        let cross = [[0.0, -k[2], k[1]], [k[2], 0.0, -k[0]], [-k[1], k[0], 0.0]];
+        let mut m = Self::identity();
+        for (i, row) in cross.iter().enumerate() {
+            for (j, cross) in row.iter().enumerate() {
+                let identity = if i == j { cos } else { 0.0 };
+                m.0[i][j] = identity + sin * cross + (1.0 - cos) * k[i] * k[j];
+            }
+        }
+        m
+    }
+
+    /// Shears each axis by the others. For example, `xy` is how far x moves
+    /// for every unit of y:
+    ///
+    /// ```text
+    /// x' = x + xy⋅y + xz⋅z
+    /// y' = y + yx⋅x + yz⋅z
+    /// z' = z + zx⋅x + zy⋅y
+    /// ```
+    pub fn shearing(xy: f32, xz: f32, yx: f32, yz: f32, zx: f32, zy: f32) -> Self {
+        Self([
+            [1.0, xy, xz, 0.0],
+            [yx, 1.0, yz, 0.0],
+            [zx, zy, 1.0, 0.0],
+            [0.0, 0.0, 0.0, 1.0],
+        ])
+    }
+
+    /// Swaps rows and columns.
+    pub fn transpose(&self) -> Self {
+        let mut m = Self::identity();
+        for i in 0..4 {
+            for j in 0..4 {
+                m.0[i][j] = self.0[j][i];
+            }
+        }
+        m
+    }
+
+    /// The determinant of the upper 3x3 part of the matrix, which is how
+    /// much it scales volumes by. Negative if it mirrors things.
+    pub fn determinant3(&self) -> f32 {
+        let m = &self.0;
+        m[0][0] * (m[1][1] * m[2][2] - m[1][2] * m[2][1])
+            - m[0][1] * (m[1][0] * m[2][2] - m[1][2] * m[2][0])
+            + m[0][2] * (m[1][0] * m[2][1] - m[1][1] * m[2][0])
+    }
+
+    /// The inverse of the matrix, using Gauss-Jordan elimination. `None` if
+    /// the matrix can't be inverted (like a scaling by 0).
+    ///
+    /// # Usage
+    ///
+    /// ```
+    /// # use weekend_tracer_rs::{matrix::Matrix4, vec3::Vec3};
+    /// let m = Matrix4::translation(Vec3::new(1.0, 2.0, 3.0)) * Matrix4::scaling(Vec3::from(2.0));
+    /// let p = Vec3::new(4.0, 5.0, 6.0);
+    ///
+    /// let back = m.inverse().unwrap().transform_point(m.transform_point(p));
+    ///
+    /// assert!((back - p).length() < 1e-6);
+    /// assert!(Matrix4::scaling(Vec3::new(1.0, 0.0, 1.0)).inverse().is_none());
+    /// ```
+    pub fn inverse(&self) -> Option<Self> {
+        // Work in f64, so that the inverse stays close to exact.
+        let mut a = [[0.0f64; 8]; 4];
+        for (i, (row, source)) in a.iter_mut().zip(self.0.iter()).enumerate() {
+            for (x, &s) in row.iter_mut().zip(source.iter()) {
+                *x = s as f64;
+            }
+            row[4 + i] = 1.0;
+        }
+
+        for column in 0..4 {
+            // Use the biggest pivot we can find, for stability.
+            let pivot = (column..4)
+                .max_by(|&x, &y| a[x][column].abs().total_cmp(&a[y][column].abs()))
+                .unwrap();
+            if a[pivot][column].abs() < 1e-12 {
+                return None;
+            }
+            a.swap(column, pivot);
+
+            let scale = 1.0 / a[column][column];
+            a[column].iter_mut().for_each(|x| *x *= scale);
+
+            for row in 0..4 {
+                if row != column {
+                    let factor = a[row][column];
+                    let pivot_row = a[column];
+                    for (x, p) in a[row].iter_mut().zip(pivot_row.iter()) {
+                        *x -= factor * p;
+                    }
+                }
+            }
+        }
+
+        let mut m = Self::identity();
+        for (row, solved) in m.0.iter_mut().zip(a.iter()) {
+            for (x, &s) in row.iter_mut().zip(solved[4..].iter()) {
+                *x = s as f32;
+            }
+        }
+        Some(m)
+    }
+
+    /// Transform a point, including the translation.
+    pub fn transform_point(&self, p: Vec3) -> Vec3 {
+        let m = &self.0;
+        let row = |i: usize| m[i][0] * p[0] + m[i][1] * p[1] + m[i][2] * p[2] + m[i][3];
+        vec3!(row(0), row(1), row(2))
+    }
+
+    /// Transform a vector, leaving out the translation.
+    pub fn transform_vector(&self, v: Vec3) -> Vec3 {
+        let m = &self.0;
+        let row = |i: usize| m[i][0] * v[0] + m[i][1] * v[1] + m[i][2] * v[2];
+        vec3!(row(0), row(1), row(2))
+    }
+
+    /// Transform a vector by the transpose of this matrix. Normals have to be
+    /// transformed by the inverse transpose of whatever transforms the
+    /// surface, so this is meant to be called on the inverse. The result
+    /// isn't normalized.
+    pub fn transform_normal(&self, n: Vec3) -> Vec3 {
+        let m = &self.0;
+        let column = |j: usize| m[0][j] * n[0] + m[1][j] * n[1] + m[2][j] * n[2];
+        vec3!(column(0), column(1), column(2))
+    }
+
+    /// Transform a ray's origin and direction. The direction isn't
+    /// normalized afterwards, so a point at some `t` along the ray ends up at
+    /// the same `t` along the transformed ray.
+    pub fn transform_ray(&self, ray: &Ray) -> Ray {
+        Ray::new(
+            self.transform_point(ray.origin),
+            self.transform_vector(ray.direction),
+            ray.time,
+        )
+    }
+
+    /// The smallest axis-aligned box around a transformed box.
+    ///
+    /// Each component of a transformed corner is a sum of one term per axis,
+    /// so the smallest and largest ones come from picking the smallest or
+    /// largest term on each axis separately (Arvo's method). This is as tight
+    /// as transforming all eight corners.
+    pub fn transform_aabb(&self, bbox: &AABB) -> AABB {
+        let mut min = Vec3::default();
+        let mut max = Vec3::default();
+        for (i, row) in self.0.iter().take(3).enumerate() {
+            min[i] = row[3];
+            max[i] = row[3];
+            for (j, &factor) in row.iter().take(3).enumerate() {
+                let a = factor * bbox.min[j];
+                let b = factor * bbox.max[j];
+                min[i] += a.min(b);
+                max[i] += a.max(b);
+            }
+        }
+        AABB::new(min, max)
+    }
+}
+
+impl Mul for Matrix4 {
+    type Output = Self;
+
+    fn mul(self, rhs: Self) -> Self {
+        let mut m = [[0.0; 4]; 4];
+        for (i, row) in m.iter_mut().enumerate() {
+            for (j, value) in row.iter_mut().enumerate() {
+                *value = (0..4).map(|k| self.0[i][k] * rhs.0[k][j]).sum();
+            }
+        }
+        Self(m)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn close(a: &Matrix4, b: &Matrix4) -> bool {
+        a.0.iter()
+            .flatten()
+            .zip(b.0.iter().flatten())
+            .all(|(x, y)| (x - y).abs() < 1e-5)
+    }
+
+    #[test]
+    fn inverse_undoes_the_matrix() {
+        let m = Matrix4::translation(vec3!(1.0, -2.0, 3.0))
+            * Matrix4::rotation(vec3!(1.0, 2.0, 3.0), 33.0)
+            * Matrix4::shearing(0.5, 0.0, 0.25, 0.0, 0.0, -1.0)
+            * Matrix4::scaling(vec3!(2.0, 0.5, -1.0));
+        let inverse = m.inverse().unwrap();
+
+        assert!(close(&(m * inverse), &Matrix4::identity()));
+        assert!(close(&(inverse * m), &Matrix4::identity()));
+    }
+
+    #[test]
+    fn rotation_matches_the_axis_rotations() {
+        // Counterclockwise about Y, like `RotateY`.
+        let (sin, cos) = deg_to_rad(30.0).sin_cos();
+        let p = vec3!(1.0, 2.0, 3.0);
+        let rotated = Matrix4::rotation(vec3!(0.0, 2.0, 0.0), 30.0).transform_point(p);
+
+        assert!((rotated - vec3!(cos + 3.0 * sin, 2.0, -sin + 3.0 * cos)).length() < 1e-6);
+    }
+}
@@ -0,0 +1,475 @@
+//! glTF 2.0 scenes, either as `.gltf` (JSON, with its buffers and images in
+//! separate files or embedded as data URIs) or `.glb` (binary, with
+//! everything in the one file).
+//!
+//! Every node in the scene that has a mesh becomes a `TriangleMesh`, with the
+//! node's transform (combined with all of its parents') baked into its vertex
+//! positions and normals. Only triangles (including strips and fans) are
+//! loaded. Points and lines are skipped, as are cameras, lights, skins and
+//! animations.
+//!
+//! glTF's metallic-roughness materials are turned into the closest
+//! `Material`:
+//! - Anything with an emissive colour becomes a `DiffuseLight`.
+//! - Transmissive materials (`KHR_materials_transmission`) become a
+//!   `Dielectric`, with the refractive index from `KHR_materials_ior`.
+//! - Mostly-metallic materials become a `Conductor` that reflects the base
+//!   colour head on, with the same roughness.
+//! - Everything else becomes `Lambertian`.
+//!
+//! Base colour and emissive textures become image textures (see
+//! `texture::from_image`), multiplied by the material's colour. The metallic,
+//! roughness, normal and occlusion maps aren't used.
+
+use crate::{
+    hittable::{
+        triangle::{Face, MeshData, TriangleMesh, Vertex},
+        world::World,
+    },
+    material::{conductor::Ior, Material},
+    mesh::MeshError,
+    texture::{self, Texture},
+    vec3,
+    vec3::Vec3,
+};
+use ::gltf::{
+    buffer, image,
+    mesh::Mode,
+    texture::{Info, WrappingMode},
+    Node,
+};
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+/// A column-major 4x4 matrix, the way glTF stores them.
+type Matrix = [[f32; 4]; 4];
+
+const IDENTITY: Matrix = [
+    [1.0, 0.0, 0.0, 0.0],
+    [0.0, 1.0, 0.0, 0.0],
+    [0.0, 0.0, 1.0, 0.0],
+    [0.0, 0.0, 0.0, 1.0],
+];
+
+/// Load the default scene (or else the first scene) of a glTF file.
+/// Primitives without a material get `default_material`.
+pub fn load<P: AsRef<Path>>(path: P, default_material: Material) -> Result<World, MeshError> {
+    let path = path.as_ref();
+    let base_dir = path.parent().unwrap_or_else(|| Path::new(""));
+
+    let ::gltf::Gltf { document, blob } =
+        ::gltf::Gltf::open(path).map_err(|source| MeshError::Gltf {
+            path: path.to_path_buf(),
+            source,
+        })?;
+
+    let buffers = document
+        .buffers()
+        .map(|buffer| match buffer.source() {
+            buffer::Source::Bin => blob.clone().ok_or_else(|| MeshError::Invalid {
+                path: path.to_path_buf(),
+                message: "a buffer refers to a missing binary chunk".to_string(),
+            }),
+            buffer::Source::Uri(uri) => read_uri(uri, base_dir, path),
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let mut textures = Textures {
+        path,
+        base_dir,
+        buffers: &buffers,
+        images: HashMap::new(),
+    };
+    let materials = document
+        .materials()
+        .map(|material| to_material(&material, &mut textures).map(Arc::new))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let scene = Importer {
+        path,
+        buffers: &buffers,
+        materials,
+        default_material: Arc::new(default_material),
+    };
+
+    let mut world = World::default();
+    if let Some(root) = document
+        .default_scene()
+        .or_else(|| document.scenes().next())
+    {
+        for node in root.nodes() {
+            scene.add_node(&mut world, &node, &IDENTITY)?;
+        }
+    }
+
+    if world.objects.is_empty() {
+        return Err(MeshError::Empty {
+            path: path.to_path_buf(),
+        });
+    }
+
+    Ok(world)
+}
+
+/// Everything needed to turn nodes into meshes.
+struct Importer<'a> {
+    path: &'a Path,
+    buffers: &'a [Vec<u8>],
+    materials: Vec<Arc<Material>>,
+    default_material: Arc<Material>,
+}
+
+impl Importer<'_> {
+    /// Add a node's mesh (if it has one) and all of its children's meshes.
+    fn add_node(&self, world: &mut World, node: &Node, parent: &Matrix) -> Result<(), MeshError> {
+        let transform = multiply(parent, &node.transform().matrix());
+
+        if let Some(mesh) = node.mesh() {
+            let data = self.mesh_data(&mesh, &transform)?;
+            if !data.faces.is_empty() {
+                world.add(Box::new(TriangleMesh::new(data)));
+            }
+        }
+
+        for child in node.children() {
+            self.add_node(world, &child, &transform)?;
+        }
+
+        Ok(())
+    }
+
+    /// Gather all of a mesh's triangles into one `MeshData`, moved into place
+    /// by `transform`.
+    fn mesh_data(&self, mesh: &::gltf::Mesh, transform: &Matrix) -> Result<MeshData, MeshError> {
+        let mut data = MeshData::default();
+        // Which glTF material each of `data.materials` came from.
+        let mut material_indices = Vec::new();
+
+        // Normals need the inverse transpose of the transform. Scaling it by
+        // the determinant doesn't matter, since normals get normalized anyway,
+        // which leaves the cofactor matrix (up to its sign).
+        let [c0, c1, c2] =
+            [0, 1, 2].map(|i| vec3!(transform[i][0], transform[i][1], transform[i][2]));
+        let determinant = c0.dot(&c1.cross(&c2));
+        let cofactors =
+            [c1.cross(&c2), c2.cross(&c0), c0.cross(&c1)].map(|c| determinant.signum() * c);
+
+        for primitive in mesh.primitives() {
+            let reader =
+                primitive.reader(|buffer| self.buffers.get(buffer.index()).map(Vec::as_slice));
+            let positions = match reader.read_positions() {
+                Some(positions) => positions,
+                None => continue,
+            };
+
+            let first = data.positions.len() as u32;
+            data.positions.extend(positions.map(|[x, y, z]| {
+                let p = x * c0 + y * c1 + z * c2;
+                p + vec3!(transform[3][0], transform[3][1], transform[3][2])
+            }));
+            let count = data.positions.len() as u32 - first;
+
+            let has_normals = match reader.read_normals() {
+                Some(normals) => {
+                    data.normals.resize(first as usize, vec3!());
+                    data.normals.extend(normals.map(|[x, y, z]| {
+                        (x * cofactors[0] + y * cofactors[1] + z * cofactors[2]).unit_vector()
+                    }));
+                    data.normals.len() == data.positions.len()
+                }
+                None => false,
+            };
+            let has_uvs = match reader.read_tex_coords(0) {
+                Some(uvs) => {
+                    data.uvs.resize(first as usize, (0.0, 0.0));
+                    // glTF's v goes down the image, but ours goes up.
+                    data.uvs.extend(uvs.into_f32().map(|[u, v]| (u, 1.0 - v)));
+                    data.uvs.len() == data.positions.len()
+                }
+                None => false,
+            };
+
+            let material = primitive.material().index();
+            let material = match material_indices.iter().position(|&m| m == material) {
+                Some(position) => position as u32,
+                None => {
+                    material_indices.push(material);
+                    data.materials.push(match material {
+                        Some(index) => self.materials[index].clone(),
+                        None => self.default_material.clone(),
+                    });
+                    data.materials.len() as u32 - 1
+                }
+            };
+
+            let indices = match reader.read_indices() {
+                Some(indices) => indices.into_u32().collect(),
+                None => (0..count).collect::<Vec<_>>(),
+            };
+            let triangles: Vec<[u32; 3]> = match primitive.mode() {
+                Mode::Triangles => indices
+                    .chunks_exact(3)
+                    .map(|t| [t[0], t[1], t[2]])
+                    .collect(),
+                // Every other triangle in a strip is wound the other way.
+                Mode::TriangleStrip => (2..indices.len())
+                    .map(|i| match i % 2 {
+                        0 => [indices[i - 2], indices[i - 1], indices[i]],
+                        _ => [indices[i - 1], indices[i - 2], indices[i]],
+                    })
+                    .collect(),
+                Mode::TriangleFan => (2..indices.len())
+                    .map(|i| [indices[0], indices[i - 1], indices[i]])
+                    .collect(),
+                _ => continue,
+            };
+
+            for mut triangle in triangles {
+                if triangle.iter().any(|&index| index >= count) {
+                    return Err(MeshError::Invalid {
+                        path: self.path.to_path_buf(),
+                        message: format!(
+                            "a triangle in mesh {} refers to a vertex that doesn't exist",
+                            mesh.index()
+                        ),
+                    });
+                }
+
+                // Mirroring turns clockwise into counterclockwise.
+                if determinant < 0.0 {
+                    triangle.swap(1, 2);
+                }
+
+                let vertex = |index: u32| Vertex {
+                    position: first + index,
+                    normal: if has_normals {
+                        Some(first + index)
+                    } else {
+                        None
+                    },
+                    uv: if has_uvs { Some(first + index) } else { None },
+                };
+                data.faces.push(Face {
+                    vertices: triangle.map(vertex),
+                    material,
+                });
+            }
+        }
+
+        Ok(data)
+    }
+}
+
+/// Multiply two column-major matrices.
+fn multiply(a: &Matrix, b: &Matrix) -> Matrix {
+    let mut product = [[0.0; 4]; 4];
+    for (column, b_column) in product.iter_mut().zip(b) {
+        for (row, value) in column.iter_mut().enumerate() {
+            *value = (0..4).map(|k| a[k][row] * b_column[k]).sum();
+        }
+    }
+    product
+}
+
+/// Read a buffer or image from a base64 data URI, or from a file relative to
+/// the glTF file.
+fn read_uri(uri: &str, base_dir: &Path, path: &Path) -> Result<Vec<u8>, MeshError> {
+    if let Some(data) = uri.strip_prefix("data:") {
+        let invalid = |message: &str| MeshError::Invalid {
+            path: path.to_path_buf(),
+            message: message.to_string(),
+        };
+        let (_, data) = data
+            .split_once(";base64,")
+            .ok_or_else(|| invalid("only base64 data URIs are supported"))?;
+
+        base64::decode(data).map_err(|_| invalid("a data URI has invalid base64 data"))
+    } else {
+        let file = base_dir.join(uri);
+        fs::read(&file).map_err(|source| MeshError::Io { path: file, source })
+    }
+}
+
+/// Decodes each image once, no matter how many materials use it.
+struct Textures<'a> {
+    path: &'a Path,
+    base_dir: &'a Path,
+    buffers: &'a [Vec<u8>],
+    images: HashMap<usize, Texture>,
+}
+
+impl Textures<'_> {
+    /// A texture for a material, or just `color` if there isn't one.
+    /// Otherwise, the texture is tinted by `color`.
+    fn get(&mut self, info: Option<Info>, color: Vec3) -> Result<Texture, MeshError> {
+        let info = match info {
+            Some(info) => info,
+            None => return Ok(texture::constant(color)),
+        };
+
+        let texture = info.texture();
+        let image = self.image(&texture.source())?;
+        let sampler = texture.sampler();
+        let (wrap_u, wrap_v) = (sampler.wrap_s(), sampler.wrap_t());
+
+        Ok(Texture(Arc::new(move |(u, v), p| {
+            color * (image.0)((wrap(wrap_u, u), wrap(wrap_v, v)), p)
+        })))
+    }
+
+    fn image(&mut self, image: &image::Image) -> Result<Texture, MeshError> {
+        if let Some(texture) = self.images.get(&image.index()) {
+            return Ok(texture.clone());
+        }
+
+        let (decoded, path) = match image.source() {
+            image::Source::View { view, .. } => {
+                let bytes = self.buffers[view.buffer().index()]
+                    .get(view.offset()..view.offset() + view.length())
+                    .ok_or_else(|| MeshError::Invalid {
+                        path: self.path.to_path_buf(),
+                        message: format!("image {} is outside of its buffer", image.index()),
+                    })?;
+                (::image::load_from_memory(bytes), self.path.to_path_buf())
+            }
+            image::Source::Uri { uri, .. } if uri.starts_with("data:") => {
+                let bytes = read_uri(uri, self.base_dir, self.path)?;
+                (::image::load_from_memory(&bytes), self.path.to_path_buf())
+            }
+            image::Source::Uri { uri, .. } => {
+                let path: PathBuf = self.base_dir.join(uri);
+                (::image::open(&path), path)
+            }
+        };
+
+        let texture =
+            texture::from_image(decoded.map_err(|source| MeshError::Texture { path, source })?);
+        self.images.insert(image.index(), texture.clone());
+        Ok(texture)
+    }
+}
+
+/// Bring a texture coordinate into [0, 1], the way the texture's sampler
+/// wants. Image textures clamp on their own.
+fn wrap(mode: WrappingMode, x: f32) -> f32 {
+    match mode {
+        WrappingMode::ClampToEdge => x,
+        WrappingMode::Repeat => x.rem_euclid(1.0),
+        WrappingMode::MirroredRepeat => {
+            let x = x.rem_euclid(2.0);
+            if x > 1.0 {
+                2.0 - x
+            } else {
+                x
+            }
+        }
+    }
+}
+
+/// Pick the `Material` that's closest to a glTF material.
+fn to_material(
+    material: &::gltf::Material,
+    textures: &mut Textures,
+) -> Result<Material, MeshError> {
+    let pbr = material.pbr_metallic_roughness();
+    let [r, g, b, _alpha] = pbr.base_color_factor();
+    let base_color = vec3!(r, g, b);
+
+    let [r, g, b] = material.emissive_factor();
+    let emission = material.emissive_strength().unwrap_or(1.0) * vec3!(r, g, b);
+    let transmission = material
+        .transmission()
+        .map_or(0.0, |transmission| transmission.transmission_factor());
+
+    Ok(if emission.length_squared() > 0.0 {
+        Material::diffuse_light(textures.get(material.emissive_texture(), emission)?)
+    } else if transmission > 0.0 {
+        Material::dielectric_with_albedo(base_color, material.ior().unwrap_or(1.5), 0.0)
+    } else if pbr.metallic_factor() >= 0.5 {
+        Material::conductor(Ior::Reflectance(base_color), pbr.roughness_factor())
+    } else {
+        Material::lambertian(textures.get(pbr.base_color_texture(), base_color)?)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{hittable::Hittable, ray::Ray};
+
+    #[test]
+    fn loads_embedded_buffers_with_node_transforms() {
+        // One triangle, with its corners at (0, 0, 0), (1, 0, 0) and
+        // (0, 1, 0), followed by its indices.
+        let mut buffer = Vec::new();
+        [0.0f32, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0]
+            .iter()
+            .for_each(|x| buffer.extend(&x.to_le_bytes()));
+        [0u16, 1, 2]
+            .iter()
+            .for_each(|i| buffer.extend(&i.to_le_bytes()));
+
+        // The child node is scaled by 2, then its parent moves it along Z.
+        let source = format!(
+            r#"{{
+                "asset": {{"version": "2.0"}},
+                "scene": 0,
+                "scenes": [{{"nodes": [0]}}],
+                "nodes": [
+                    {{"translation": [0, 0, -5], "children": [1]}},
+                    {{"scale": [2, 2, 2], "mesh": 0}}
+                ],
+                "meshes": [{{"primitives": [{{
+                    "attributes": {{"POSITION": 0}},
+                    "indices": 1,
+                    "material": 0
+                }}]}}],
+                "materials": [{{"pbrMetallicRoughness": {{
+                    "baseColorFactor": [0.9, 0.8, 0.7, 1],
+                    "metallicFactor": 1,
+                    "roughnessFactor": 0.25
+                }}}}],
+                "accessors": [
+                    {{"bufferView": 0, "componentType": 5126, "count": 3, "type": "VEC3",
+                      "min": [0, 0, 0], "max": [1, 1, 0]}},
+                    {{"bufferView": 1, "componentType": 5123, "count": 3, "type": "SCALAR"}}
+                ],
+                "bufferViews": [
+                    {{"buffer": 0, "byteOffset": 0, "byteLength": 36}},
+                    {{"buffer": 0, "byteOffset": 36, "byteLength": 6}}
+                ],
+                "buffers": [{{
+                    "byteLength": {},
+                    "uri": "data:application/octet-stream;base64,{}"
+                }}]
+            }}"#,
+            buffer.len(),
+            base64::encode(&buffer),
+        );
+
+        let path = std::env::temp_dir().join(format!("gltf-test-{}.gltf", std::process::id()));
+        fs::write(&path, source).unwrap();
+        let default_material = Material::lambertian(texture::constant(Vec3::from(0.5)));
+        let world = load(&path, default_material);
+        fs::remove_file(&path).unwrap();
+        let world = world.unwrap();
+
+        assert_eq!(world.objects.len(), 1);
+
+        // (1.5, 0.25) is only inside the triangle once it's been scaled up.
+        let ray = Ray::new(vec3!(1.5, 0.25, 0.0), vec3!(0.0, 0.0, -1.0), 0.0);
+        let rec = world.hit(&ray, 0.0, 10.0).unwrap();
+        assert!((rec.t - 5.0).abs() < 1e-5);
+        match rec.material.as_ref() {
+            Material::Conductor(c) => {
+                assert_eq!(c.alpha, (0.0625, 0.0625));
+                assert!((c.fresnel(1.0) - vec3!(0.9, 0.8, 0.7)).length() < 1e-4);
+            }
+            m => panic!("expected a conductor, got {:?}", m),
+        }
+    }
+}
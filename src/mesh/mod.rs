@@ -0,0 +1,122 @@
+//! Loading triangle meshes from model files.
+//!
+//! Supported formats:
+//! - Wavefront OBJ (`.obj`), with materials from MTL files. See `obj`.
+//! - PLY (`.ply`), in either its ASCII or binary flavours. See `ply`.
+//! - glTF 2.0 (`.gltf` or `.glb`), which can hold a whole scene's worth of
+//!   meshes, materials and textures. See `gltf`.
+
+pub mod gltf;
+pub mod obj;
+pub mod ply;
+
+use crate::{
+    hittable::{triangle::TriangleMesh, world::World},
+    material::Material,
+};
+use std::{
+    error::Error,
+    fmt, io,
+    path::{Path, PathBuf},
+};
+
+/// Something that went wrong while loading a mesh.
+#[derive(Debug)]
+pub enum MeshError {
+    /// A file couldn't be read.
+    Io { path: PathBuf, source: io::Error },
+    /// A file isn't valid. `line` is 1-based.
+    Parse {
+        path: PathBuf,
+        line: usize,
+        message: String,
+    },
+    /// An image texture couldn't be loaded.
+    Texture {
+        path: PathBuf,
+        source: image::ImageError,
+    },
+    /// A file is readable, but what's in it doesn't make sense. Used for
+    /// problems that don't have a line number, like a bad index in a binary
+    /// file.
+    Invalid { path: PathBuf, message: String },
+    /// A glTF file couldn't be parsed.
+    Gltf {
+        path: PathBuf,
+        source: ::gltf::Error,
+    },
+    /// The file doesn't have any triangles in it.
+    Empty { path: PathBuf },
+    /// The file's extension isn't one of the supported formats.
+    UnsupportedFormat { path: PathBuf },
+}
+
+impl fmt::Display for MeshError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io { path, source } => write!(f, "{}: {}", path.display(), source),
+            Self::Parse {
+                path,
+                line,
+                message,
+            } => write!(f, "{}:{}: {}", path.display(), line, message),
+            Self::Texture { path, source } => write!(
+                f,
+                "could not load image texture {}: {}",
+                path.display(),
+                source
+            ),
+            Self::Invalid { path, message } => write!(f, "{}: {}", path.display(), message),
+            Self::Gltf { path, source } => write!(f, "{}: {}", path.display(), source),
+            Self::Empty { path } => write!(f, "{}: the mesh has no triangles", path.display()),
+            Self::UnsupportedFormat { path } => write!(
+                f,
+                "{}: unsupported mesh format (expected an .obj, .ply, .gltf or .glb file)",
+                path.display()
+            ),
+        }
+    }
+}
+
+impl Error for MeshError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::Io { source, .. } => Some(source),
+            Self::Texture { source, .. } => Some(source),
+            Self::Gltf { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+/// Load a model, picking the format from the file's extension. Faces that
+/// don't have a material of their own get `default_material`.
+///
+/// OBJ and PLY files hold a single mesh, so the `World` will only have one
+/// object in it. glTF files get one object per mesh in the scene.
+pub fn load<P: AsRef<Path>>(path: P, default_material: Material) -> Result<World, MeshError> {
+    let path = path.as_ref();
+    let extension = path
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .map(str::to_ascii_lowercase);
+
+    let data = match extension.as_deref() {
+        Some("obj") => obj::load(path, default_material)?,
+        Some("ply") => ply::load(path, default_material)?,
+        Some("gltf") | Some("glb") => return gltf::load(path, default_material),
+        _ => {
+            return Err(MeshError::UnsupportedFormat {
+                path: path.to_path_buf(),
+            })
+        }
+    };
+
+    if data.faces.is_empty() {
+        return Err(MeshError::Empty {
+            path: path.to_path_buf(),
+        });
+    }
+
+    Ok(World::new(vec![Box::new(TriangleMesh::new(data))]))
+}
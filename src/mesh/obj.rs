@@ -0,0 +1,451 @@
+//! Wavefront OBJ files, and the MTL material libraries that go with them.
+//!
+//! Vertex positions (`v`), texture coordinates (`vt`), normals (`vn`) and
+//! faces (`f`) are read, along with materials (`mtllib` and `usemtl`).
+//! Faces with more than three corners are split into a fan of triangles.
+//! Everything else (groups, smoothing groups, lines, etc...) is ignored.
+//!
+//! MTL materials are turned into `Material`s as best they can be:
+//! - Anything with an emissive colour (`Ke`) becomes a `DiffuseLight`.
+//! - Transparent materials (`d` or `Tr`, or `illum` 4, 6, 7 or 9) become a
+//!   `Dielectric` with the material's refractive index (`Ni`).
+//! - Mirror-like materials (`illum` 3, or a black `Kd` with a non-black `Ks`)
+//!   become a `Conductor` that reflects the specular colour (`Ks`) head on,
+//!   with the specular exponent (`Ns`) setting the roughness.
+//! - Everything else becomes `Lambertian`, using the diffuse texture
+//!   (`map_Kd`) if there is one.
+
+use crate::{
+    hittable::triangle::{Face, MeshData, Vertex},
+    material::{conductor::Ior, Material},
+    mesh::MeshError,
+    texture, vec3,
+    vec3::Vec3,
+};
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::{BufRead, BufReader},
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+/// Load an OBJ file. Faces without a material (or with one that isn't in any
+/// of the file's material libraries) get `default_material`.
+pub fn load<P: AsRef<Path>>(path: P, default_material: Material) -> Result<MeshData, MeshError> {
+    let path = path.as_ref();
+    let file = File::open(path).map_err(|source| MeshError::Io {
+        path: path.to_path_buf(),
+        source,
+    })?;
+
+    parse(BufReader::new(file), path, default_material)
+}
+
+/// Parse an OBJ file. `path` is only used for error messages and to find
+/// material libraries, which are relative to the OBJ file.
+///
+/// # Usage
+///
+/// ```
+/// # use weekend_tracer_rs::{material::Material, mesh::obj, texture, vec3::Vec3};
+/// # use std::path::Path;
+/// let square = "
+///     v 0 0 0
+///     v 1 0 0
+///     v 1 1 0
+///     v 0 1 0
+///     f 1 2 3 4
+/// ";
+/// let material = Material::lambertian(texture::constant(Vec3::from(0.5)));
+/// let mesh = obj::parse(square.as_bytes(), Path::new("square.obj"), material).unwrap();
+///
+/// assert_eq!(mesh.positions.len(), 4);
+/// assert_eq!(mesh.faces.len(), 2);
+/// ```
+pub fn parse<R: BufRead>(
+    reader: R,
+    path: &Path,
+    default_material: Material,
+) -> Result<MeshData, MeshError> {
+    let base_dir = path.parent().unwrap_or_else(|| Path::new(""));
+
+    let mut mesh = MeshData {
+        materials: vec![Arc::new(default_material)],
+        ..MeshData::default()
+    };
+    let mut library = HashMap::new();
+    let mut material_indices = HashMap::new();
+    let mut current_material = 0;
+
+    for (number, line) in reader.lines().enumerate() {
+        let error = |message: String| MeshError::Parse {
+            path: path.to_path_buf(),
+            line: number + 1,
+            message,
+        };
+
+        let line = line.map_err(|source| MeshError::Io {
+            path: path.to_path_buf(),
+            source,
+        })?;
+        let line = line.split('#').next().unwrap_or("").trim();
+
+        let mut tokens = line.split_whitespace();
+        let keyword = match tokens.next() {
+            Some(keyword) => keyword,
+            None => continue,
+        };
+        let rest = line[keyword.len()..].trim();
+
+        match keyword {
+            "v" => {
+                let [x, y, z] = floats(tokens, [0.0; 3], 3).map_err(error)?;
+                mesh.positions.push(vec3!(x, y, z));
+            }
+            "vt" => {
+                let [u, v] = floats(tokens, [0.0; 2], 1).map_err(error)?;
+                mesh.uvs.push((u, v));
+            }
+            "vn" => {
+                let [x, y, z] = floats(tokens, [0.0; 3], 3).map_err(error)?;
+                mesh.normals.push(vec3!(x, y, z).unit_vector());
+            }
+            "f" => {
+                let vertices = tokens
+                    .map(|token| vertex(token, &mesh))
+                    .collect::<Result<Vec<_>, _>>()
+                    .map_err(error)?;
+
+                if vertices.len() < 3 {
+                    return Err(error("a face needs at least 3 vertices".to_string()));
+                }
+
+                for i in 1..vertices.len() - 1 {
+                    mesh.faces.push(Face {
+                        vertices: [vertices[0], vertices[i], vertices[i + 1]],
+                        material: current_material,
+                    });
+                }
+            }
+            "mtllib" => {
+                for name in rest.split_whitespace() {
+                    library.extend(load_library(&base_dir.join(name))?);
+                }
+            }
+            "usemtl" => {
+                current_material = match material_indices.get(rest) {
+                    Some(&index) => index,
+                    None => match library.get(rest) {
+                        Some(mtl) => {
+                            let material = to_material(mtl)?;
+                            mesh.materials.push(Arc::new(material));
+
+                            let index = mesh.materials.len() as u32 - 1;
+                            material_indices.insert(rest.to_string(), index);
+                            index
+                        }
+                        None => 0,
+                    },
+                };
+            }
+            _ => {}
+        }
+    }
+
+    Ok(mesh)
+}
+
+/// Parse up to `N` numbers, of which at least `required` must be there. Any
+/// missing ones keep their value from `defaults`.
+fn floats<'a, I, const N: usize>(
+    tokens: I,
+    defaults: [f32; N],
+    required: usize,
+) -> Result<[f32; N], String>
+where
+    I: Iterator<Item = &'a str>,
+{
+    let mut values = defaults;
+    let mut count = 0;
+
+    for (value, token) in values.iter_mut().zip(tokens) {
+        *value = token
+            .parse()
+            .map_err(|_| format!("{:?} is not a number", token))?;
+        count += 1;
+    }
+
+    if count < required {
+        Err(format!("expected at least {} numbers", required))
+    } else {
+        Ok(values)
+    }
+}
+
+/// Parse a face's vertex, like `3`, `3/1`, `3//2` or `3/1/2`.
+fn vertex(token: &str, mesh: &MeshData) -> Result<Vertex, String> {
+    let mut indices = token.split('/');
+
+    let position = index(indices.next(), mesh.positions.len(), token)?
+        .ok_or_else(|| format!("vertex {:?} has no position", token))?;
+    let uv = index(indices.next(), mesh.uvs.len(), token)?;
+    let normal = index(indices.next(), mesh.normals.len(), token)?;
+
+    Ok(Vertex {
+        position,
+        normal,
+        uv,
+    })
+}
+
+/// Turn a 1-based (or, if negative, relative to the end) OBJ index into a
+/// 0-based one, checking that it's in range.
+fn index(index: Option<&str>, count: usize, token: &str) -> Result<Option<u32>, String> {
+    let index = match index {
+        Some(index) if !index.is_empty() => index,
+        _ => return Ok(None),
+    };
+
+    let index: i64 = index
+        .parse()
+        .map_err(|_| format!("vertex {:?} has an invalid index", token))?;
+    let resolved = if index < 0 {
+        count as i64 + index
+    } else {
+        index - 1
+    };
+
+    if (0..count as i64).contains(&resolved) {
+        Ok(Some(resolved as u32))
+    } else {
+        Err(format!(
+            "vertex {:?} refers to an index that doesn't exist",
+            token
+        ))
+    }
+}
+
+/// A material from an MTL file.
+#[derive(Debug, Clone)]
+struct MtlMaterial {
+    diffuse: Vec3,
+    specular: Vec3,
+    emission: Vec3,
+    shininess: f32,
+    refractive_index: f32,
+    dissolve: f32,
+    illumination: u32,
+    /// Already resolved relative to the MTL file.
+    diffuse_map: Option<PathBuf>,
+}
+
+impl Default for MtlMaterial {
+    fn default() -> Self {
+        Self {
+            diffuse: Vec3::from(0.8),
+            specular: vec3!(),
+            emission: vec3!(),
+            shininess: 0.0,
+            refractive_index: 1.0,
+            dissolve: 1.0,
+            illumination: 2,
+            diffuse_map: None,
+        }
+    }
+}
+
+/// Load every material in an MTL file.
+fn load_library(path: &Path) -> Result<HashMap<String, MtlMaterial>, MeshError> {
+    let file = File::open(path).map_err(|source| MeshError::Io {
+        path: path.to_path_buf(),
+        source,
+    })?;
+    let base_dir = path.parent().unwrap_or_else(|| Path::new(""));
+
+    let mut materials = HashMap::new();
+    let mut current: Option<(String, MtlMaterial)> = None;
+
+    for (number, line) in BufReader::new(file).lines().enumerate() {
+        let error = |message: String| MeshError::Parse {
+            path: path.to_path_buf(),
+            line: number + 1,
+            message,
+        };
+
+        let line = line.map_err(|source| MeshError::Io {
+            path: path.to_path_buf(),
+            source,
+        })?;
+        let line = line.split('#').next().unwrap_or("").trim();
+
+        let mut tokens = line.split_whitespace();
+        let keyword = match tokens.next() {
+            Some(keyword) => keyword,
+            None => continue,
+        };
+
+        if keyword == "newmtl" {
+            materials.extend(current.take());
+            let name = line[keyword.len()..].trim().to_string();
+            current = Some((name, MtlMaterial::default()));
+            continue;
+        }
+
+        let material = match &mut current {
+            Some((_, material)) => material,
+            None => continue,
+        };
+
+        let color = |tokens| {
+            floats(tokens, [f32::NAN; 3], 1).map(|[r, g, b]| {
+                // A single number is a grey.
+                if g.is_nan() || b.is_nan() {
+                    Vec3::from(r)
+                } else {
+                    vec3!(r, g, b)
+                }
+            })
+        };
+        let number = |tokens| floats(tokens, [0.0], 1).map(|[x]| x);
+
+        match keyword {
+            "Kd" => material.diffuse = color(tokens).map_err(error)?,
+            "Ks" => material.specular = color(tokens).map_err(error)?,
+            "Ke" => material.emission = color(tokens).map_err(error)?,
+            "Ns" => material.shininess = number(tokens).map_err(error)?,
+            "Ni" => material.refractive_index = number(tokens).map_err(error)?,
+            "d" => material.dissolve = number(tokens).map_err(error)?,
+            "Tr" => material.dissolve = 1.0 - number(tokens).map_err(error)?,
+            "illum" => material.illumination = number(tokens).map_err(error)? as u32,
+            // Texture options come before the file name.
+            "map_Kd" => material.diffuse_map = tokens.last().map(|name| base_dir.join(name)),
+            _ => {}
+        }
+    }
+
+    materials.extend(current);
+    Ok(materials)
+}
+
+/// Pick the `Material` that's closest to an MTL material.
+fn to_material(mtl: &MtlMaterial) -> Result<Material, MeshError> {
+    let is_black = |color: Vec3| color[0] <= 0.0 && color[1] <= 0.0 && color[2] <= 0.0;
+
+    Ok(if !is_black(mtl.emission) {
+        Material::diffuse_light(texture::constant(mtl.emission))
+    } else if mtl.dissolve < 1.0 || [4, 6, 7, 9].contains(&mtl.illumination) {
+        // Plenty of exporters leave the refractive index at 1, which would
+        // make the material invisible. Glass is a better guess.
+        let refractive_index = if mtl.refractive_index > 1.0 {
+            mtl.refractive_index
+        } else {
+            1.5
+        };
+        Material::dielectric(refractive_index, 0.0)
+    } else if mtl.illumination == 3 || (is_black(mtl.diffuse) && !is_black(mtl.specular)) {
+        // Turn the Phong exponent into a microfacet alpha, and that into a
+        // roughness.
+        let alpha = (2.0 / (mtl.shininess.max(0.0) + 2.0)).sqrt();
+        Material::conductor(Ior::Reflectance(mtl.specular), alpha.sqrt())
+    } else {
+        let albedo = match &mtl.diffuse_map {
+            Some(path) => texture::try_image(path).map_err(|source| MeshError::Texture {
+                path: path.clone(),
+                source,
+            })?,
+            None => texture::constant(mtl.diffuse),
+        };
+        Material::lambertian(albedo)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn default_material() -> Material {
+        Material::lambertian(texture::constant(Vec3::from(0.5)))
+    }
+
+    #[test]
+    fn reads_normals_uvs_and_negative_indices() {
+        let source = "
+            # A quad, with every kind of vertex.
+            v 0 0 0
+            v 1 0 0
+            v 1 1 0
+            v 0 1 0
+            vt 0 0
+            vt 1 0
+            vt 1 1
+            vn 0 0 2
+            f 1 2/1 -2/2/1 -1//1
+        ";
+        let mesh = parse(source.as_bytes(), Path::new("quad.obj"), default_material()).unwrap();
+
+        assert_eq!(mesh.faces.len(), 2);
+        assert_eq!(mesh.normals, vec![vec3!(0.0, 0.0, 1.0)]);
+
+        let [a, b, c] = mesh.faces[0].vertices;
+        assert_eq!(a, Vertex::new(0));
+        assert_eq!((b.position, b.uv, b.normal), (1, Some(0), None));
+        assert_eq!((c.position, c.uv, c.normal), (2, Some(1), Some(0)));
+        assert_eq!(mesh.faces[1].vertices[2].position, 3);
+    }
+
+    #[test]
+    fn bad_indices_are_reported() {
+        let err = parse(
+            "v 0 0 0\nv 1 0 0\nf 1 2 3\n".as_bytes(),
+            Path::new("bad.obj"),
+            default_material(),
+        )
+        .unwrap_err();
+
+        assert!(matches!(err, MeshError::Parse { line: 3, .. }));
+    }
+
+    #[test]
+    fn materials_come_from_the_library() {
+        let dir = std::env::temp_dir().join(format!("obj-test-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(
+            dir.join("materials.mtl"),
+            "newmtl light\nKe 4 4 4\n\nnewmtl glass\nNi 1.33\nd 0.2\n\nnewmtl mirror\nillum 3\nKs 0.9\nNs 1000\n",
+        )
+        .unwrap();
+
+        let source = "
+            mtllib materials.mtl
+            v 0 0 0
+            v 1 0 0
+            v 0 1 0
+            f 1 2 3
+            usemtl glass
+            f 1 2 3
+            usemtl mirror
+            f 1 2 3
+            usemtl light
+            f 1 2 3
+            usemtl glass
+            f 1 2 3
+            usemtl missing
+            f 1 2 3
+        ";
+        let mesh = parse(source.as_bytes(), &dir.join("test.obj"), default_material());
+        fs::remove_dir_all(&dir).unwrap();
+        let mesh = mesh.unwrap();
+
+        let materials = mesh.faces.iter().map(|f| f.material).collect::<Vec<_>>();
+        assert_eq!(materials, vec![0, 1, 2, 3, 1, 0]);
+
+        assert!(matches!(*mesh.materials[0], Material::Lambertian(_)));
+        assert!(
+            matches!(*mesh.materials[1], Material::Dielectric(ref d) if d.refractive_index == 1.33)
+        );
+        assert!(matches!(*mesh.materials[2], Material::Conductor(ref c) if c.alpha.0 < 0.1));
+        assert!(matches!(*mesh.materials[3], Material::DiffuseLight(_)));
+    }
+}
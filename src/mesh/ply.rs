@@ -0,0 +1,551 @@
+//! PLY (Stanford polygon) files, as written by most 3D scanners.
+//!
+//! ASCII, binary little-endian and binary big-endian files are all supported.
+//! From the `vertex` element, positions (`x`, `y`, `z`), normals (`nx`, `ny`,
+//! `nz`) and texture coordinates (`u`/`v`, `s`/`t` or `texture_u`/
+//! `texture_v`) are read. From the `face` element, the `vertex_indices` (or
+//! `vertex_index`) list is read, and faces with more than three corners are
+//! split into a fan of triangles. Any other elements and properties (vertex
+//! colours, edges, etc...) are skipped over.
+//!
+//! PLY files don't have materials, so every face gets the default material.
+
+use crate::{
+    hittable::triangle::{Face, MeshData, Vertex},
+    material::Material,
+    mesh::MeshError,
+    vec3,
+    vec3::Vec3,
+};
+use std::{
+    fs::File,
+    io::{BufRead, BufReader},
+    path::Path,
+    sync::Arc,
+};
+
+/// Load a PLY file. Every face gets `default_material`.
+pub fn load<P: AsRef<Path>>(path: P, default_material: Material) -> Result<MeshData, MeshError> {
+    let path = path.as_ref();
+    let file = File::open(path).map_err(|source| MeshError::Io {
+        path: path.to_path_buf(),
+        source,
+    })?;
+
+    parse(BufReader::new(file), path, default_material)
+}
+
+/// Parse a PLY file. `path` is only used for error messages.
+///
+/// # Usage
+///
+/// ```
+/// # use weekend_tracer_rs::{material::Material, mesh::ply, texture, vec3::Vec3};
+/// # use std::path::Path;
+/// let square = "ply
+/// format ascii 1.0
+/// element vertex 4
+/// property float x
+/// property float y
+/// property float z
+/// element face 1
+/// property list uchar int vertex_indices
+/// end_header
+/// 0 0 0
+/// 1 0 0
+/// 1 1 0
+/// 0 1 0
+/// 4 0 1 2 3
+/// ";
+/// let material = Material::lambertian(texture::constant(Vec3::from(0.5)));
+/// let mesh = ply::parse(square.as_bytes(), Path::new("square.ply"), material).unwrap();
+///
+/// assert_eq!(mesh.positions.len(), 4);
+/// assert_eq!(mesh.faces.len(), 2);
+/// ```
+pub fn parse<R: BufRead>(
+    reader: R,
+    path: &Path,
+    default_material: Material,
+) -> Result<MeshData, MeshError> {
+    let mut body = Body {
+        reader,
+        path,
+        format: Format::Ascii,
+        line: 0,
+        tokens: Vec::new(),
+        next_token: 0,
+    };
+    let elements = header(&mut body)?;
+
+    let mut mesh = MeshData {
+        materials: vec![Arc::new(default_material)],
+        ..MeshData::default()
+    };
+    let mut has_normals = false;
+    let mut has_uvs = false;
+
+    for element in &elements {
+        match element.name.as_str() {
+            "vertex" => {
+                let slot = |names: &[&str]| {
+                    element
+                        .properties
+                        .iter()
+                        .position(|property| names.contains(&property.name.as_str()))
+                };
+                let position = [slot(&["x"]), slot(&["y"]), slot(&["z"])];
+                let normal = [slot(&["nx"]), slot(&["ny"]), slot(&["nz"])];
+                let uv = [
+                    slot(&["u", "s", "texture_u", "texture_s"]),
+                    slot(&["v", "t", "texture_v", "texture_t"]),
+                ];
+
+                if position.iter().any(Option::is_none) {
+                    return Err(body.error("vertices need x, y and z properties".to_string()));
+                }
+                has_normals = normal.iter().all(Option::is_some);
+                has_uvs = uv.iter().all(Option::is_some);
+
+                let mut values = vec![0.0; element.properties.len()];
+                for _ in 0..element.count {
+                    body.start_instance()?;
+                    for (value, property) in values.iter_mut().zip(&element.properties) {
+                        *value = match property.kind {
+                            Kind::Scalar(scalar) => body.read(scalar)? as f32,
+                            Kind::List { .. } => {
+                                body.skip(property.kind)?;
+                                0.0
+                            }
+                        };
+                    }
+
+                    let get = |slot: Option<usize>| slot.map_or(0.0, |slot| values[slot]);
+                    mesh.positions.push(vec3!(
+                        get(position[0]),
+                        get(position[1]),
+                        get(position[2])
+                    ));
+                    if has_normals {
+                        mesh.normals.push(
+                            vec3!(get(normal[0]), get(normal[1]), get(normal[2])).unit_vector(),
+                        );
+                    }
+                    if has_uvs {
+                        mesh.uvs.push((get(uv[0]), get(uv[1])));
+                    }
+                }
+            }
+            "face" => {
+                let indices = element.properties.iter().position(|property| {
+                    property.name == "vertex_indices" || property.name == "vertex_index"
+                });
+
+                for _ in 0..element.count {
+                    body.start_instance()?;
+                    let mut corners = Vec::new();
+
+                    for (i, property) in element.properties.iter().enumerate() {
+                        match property.kind {
+                            Kind::List { count, item } if Some(i) == indices => {
+                                let count = body.read(count)? as usize;
+                                corners.clear();
+                                for _ in 0..count {
+                                    corners.push(body.read(item)? as i64);
+                                }
+                            }
+                            kind => body.skip(kind)?,
+                        }
+                    }
+
+                    if corners.len() < 3 {
+                        return Err(body.error("a face needs at least 3 vertices".to_string()));
+                    }
+
+                    let vertices = corners
+                        .iter()
+                        .map(|&index| {
+                            if (0..mesh.positions.len() as i64).contains(&index) {
+                                let index = index as u32;
+                                Ok(Vertex {
+                                    position: index,
+                                    normal: if has_normals { Some(index) } else { None },
+                                    uv: if has_uvs { Some(index) } else { None },
+                                })
+                            } else {
+                                Err(body.error(format!(
+                                    "a face refers to vertex {}, which doesn't exist",
+                                    index
+                                )))
+                            }
+                        })
+                        .collect::<Result<Vec<_>, _>>()?;
+
+                    for i in 1..vertices.len() - 1 {
+                        mesh.faces.push(Face {
+                            vertices: [vertices[0], vertices[i], vertices[i + 1]],
+                            material: 0,
+                        });
+                    }
+                }
+            }
+            _ => {
+                for _ in 0..element.count {
+                    body.start_instance()?;
+                    for property in &element.properties {
+                        body.skip(property.kind)?;
+                    }
+                }
+            }
+        }
+    }
+
+    Ok(mesh)
+}
+
+/// How the body of the file is stored.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Format {
+    Ascii,
+    BinaryLittleEndian,
+    BinaryBigEndian,
+}
+
+/// The type of a single number in the file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Scalar {
+    I8,
+    U8,
+    I16,
+    U16,
+    I32,
+    U32,
+    F32,
+    F64,
+}
+
+impl Scalar {
+    fn parse(name: &str) -> Option<Self> {
+        Some(match name {
+            "char" | "int8" => Self::I8,
+            "uchar" | "uint8" => Self::U8,
+            "short" | "int16" => Self::I16,
+            "ushort" | "uint16" => Self::U16,
+            "int" | "int32" => Self::I32,
+            "uint" | "uint32" => Self::U32,
+            "float" | "float32" => Self::F32,
+            "double" | "float64" => Self::F64,
+            _ => return None,
+        })
+    }
+
+    /// The size in bytes, in a binary file.
+    fn size(self) -> usize {
+        match self {
+            Self::I8 | Self::U8 => 1,
+            Self::I16 | Self::U16 => 2,
+            Self::I32 | Self::U32 | Self::F32 => 4,
+            Self::F64 => 8,
+        }
+    }
+}
+
+/// The type of a property: either a single number, or a list of numbers that
+/// starts with how many there are.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Kind {
+    Scalar(Scalar),
+    List { count: Scalar, item: Scalar },
+}
+
+#[derive(Debug, Clone)]
+struct Property {
+    name: String,
+    kind: Kind,
+}
+
+/// A kind of thing in the file (like vertices or faces), how many of them
+/// there are, and what each one is made of.
+#[derive(Debug, Clone)]
+struct Element {
+    name: String,
+    count: usize,
+    properties: Vec<Property>,
+}
+
+/// Read the header, up to and including `end_header`. Sets the body's format.
+fn header<R: BufRead>(body: &mut Body<R>) -> Result<Vec<Element>, MeshError> {
+    let mut elements: Vec<Element> = Vec::new();
+    let mut format = None;
+
+    loop {
+        let line = body
+            .read_line()?
+            .ok_or_else(|| body.error("the file ended before the end of the header".to_string()))?;
+        let tokens = line.split_whitespace().collect::<Vec<_>>();
+
+        match tokens.as_slice() {
+            ["ply"] if body.line == 1 => {}
+            _ if body.line == 1 => return Err(body.error("not a PLY file".to_string())),
+            ["format", name, _version] => {
+                format = Some(match *name {
+                    "ascii" => Format::Ascii,
+                    "binary_little_endian" => Format::BinaryLittleEndian,
+                    "binary_big_endian" => Format::BinaryBigEndian,
+                    _ => return Err(body.error(format!("unknown format {:?}", name))),
+                });
+            }
+            ["element", name, count] => elements.push(Element {
+                name: name.to_string(),
+                count: count
+                    .parse()
+                    .map_err(|_| body.error(format!("{:?} is not a count", count)))?,
+                properties: Vec::new(),
+            }),
+            ["property", rest @ ..] => {
+                let scalar = |name: &str| {
+                    Scalar::parse(name)
+                        .ok_or_else(|| body.error(format!("unknown type {:?}", name)))
+                };
+                let property = match rest {
+                    ["list", count, item, name] => Property {
+                        name: name.to_string(),
+                        kind: Kind::List {
+                            count: scalar(count)?,
+                            item: scalar(item)?,
+                        },
+                    },
+                    [kind, name] => Property {
+                        name: name.to_string(),
+                        kind: Kind::Scalar(scalar(kind)?),
+                    },
+                    _ => return Err(body.error("invalid property".to_string())),
+                };
+
+                elements
+                    .last_mut()
+                    .ok_or_else(|| body.error("a property needs an element".to_string()))?
+                    .properties
+                    .push(property);
+            }
+            ["end_header"] => break,
+            // Comments, `obj_info` and blank lines.
+            _ => {}
+        }
+    }
+
+    body.format = format.ok_or_else(|| body.error("the header has no format".to_string()))?;
+    Ok(elements)
+}
+
+/// Reads numbers out of the file, one at a time, in whichever format it's in.
+struct Body<'a, R> {
+    reader: R,
+    path: &'a Path,
+    format: Format,
+    /// The number of lines read so far. Only counted in the header and in
+    /// ASCII files.
+    line: usize,
+    /// The current line's numbers, for ASCII files.
+    tokens: Vec<String>,
+    next_token: usize,
+}
+
+impl<'a, R: BufRead> Body<'a, R> {
+    /// An error at the current line, or without a line number in the middle
+    /// of a binary file.
+    fn error(&self, message: String) -> MeshError {
+        match self.format {
+            Format::Ascii => MeshError::Parse {
+                path: self.path.to_path_buf(),
+                line: self.line,
+                message,
+            },
+            _ => MeshError::Invalid {
+                path: self.path.to_path_buf(),
+                message,
+            },
+        }
+    }
+
+    fn io_error(&self, source: std::io::Error) -> MeshError {
+        MeshError::Io {
+            path: self.path.to_path_buf(),
+            source,
+        }
+    }
+
+    fn read_line(&mut self) -> Result<Option<String>, MeshError> {
+        let mut line = String::new();
+        match self.reader.read_line(&mut line) {
+            Ok(0) => Ok(None),
+            Ok(_) => {
+                self.line += 1;
+                Ok(Some(line))
+            }
+            Err(source) => Err(self.io_error(source)),
+        }
+    }
+
+    /// Get ready to read an element. In an ASCII file, each one is on its own
+    /// line.
+    fn start_instance(&mut self) -> Result<(), MeshError> {
+        if self.format != Format::Ascii {
+            return Ok(());
+        }
+
+        loop {
+            let line = self
+                .read_line()?
+                .ok_or_else(|| self.error("the file ended too soon".to_string()))?;
+
+            self.tokens = line.split_whitespace().map(str::to_string).collect();
+            self.next_token = 0;
+            if !self.tokens.is_empty() {
+                return Ok(());
+            }
+        }
+    }
+
+    fn read(&mut self, scalar: Scalar) -> Result<f64, MeshError> {
+        let big_endian = match self.format {
+            Format::Ascii => {
+                let token = self
+                    .tokens
+                    .get(self.next_token)
+                    .ok_or_else(|| self.error("not enough numbers on the line".to_string()))?;
+                self.next_token += 1;
+
+                return token
+                    .parse()
+                    .map_err(|_| self.error(format!("{:?} is not a number", token)));
+            }
+            Format::BinaryLittleEndian => false,
+            Format::BinaryBigEndian => true,
+        };
+
+        let mut bytes = [0; 8];
+        let bytes = &mut bytes[..scalar.size()];
+        self.reader
+            .read_exact(bytes)
+            .map_err(|source| self.io_error(source))?;
+        if big_endian {
+            bytes.reverse();
+        }
+
+        Ok(match scalar {
+            Scalar::I8 => bytes[0] as i8 as f64,
+            Scalar::U8 => bytes[0] as f64,
+            Scalar::I16 => i16::from_le_bytes([bytes[0], bytes[1]]) as f64,
+            Scalar::U16 => u16::from_le_bytes([bytes[0], bytes[1]]) as f64,
+            Scalar::I32 => i32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as f64,
+            Scalar::U32 => u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as f64,
+            Scalar::F32 => f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as f64,
+            Scalar::F64 => {
+                let mut array = [0; 8];
+                array.copy_from_slice(bytes);
+                f64::from_le_bytes(array)
+            }
+        })
+    }
+
+    /// Read a property and throw it away.
+    fn skip(&mut self, kind: Kind) -> Result<(), MeshError> {
+        match kind {
+            Kind::Scalar(scalar) => {
+                self.read(scalar)?;
+            }
+            Kind::List { count, item } => {
+                let count = self.read(count)? as usize;
+                for _ in 0..count {
+                    self.read(item)?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::texture;
+
+    fn default_material() -> Material {
+        Material::lambertian(texture::constant(Vec3::from(0.5)))
+    }
+
+    #[test]
+    fn reads_binary_files_with_extra_properties() {
+        let header = "ply
+format binary_big_endian 1.0
+comment colours and the face flags should be skipped
+element vertex 3
+property float x
+property float y
+property float z
+property uchar red
+property float nx
+property float ny
+property float nz
+property float s
+property float t
+element face 1
+property uchar flags
+property list uchar uint vertex_indices
+end_header
+";
+        let mut file = header.as_bytes().to_vec();
+        for (i, position) in [[0.0f32, 0.0, 0.0], [1.0, 0.0, 0.0], [0.0, 1.0, 0.0]]
+            .iter()
+            .enumerate()
+        {
+            position.iter().for_each(|x| file.extend(&x.to_be_bytes()));
+            file.push(255);
+            [0.0f32, 0.0, 2.0]
+                .iter()
+                .chain(&[i as f32, 0.5])
+                .for_each(|x| file.extend(&x.to_be_bytes()));
+        }
+        file.extend(&[7, 3]);
+        [0u32, 1, 2]
+            .iter()
+            .for_each(|i| file.extend(&i.to_be_bytes()));
+
+        let mesh = parse(&file[..], Path::new("scan.ply"), default_material()).unwrap();
+
+        assert_eq!(mesh.positions[1], vec3!(1.0, 0.0, 0.0));
+        assert_eq!(mesh.normals[2], vec3!(0.0, 0.0, 1.0));
+        assert_eq!(mesh.uvs[2], (2.0, 0.5));
+        assert_eq!(mesh.faces.len(), 1);
+        assert_eq!(
+            mesh.faces[0].vertices[2],
+            Vertex {
+                position: 2,
+                normal: Some(2),
+                uv: Some(2)
+            }
+        );
+    }
+
+    #[test]
+    fn bad_indices_are_reported() {
+        let source = "ply
+format ascii 1.0
+element vertex 2
+property float x
+property float y
+property float z
+element face 1
+property list uchar int vertex_indices
+end_header
+0 0 0
+1 0 0
+3 0 1 2
+";
+        let err = parse(source.as_bytes(), Path::new("bad.ply"), default_material()).unwrap_err();
+
+        assert!(matches!(err, MeshError::Parse { line: 12, .. }));
+    }
+}
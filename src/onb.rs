@@ -43,6 +43,22 @@ impl ONB {
     pub fn local_coords(&self, a: f32, b: f32, c: f32) -> Vec3 {
         a * self.u + b * self.v + c * self.w
     }
+
+    /// Get the coordinates of a vector along each of the basis vectors. This
+    /// undoes `local`.
+    ///
+    /// # Usage
+    ///
+    /// ```
+    /// # use weekend_tracer_rs::{onb::ONB, vec3::Vec3};
+    /// let basis = ONB::build_from_w(Vec3::new(1.0, 2.0, 3.0));
+    /// let a = Vec3::new(-4.0, 5.0, 0.5);
+    ///
+    /// assert!((basis.local(&basis.coords(&a)) - a).length() < 1e-5);
+    /// ```
+    pub fn coords(&self, a: &Vec3) -> Vec3 {
+        vec3!(a.dot(&self.u), a.dot(&self.v), a.dot(&self.w))
+    }
 }
 
 impl Default for ONB {
@@ -1,7 +1,6 @@
 //! A cosine density PDF.
 
 use crate::{onb::ONB, vec3::Vec3};
-use rand::prelude::*;
 
 #[derive(Debug, Clone, Copy)]
 pub struct CosinePDF {
@@ -25,7 +24,7 @@ impl CosinePDF {
         }
     }
 
-    pub fn generate<R: Rng + ?Sized>(&self, rng: &mut R) -> Vec3 {
-        self.uvw.local(&Vec3::random_cosine_direction(rng))
+    pub fn generate(&self, sample: (f32, f32)) -> Vec3 {
+        self.uvw.local(&Vec3::cosine_direction(sample))
     }
 }
@@ -0,0 +1,378 @@
+//! PDFs for the GGX (Trowbridge-Reitz) microfacet distribution, which sample
+//! reflections off rough surfaces, and refractions through them.
+
+use crate::{
+    material::dielectric::fresnel,
+    onb::ONB,
+    vec3,
+    vec3::{Axis::*, Vec3},
+};
+
+/// The GGX (Trowbridge-Reitz) distribution of microfacet normals. Directions
+/// are in a local space where the surface normal is +Z, and `alpha.0` and
+/// `alpha.1` are the roughness along X and Y. They're the same for an
+/// isotropic surface.
+#[derive(Debug, Clone, Copy)]
+pub struct TrowbridgeReitz {
+    pub alpha: (f32, f32),
+}
+
+impl TrowbridgeReitz {
+    pub fn new(alpha: (f32, f32)) -> Self {
+        Self { alpha }
+    }
+
+    /// How much of the surface faces the direction `h`.
+    pub fn distribution(&self, h: &Vec3) -> f32 {
+        if h[Z] <= 0.0 {
+            return 0.0;
+        }
+
+        let (ax, ay) = self.alpha;
+        let d = (h[X] / ax).powi(2) + (h[Y] / ay).powi(2) + h[Z] * h[Z];
+        1.0 / (std::f32::consts::PI * ax * ay * d * d)
+    }
+
+    /// Smith's Λ function, for how much of the surface is hidden by the rest
+    /// of it when looking from `w`.
+    pub fn lambda(&self, w: &Vec3) -> f32 {
+        let (ax, ay) = self.alpha;
+        let tan2 = ((ax * w[X]).powi(2) + (ay * w[Y]).powi(2)) / (w[Z] * w[Z]);
+        if tan2.is_infinite() {
+            return f32::INFINITY;
+        }
+        ((1.0 + tan2).sqrt() - 1.0) / 2.0
+    }
+
+    /// The fraction of microfacets that can be seen from `w`.
+    pub fn masking(&self, w: &Vec3) -> f32 {
+        1.0 / (1.0 + self.lambda(w))
+    }
+
+    /// The fraction of microfacets that can be seen from both `wo` and `wi`
+    /// (height-correlated).
+    pub fn masking_shadowing(&self, wo: &Vec3, wi: &Vec3) -> f32 {
+        1.0 / (1.0 + self.lambda(wo) + self.lambda(wi))
+    }
+
+    /// Pick a microfacet normal that can be seen from `wo`, from a point
+    /// `sample` in the unit square. This is Heitz's method from "Sampling the
+    /// GGX Distribution of Visible Normals" (2018).
+    pub fn sample_visible_normal(&self, wo: &Vec3, (u1, u2): (f32, f32)) -> Vec3 {
+        let (ax, ay) = self.alpha;
+
+        // Stretch the view direction so the distribution becomes a
+        // hemisphere.
+        let vh = vec3!(ax * wo[X], ay * wo[Y], wo[Z]).unit_vector();
+        let length_squared = vh[X] * vh[X] + vh[Y] * vh[Y];
+        let t1 = if length_squared > 0.0 {
+            vec3!(-vh[Y], vh[X], 0.0) / length_squared.sqrt()
+        } else {
+            vec3!(1.0, 0.0, 0.0)
+        };
+        let t2 = vh.cross(&t1);
+
+        // Pick a point on the projected hemisphere.
+        let r = u1.sqrt();
+        let phi = 2.0 * std::f32::consts::PI * u2;
+        let p1 = r * phi.cos();
+        let s = 0.5 * (1.0 + vh[Z]);
+        let p2 = (1.0 - s) * (1.0 - p1 * p1).sqrt() + s * r * phi.sin();
+        let nh = p1 * t1 + p2 * t2 + (1.0 - p1 * p1 - p2 * p2).max(0.0).sqrt() * vh;
+
+        // And squash it back.
+        vec3!(ax * nh[X], ay * nh[Y], nh[Z].max(0.0)).unit_vector()
+    }
+
+    /// The density of reflected directions `wi` that come from sampling
+    /// visible normals for `wo`.
+    pub fn reflection_pdf(&self, wo: &Vec3, wi: &Vec3) -> f32 {
+        if wo[Z] <= 0.0 {
+            return 0.0;
+        }
+
+        let h = (*wo + *wi).unit_vector();
+        if wo.dot(&h) <= 0.0 {
+            return 0.0;
+        }
+
+        // D_wo(h) / (4 wo⋅h), where D_wo(h) = G1(wo) max(0, wo⋅h) D(h) / cos(wo).
+        self.masking(wo) * self.distribution(&h) / (4.0 * wo[Z])
+    }
+}
+
+/// A PDF for directions reflected off a rough surface, from a view direction
+/// `wo` that points away from the surface.
+#[derive(Debug, Clone, Copy)]
+pub struct GGXPDF {
+    pub uvw: ONB,
+    /// The view direction, in `uvw` space.
+    pub wo: Vec3,
+    pub distribution: TrowbridgeReitz,
+}
+
+impl GGXPDF {
+    /// Create a new GGX PDF. The surface normal is `uvw.w`, and `wo` is in
+    /// world space.
+    pub fn new(uvw: ONB, wo: Vec3, alpha: (f32, f32)) -> Self {
+        Self {
+            uvw,
+            wo: uvw.coords(&wo.unit_vector()),
+            distribution: TrowbridgeReitz::new(alpha),
+        }
+    }
+
+    pub fn value(&self, direction: &Vec3) -> f32 {
+        let wi = self.uvw.coords(&direction.unit_vector());
+        self.distribution.reflection_pdf(&self.wo, &wi)
+    }
+
+    pub fn generate(&self, sample: (f32, f32)) -> Vec3 {
+        let h = self.distribution.sample_visible_normal(&self.wo, sample);
+        let wi = 2.0 * self.wo.dot(&h) * h - self.wo;
+        self.uvw.local(&wi)
+    }
+}
+
+/// The microfacet normal that refracts `wo` into `wi`, through a surface
+/// with a relative refractive index `eta` (the index on the far side, over
+/// the one `wo` is on). Both are in local space, and `wo` should be above
+/// the surface.
+///
+/// # Returns:
+///
+/// - `None` if no microfacet can refract `wo` into `wi`.
+/// - `Some(Vec3)` with the normal, facing up, otherwise.
+pub fn refraction_half_vector(wo: &Vec3, wi: &Vec3, eta: f32) -> Option<Vec3> {
+    if wo[Z] <= 0.0 {
+        return None;
+    }
+
+    let h = *wo + eta * *wi;
+    if h.length_squared() == 0.0 {
+        return None;
+    }
+    let h = if h[Z] < 0.0 { -h } else { h }.unit_vector();
+
+    // Both directions have to be on the right side of the microfacet too.
+    if wo.dot(&h) <= 0.0 || wi.dot(&h) >= 0.0 {
+        return None;
+    }
+    Some(h)
+}
+
+/// A PDF for directions reflected off or refracted through a rough
+/// dielectric, from a view direction `wo` that points away from the surface.
+///
+/// A visible normal gets picked first, and then the direction reflects off it
+/// with a chance given by the Fresnel equations, or refracts through it
+/// otherwise. `generate` only takes two numbers, so the choice between the
+/// two is made with `choice`, which should be a fresh random number in
+/// [0, 1) each time the PDF is made.
+#[derive(Debug, Clone, Copy)]
+pub struct GGXRefractionPDF {
+    pub uvw: ONB,
+    /// The view direction, in `uvw` space.
+    pub wo: Vec3,
+    /// The refractive index under the surface, over the one above it.
+    pub eta: f32,
+    pub distribution: TrowbridgeReitz,
+    pub choice: f32,
+}
+
+impl GGXRefractionPDF {
+    /// Create a new GGX refraction PDF. The surface normal is `uvw.w`, on the
+    /// same side as `wo`, which is in world space.
+    pub fn new(uvw: ONB, wo: Vec3, eta: f32, alpha: (f32, f32), choice: f32) -> Self {
+        Self {
+            uvw,
+            wo: uvw.coords(&wo.unit_vector()),
+            eta,
+            distribution: TrowbridgeReitz::new(alpha),
+            choice,
+        }
+    }
+
+    pub fn value(&self, direction: &Vec3) -> f32 {
+        let wo = self.wo;
+        let wi = self.uvw.coords(&direction.unit_vector());
+        if wo[Z] <= 0.0 {
+            return 0.0;
+        }
+
+        // Some microfacets reflect or refract to the "wrong" side of the
+        // surface, so any direction could have come from either.
+        let h = (wo + wi).unit_vector();
+        let reflection = fresnel(wo.dot(&h), self.eta) * self.distribution.reflection_pdf(&wo, &wi);
+
+        let refraction = match refraction_half_vector(&wo, &wi, self.eta) {
+            Some(h) => {
+                // D_wo(h), times how much the refraction squashes directions
+                // around h: η² |wi⋅h| / (wo⋅h + η wi⋅h)².
+                let (cos_o, cos_i) = (wo.dot(&h), wi.dot(&h));
+                let visible =
+                    self.distribution.masking(&wo) * cos_o * self.distribution.distribution(&h)
+                        / wo[Z];
+                let denominator = cos_o + self.eta * cos_i;
+                (1.0 - fresnel(cos_o, self.eta)) * visible * self.eta * self.eta * cos_i.abs()
+                    / (denominator * denominator)
+            }
+            None => 0.0,
+        };
+
+        reflection + refraction
+    }
+
+    pub fn generate(&self, sample: (f32, f32)) -> Vec3 {
+        let wo = self.wo;
+        let h = self.distribution.sample_visible_normal(&wo, sample);
+        let cos_o = wo.dot(&h);
+
+        // Total internal reflection has a Fresnel reflectance of 1, so it
+        // always reflects here.
+        let wi = if self.choice < fresnel(cos_o, self.eta) {
+            2.0 * cos_o * h - wo
+        } else {
+            let sin2_i = (1.0 - cos_o * cos_o).max(0.0) / (self.eta * self.eta);
+            let cos_i = (1.0 - sin2_i).max(0.0).sqrt();
+            -wo / self.eta + (cos_o / self.eta - cos_i) * h
+        };
+        self.uvw.local(&wi)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::prelude::*;
+    use rand_chacha::ChaCha8Rng;
+
+    #[test]
+    fn pdfs_integrate_to_one() {
+        let mut rng = ChaCha8Rng::seed_from_u64(24);
+        let uvw = ONB::build_from_w(vec3!(0.0, 0.0, 1.0));
+        let wo = vec3!(0.6, -0.2, 0.4);
+
+        for &alpha in &[(0.3, 0.3), (0.6, 0.6), (0.2, 0.7)] {
+            let pdf = GGXPDF::new(uvw, wo, alpha);
+
+            // Sampled directions can point under the surface, so this
+            // integrates over the whole sphere.
+            let samples = 200_000;
+            let total: f32 = (0..samples)
+                .map(|_| pdf.value(&Vec3::on_unit_sphere((rng.gen(), rng.gen()))))
+                .sum();
+            let integral = total * 4.0 * std::f32::consts::PI / samples as f32;
+            assert!((integral - 1.0).abs() < 0.05, "{:?}: {}", alpha, integral);
+
+            // The sampled directions should follow the PDF: averaging
+            // g / pdf over them and g over even directions (where the PDF
+            // isn't 0) should give the same integral of g.
+            let g = |w: &Vec3| w[Z].max(0.0);
+            let sampled: f32 = (0..samples)
+                .map(|_| {
+                    let wi = pdf.generate((rng.gen(), rng.gen()));
+                    g(&wi.unit_vector()) / pdf.value(&wi)
+                })
+                .sum::<f32>()
+                / samples as f32;
+            let even: f32 = (0..samples)
+                .map(|_| {
+                    let wi = Vec3::on_unit_sphere((rng.gen(), rng.gen()));
+                    if pdf.value(&wi) > 0.0 {
+                        g(&wi)
+                    } else {
+                        0.0
+                    }
+                })
+                .sum::<f32>()
+                * 4.0
+                * std::f32::consts::PI
+                / samples as f32;
+            assert!(
+                (sampled - even).abs() < 0.05 * even,
+                "{:?}: {} vs {}",
+                alpha,
+                sampled,
+                even
+            );
+        }
+    }
+
+    #[test]
+    fn refraction_pdfs_integrate_to_one() {
+        let mut rng = ChaCha8Rng::seed_from_u64(25);
+        let uvw = ONB::build_from_w(vec3!(0.0, 0.0, 1.0));
+        let wo = vec3!(0.6, -0.2, 0.4);
+
+        // Going into glass, and coming out of it (with some total internal
+        // reflection).
+        for &(eta, alpha) in &[(1.5, 0.2), (1.5, 0.6), (1.0 / 1.5, 0.3)] {
+            let pdf = |choice| GGXRefractionPDF::new(uvw, wo, eta, (alpha, alpha), choice);
+
+            let samples = 400_000;
+            let total: f32 = (0..samples)
+                .map(|_| pdf(0.0).value(&Vec3::on_unit_sphere((rng.gen(), rng.gen()))))
+                .sum();
+            let integral = total * 4.0 * std::f32::consts::PI / samples as f32;
+            assert!(
+                (integral - 1.0).abs() < 0.05,
+                "{}, {}: {}",
+                eta,
+                alpha,
+                integral
+            );
+
+            // Check the sampled directions follow the PDF, above and below
+            // the surface.
+            let g = |w: &Vec3| w[Z].abs() + w[X].max(0.0);
+            let sampled: f32 = (0..samples)
+                .map(|_| {
+                    let pdf = pdf(rng.gen());
+                    let wi = pdf.generate((rng.gen(), rng.gen()));
+                    g(&wi.unit_vector()) / pdf.value(&wi)
+                })
+                .sum::<f32>()
+                / samples as f32;
+            let even: f32 = (0..samples)
+                .map(|_| {
+                    let wi = Vec3::on_unit_sphere((rng.gen(), rng.gen()));
+                    if pdf(0.0).value(&wi) > 0.0 {
+                        g(&wi)
+                    } else {
+                        0.0
+                    }
+                })
+                .sum::<f32>()
+                * 4.0
+                * std::f32::consts::PI
+                / samples as f32;
+            assert!(
+                (sampled - even).abs() < 0.05 * even,
+                "{}, {}: {} vs {}",
+                eta,
+                alpha,
+                sampled,
+                even
+            );
+        }
+    }
+
+    #[test]
+    fn normals_are_spread_like_the_distribution() {
+        // ∫ D(h) cos(h) dh = 1, for any roughness.
+        let mut rng = ChaCha8Rng::seed_from_u64(24);
+        for &alpha in &[(0.3, 0.3), (0.1, 0.5)] {
+            let distribution = TrowbridgeReitz::new(alpha);
+            let samples = 400_000;
+            let total: f32 = (0..samples)
+                .map(|_| {
+                    let h = Vec3::on_unit_sphere((rng.gen(), rng.gen()));
+                    distribution.distribution(&h) * h[Z].max(0.0)
+                })
+                .sum();
+            let integral = total * 4.0 * std::f32::consts::PI / samples as f32;
+            assert!((integral - 1.0).abs() < 0.05, "{:?}: {}", alpha, integral);
+        }
+    }
+}
@@ -1,7 +1,6 @@
 //! A PDF that samples directions towards a `Hittable`, like a light (for example).
 
 use crate::{hittable::Hittable, vec3::Vec3};
-use rand::prelude::*;
 use std::sync::Arc;
 
 /// A PDF that will bias vectors to point towards some `Hittable` object.
@@ -21,7 +20,7 @@ impl HittablePDF {
         self.obj.pdf_value(&self.origin, direction)
     }
 
-    pub fn generate<R: Rng + ?Sized>(&self, _rng: &mut R) -> Vec3 {
-        self.obj.random(&self.origin)
+    pub fn generate(&self, sample: (f32, f32)) -> Vec3 {
+        self.obj.random(&self.origin, sample)
     }
 }
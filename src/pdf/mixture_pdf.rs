@@ -1,7 +1,6 @@
 //! A helper struct for evenly "mixing" together two PDFs.
 
 use crate::{pdf::PDF, vec3::Vec3};
-use rand::prelude::*;
 
 /// A helper struct for evenly "mixing" together two PDFs. The method
 /// `MixturePDF::value()` will average out the calls to `value()` for the two
@@ -18,12 +17,14 @@ impl MixturePDF {
     }
 
     /// Randomly generate a vector using either the first or the second PDF, with
-    /// a 50% chance of picking one or the other.
-    pub fn generate<R: Rng + ?Sized>(&self, rng: &mut R) -> Vec3 {
-        if rng.gen::<f32>() < 0.5 {
-            self.0.generate(rng)
+    /// a 50% chance of picking one or the other. The first coordinate of
+    /// `sample` makes the choice, and is then stretched back out to `[0, 1)`
+    /// for the chosen PDF to use.
+    pub fn generate(&self, (u, v): (f32, f32)) -> Vec3 {
+        if u < 0.5 {
+            self.0.generate((2.0 * u, v))
         } else {
-            self.1.generate(rng)
+            self.1.generate((2.0 * u - 1.0, v))
         }
     }
 }
@@ -1,17 +1,19 @@
 //! Probability Density Functions (PDFs), and their associated structs and methods.
 
 pub mod cosine_pdf;
+pub mod ggx_pdf;
 pub mod hittable_pdf;
 pub mod mixture_pdf;
 
-use crate::{hittable::Hittable, vec3::Vec3};
-use rand::prelude::*;
+use crate::{hittable::Hittable, onb::ONB, vec3::Vec3};
 use std::sync::Arc;
 
 /// A probability density function. Supports generating either floats or vectors.
 #[derive(Clone, Debug)]
 pub enum PDF {
     Cosine(cosine_pdf::CosinePDF),
+    GGX(ggx_pdf::GGXPDF),
+    GGXRefraction(ggx_pdf::GGXRefractionPDF),
     Hittable(hittable_pdf::HittablePDF),
     Mixture(mixture_pdf::MixturePDF),
 }
@@ -22,6 +24,19 @@ impl PDF {
         Self::Cosine(cosine_pdf::CosinePDF::new(w))
     }
 
+    /// Create a new PDF for reflections off a rough surface with normal
+    /// `uvw.w`, seen from `wo`. See `ggx_pdf::TrowbridgeReitz` for `alpha`.
+    pub fn ggx(uvw: ONB, wo: Vec3, alpha: (f32, f32)) -> Self {
+        Self::GGX(ggx_pdf::GGXPDF::new(uvw, wo, alpha))
+    }
+
+    /// Create a new PDF for reflections off and refractions through a rough
+    /// dielectric with normal `uvw.w` and relative refractive index `eta`,
+    /// seen from `wo`. See `ggx_pdf::GGXRefractionPDF` for `choice`.
+    pub fn ggx_refraction(uvw: ONB, wo: Vec3, eta: f32, alpha: (f32, f32), choice: f32) -> Self {
+        Self::GGXRefraction(ggx_pdf::GGXRefractionPDF::new(uvw, wo, eta, alpha, choice))
+    }
+
     /// Create a new PDF for some `Hittable` object.
     pub fn hittable(obj: Arc<dyn Hittable>, origin: Vec3) -> Self {
         Self::Hittable(hittable_pdf::HittablePDF::new(obj, origin))
@@ -37,18 +52,22 @@ impl PDF {
     pub fn value(&self, direction: &Vec3) -> f32 {
         match self {
             Self::Cosine(c) => c.value(direction),
+            Self::GGX(g) => g.value(direction),
+            Self::GGXRefraction(g) => g.value(direction),
             Self::Hittable(h) => h.value(direction),
             Self::Mixture(m) => m.value(direction),
         }
     }
 
-    /// Generate a vector.
+    /// Generate a vector from a point `sample` in the unit square.
     /// Calls the `generate()` method on the underlying PDF struct.
-    pub fn generate<R: Rng + ?Sized>(&self, rng: &mut R) -> Vec3 {
+    pub fn generate(&self, sample: (f32, f32)) -> Vec3 {
         match self {
-            Self::Cosine(c) => c.generate(rng),
-            Self::Hittable(h) => h.generate(rng),
-            Self::Mixture(m) => m.generate(rng),
+            Self::Cosine(c) => c.generate(sample),
+            Self::GGX(g) => g.generate(sample),
+            Self::GGXRefraction(g) => g.generate(sample),
+            Self::Hittable(h) => h.generate(sample),
+            Self::Mixture(m) => m.generate(sample),
         }
     }
 
@@ -58,11 +77,9 @@ impl PDF {
     }
 }
 
-/// A utility function for getting a special PDF-ready vector on a sphere.
-pub fn random_to_sphere<R: Rng + ?Sized>(rng: &mut R, radius: f32, distance_squared: f32) -> Vec3 {
-    let r1: f32 = rng.gen();
-    let r2: f32 = rng.gen();
-
+/// A utility function for getting a special PDF-ready vector on a sphere, from
+/// a point `(r1, r2)` in the unit square.
+pub fn random_to_sphere((r1, r2): (f32, f32), radius: f32, distance_squared: f32) -> Vec3 {
     let z = 1.0 + r2 * ((1.0 - radius * radius / distance_squared).sqrt() - 1.0);
 
     let phi = 2.0 * std::f32::consts::PI * r1;
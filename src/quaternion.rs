@@ -0,0 +1,209 @@
+//! Quaternions, for rotations about any axis, and Euler angles.
+//!
+//! A unit quaternion `(w, x, y, z)` rotates by θ about the unit axis `k` when
+//! `w = cos(θ/2)` and `(x, y, z) = sin(θ/2)⋅k`. Multiplying two of them gives
+//! one that does both rotations, like with matrices: in `b * a`, `a` happens
+//! first.
+
+use crate::{
+    matrix::Matrix4,
+    util::deg_to_rad,
+    vec3,
+    vec3::{Axis, Axis::*, Vec3},
+};
+use serde::Deserialize;
+use std::ops::Mul;
+
+/// A quaternion. Only unit quaternions are rotations, but the conversion to a
+/// matrix normalizes it first, so any non-zero quaternion can be used.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+pub struct Quaternion {
+    pub w: f32,
+    pub x: f32,
+    pub y: f32,
+    pub z: f32,
+}
+
+/// The order that Euler angles are applied in. `XYZ` rotates about the X
+/// axis first, then Y, then Z. The axes stay fixed in world space while the
+/// object turns (extrinsic rotations), which is the same as rotating about
+/// the object's own axes in the opposite order (intrinsic rotations).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+pub enum EulerOrder {
+    XYZ,
+    XZY,
+    YXZ,
+    YZX,
+    ZXY,
+    ZYX,
+}
+
+impl EulerOrder {
+    /// The axes, in the order their rotations happen.
+    pub fn axes(self) -> [Axis; 3] {
+        match self {
+            EulerOrder::XYZ => [X, Y, Z],
+            EulerOrder::XZY => [X, Z, Y],
+            EulerOrder::YXZ => [Y, X, Z],
+            EulerOrder::YZX => [Y, Z, X],
+            EulerOrder::ZXY => [Z, X, Y],
+            EulerOrder::ZYX => [Z, Y, X],
+        }
+    }
+}
+
+impl Quaternion {
+    pub fn new(w: f32, x: f32, y: f32, z: f32) -> Self {
+        Self { w, x, y, z }
+    }
+
+    /// The rotation that doesn't do anything.
+    pub fn identity() -> Self {
+        Self::new(1.0, 0.0, 0.0, 0.0)
+    }
+
+    /// Rotates counterclockwise by `angle` degrees about `axis`, like
+    /// `Matrix4::rotation`. `axis` doesn't need to be a unit vector.
+    ///
+    /// # Usage
+    ///
+    /// ```
+    /// # use weekend_tracer_rs::{quaternion::Quaternion, vec3::Vec3};
+    /// let quarter_turn = Quaternion::from_axis_angle(Vec3::new(0.0, 0.0, 1.0), 90.0);
+    /// let y = quarter_turn.rotate(Vec3::new(1.0, 0.0, 0.0));
+    ///
+    /// assert!((y - Vec3::new(0.0, 1.0, 0.0)).length() < 1e-6);
+    /// ```
+    pub fn from_axis_angle(axis: Vec3, angle: f32) -> Self {
+        let k = axis.unit_vector();
+        let (sin, cos) = (deg_to_rad(angle) / 2.0).sin_cos();
+        Self::new(cos, sin * k[X], sin * k[Y], sin * k[Z])
+    }
+
+    /// Rotates counterclockwise about each axis by the matching component of
+    /// `angles` (in degrees), in the given order.
+    pub fn from_euler(order: EulerOrder, angles: Vec3) -> Self {
+        order.axes().iter().fold(Self::identity(), |q, &axis| {
+            let mut direction = Vec3::default();
+            direction[axis] = 1.0;
+            Self::from_axis_angle(direction, angles[axis]) * q
+        })
+    }
+
+    pub fn length_squared(&self) -> f32 {
+        self.w * self.w + self.x * self.x + self.y * self.y + self.z * self.z
+    }
+
+    /// The opposite rotation, for a unit quaternion.
+    pub fn conjugate(&self) -> Self {
+        Self::new(self.w, -self.x, -self.y, -self.z)
+    }
+
+    /// Scale to unit length.
+    pub fn unit_quaternion(&self) -> Self {
+        let length = self.length_squared().sqrt();
+        Self::new(
+            self.w / length,
+            self.x / length,
+            self.y / length,
+            self.z / length,
+        )
+    }
+
+    /// Rotate a vector by a unit quaternion, working out `q⋅v⋅q*` directly.
+    pub fn rotate(&self, v: Vec3) -> Vec3 {
+        let p = *self * Self::new(0.0, v[X], v[Y], v[Z]) * self.conjugate();
+        vec3!(p.x, p.y, p.z)
+    }
+}
+
+impl Mul for Quaternion {
+    type Output = Self;
+
+    /// The Hamilton product.
+    fn mul(self, rhs: Self) -> Self {
+        Self::new(
+            self.w * rhs.w - self.x * rhs.x - self.y * rhs.y - self.z * rhs.z,
+            self.w * rhs.x + self.x * rhs.w + self.y * rhs.z - self.z * rhs.y,
+            self.w * rhs.y - self.x * rhs.z + self.y * rhs.w + self.z * rhs.x,
+            self.w * rhs.z + self.x * rhs.y - self.y * rhs.x + self.z * rhs.w,
+        )
+    }
+}
+
+impl From<Quaternion> for Matrix4 {
+    /// The rotation matrix for a quaternion, which is normalized first.
+    fn from(q: Quaternion) -> Self {
+        let Quaternion { w, x, y, z } = q.unit_quaternion();
+        Matrix4([
+            [
+                1.0 - 2.0 * (y * y + z * z),
+                2.0 * (x * y - w * z),
+                2.0 * (x * z + w * y),
+                0.0,
+            ],
+            [
+                2.0 * (x * y + w * z),
+                1.0 - 2.0 * (x * x + z * z),
+                2.0 * (y * z - w * x),
+                0.0,
+            ],
+            [
+                2.0 * (x * z - w * y),
+                2.0 * (y * z + w * x),
+                1.0 - 2.0 * (x * x + y * y),
+                0.0,
+            ],
+            [0.0, 0.0, 0.0, 1.0],
+        ])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn close(a: &Matrix4, b: &Matrix4) -> bool {
+        a.0.iter()
+            .flatten()
+            .zip(b.0.iter().flatten())
+            .all(|(x, y)| (x - y).abs() < 1e-5)
+    }
+
+    #[test]
+    fn matrices_match_the_axis_angle_matrices() {
+        let axis = vec3!(1.0, -2.0, 0.5);
+        for &angle in &[0.0, 33.0, 90.0, 180.0, -250.0] {
+            let q = Quaternion::from_axis_angle(axis, angle);
+            assert!(close(&q.into(), &Matrix4::rotation(axis, angle)));
+        }
+
+        // Scaling the quaternion doesn't change the rotation.
+        let q = Quaternion::from_axis_angle(axis, 33.0);
+        let scaled = Quaternion::new(3.0 * q.w, 3.0 * q.x, 3.0 * q.y, 3.0 * q.z);
+        assert!(close(&scaled.into(), &q.into()));
+    }
+
+    #[test]
+    fn euler_angles_apply_in_order() {
+        let angles = vec3!(90.0, 90.0, 0.0);
+        let up = vec3!(0.0, 1.0, 0.0);
+
+        // X takes Y to Z, then Y takes Z to X.
+        let xyz = Quaternion::from_euler(EulerOrder::XYZ, angles);
+        assert!((xyz.rotate(up) - vec3!(1.0, 0.0, 0.0)).length() < 1e-6);
+
+        // Y leaves Y alone, then X takes it to Z.
+        let yxz = Quaternion::from_euler(EulerOrder::YXZ, angles);
+        assert!((yxz.rotate(up) - vec3!(0.0, 0.0, 1.0)).length() < 1e-6);
+
+        let angles = vec3!(10.0, -20.0, 30.0);
+        let expected = Matrix4::rotation(vec3!(1.0, 0.0, 0.0), 10.0)
+            * Matrix4::rotation(vec3!(0.0, 0.0, 1.0), 30.0)
+            * Matrix4::rotation(vec3!(0.0, 1.0, 0.0), -20.0);
+        assert!(close(
+            &Quaternion::from_euler(EulerOrder::YZX, angles).into(),
+            &expected
+        ));
+    }
+}
@@ -0,0 +1,278 @@
+//! Saving a render in progress to disk, so that it can be picked up again
+//! later.
+//!
+//! A checkpoint holds the raw per-pixel sample sums, sums of squares and
+//! sample counts of an `Accumulator`, along with a fingerprint of the scene
+//! and settings that produced it. Resuming with different settings would
+//! silently mix two different images together, so the fingerprints have to
+//! match.
+//!
+//! The file format is little-endian binary:
+//! - The magic bytes `WTRCHKPT`, then a `u32` format version.
+//! - The fingerprint (`u64`), width (`u64`) and height (`u64`).
+//! - For each pixel, in rows from the top-left: the R, G and B sums (`f32`),
+//!   the R, G and B sums of squares (`f32`), and the number of samples
+//!   (`u32`).
+
+use crate::renderer::Accumulator;
+use crate::vec3::Vec3;
+use std::{
+    error::Error,
+    fmt,
+    fs::{self, File},
+    io::{self, BufReader, BufWriter, Read, Write},
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+const MAGIC: &[u8; 8] = b"WTRCHKPT";
+const VERSION: u32 = 2;
+
+/// Where and how often a render saves checkpoints.
+#[derive(Debug, Clone)]
+pub struct Checkpointing {
+    pub path: PathBuf,
+    /// The minimum time between two checkpoints.
+    pub interval: Duration,
+    /// Identifies the scene and settings being rendered. See
+    /// `Renderer::fingerprint`.
+    pub fingerprint: u64,
+}
+
+/// A snapshot of a render in progress.
+#[derive(Debug, Clone)]
+pub struct Checkpoint {
+    pub fingerprint: u64,
+    pub accumulator: Accumulator,
+}
+
+/// Something went wrong while loading a checkpoint.
+#[derive(Debug)]
+pub enum CheckpointError {
+    Io(io::Error),
+    /// The file isn't a checkpoint, or is from an incompatible version.
+    NotACheckpoint,
+    /// The file was written by a render of a different scene, or with
+    /// different settings.
+    Mismatch,
+    /// The file has the right fingerprint, but its size doesn't make sense
+    /// for the render, so it must have been damaged.
+    Corrupt,
+}
+
+impl fmt::Display for CheckpointError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CheckpointError::Io(e) => write!(f, "{}", e),
+            CheckpointError::NotACheckpoint => write!(f, "not a checkpoint file"),
+            CheckpointError::Mismatch => write!(
+                f,
+                "the checkpoint is from a different scene, or was rendered with different settings"
+            ),
+            CheckpointError::Corrupt => write!(f, "the checkpoint is damaged"),
+        }
+    }
+}
+
+impl Error for CheckpointError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            CheckpointError::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for CheckpointError {
+    fn from(e: io::Error) -> Self {
+        if e.kind() == io::ErrorKind::UnexpectedEof {
+            CheckpointError::NotACheckpoint
+        } else {
+            CheckpointError::Io(e)
+        }
+    }
+}
+
+impl Checkpoint {
+    /// Load a checkpoint, checking that it has the expected fingerprint, and
+    /// that it's `width` by `height` pixels (the size of the region being
+    /// rendered).
+    pub fn load<P: AsRef<Path>>(
+        path: P,
+        fingerprint: u64,
+        (width, height): (usize, usize),
+    ) -> Result<Self, CheckpointError> {
+        let mut reader = BufReader::new(File::open(path)?);
+
+        let mut magic = [0; 8];
+        reader.read_exact(&mut magic)?;
+        if &magic != MAGIC || read_u32(&mut reader)? != VERSION {
+            return Err(CheckpointError::NotACheckpoint);
+        }
+
+        if read_u64(&mut reader)? != fingerprint {
+            return Err(CheckpointError::Mismatch);
+        }
+
+        // Check the size before allocating anything for it.
+        let file_width = read_u64(&mut reader)?;
+        let file_height = read_u64(&mut reader)?;
+        if file_width.checked_mul(file_height).is_none()
+            || (file_width, file_height) != (width as u64, height as u64)
+        {
+            return Err(CheckpointError::Corrupt);
+        }
+
+        let mut accumulator = Accumulator::new(width, height);
+        for index in 0..(width * height) {
+            accumulator.sums[index] = read_vec3(&mut reader)?;
+            accumulator.squares[index] = read_vec3(&mut reader)?;
+            accumulator.counts[index] = read_u32(&mut reader)?;
+        }
+
+        Ok(Self {
+            fingerprint,
+            accumulator,
+        })
+    }
+
+    /// Write the checkpoint to `path`. The checkpoint is written to a
+    /// temporary file first, so that an existing checkpoint isn't lost if
+    /// writing is interrupted.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let path = path.as_ref();
+        let mut temporary = path.as_os_str().to_owned();
+        temporary.push(".tmp");
+
+        {
+            let mut writer = BufWriter::new(File::create(&temporary)?);
+            let accumulator = &self.accumulator;
+
+            writer.write_all(MAGIC)?;
+            writer.write_all(&VERSION.to_le_bytes())?;
+            writer.write_all(&self.fingerprint.to_le_bytes())?;
+            writer.write_all(&(accumulator.width as u64).to_le_bytes())?;
+            writer.write_all(&(accumulator.height as u64).to_le_bytes())?;
+
+            for index in 0..accumulator.counts.len() {
+                write_vec3(&mut writer, accumulator.sums[index])?;
+                write_vec3(&mut writer, accumulator.squares[index])?;
+                writer.write_all(&accumulator.counts[index].to_le_bytes())?;
+            }
+
+            writer.flush()?;
+        }
+
+        fs::rename(&temporary, path)
+    }
+}
+
+/// A 64-bit FNV-1a hash. Unlike `std`'s `DefaultHasher`, this is guaranteed
+/// to give the same answer across Rust versions, which matters for anything
+/// written to disk.
+///
+/// # Usage
+///
+/// ```
+/// # use weekend_tracer_rs::renderer::checkpoint::fnv1a;
+/// assert_eq!(fnv1a(b""), 0xcbf2_9ce4_8422_2325);
+/// assert_eq!(fnv1a(b"a"), 0xaf63_dc4c_8601_ec8c);
+/// ```
+pub fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, &byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+fn read_u32<R: Read>(reader: &mut R) -> io::Result<u32> {
+    let mut bytes = [0; 4];
+    reader.read_exact(&mut bytes)?;
+    Ok(u32::from_le_bytes(bytes))
+}
+
+fn read_u64<R: Read>(reader: &mut R) -> io::Result<u64> {
+    let mut bytes = [0; 8];
+    reader.read_exact(&mut bytes)?;
+    Ok(u64::from_le_bytes(bytes))
+}
+
+fn read_f32<R: Read>(reader: &mut R) -> io::Result<f32> {
+    let mut bytes = [0; 4];
+    reader.read_exact(&mut bytes)?;
+    Ok(f32::from_le_bytes(bytes))
+}
+
+fn read_vec3<R: Read>(reader: &mut R) -> io::Result<Vec3> {
+    Ok(Vec3(
+        read_f32(reader)?,
+        read_f32(reader)?,
+        read_f32(reader)?,
+    ))
+}
+
+fn write_vec3<W: Write>(writer: &mut W, Vec3(r, g, b): Vec3) -> io::Result<()> {
+    for component in &[r, g, b] {
+        writer.write_all(&component.to_le_bytes())?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        let path = std::env::temp_dir().join(format!("checkpoint-{}.bin", std::process::id()));
+
+        let mut accumulator = Accumulator::new(3, 2);
+        accumulator.add_sample(1, Vec3(0.5, 1.5, 2.5));
+        accumulator.add_sample(1, Vec3(1.0, 1.0, 1.0));
+        accumulator.add_sample(5, Vec3(7.0, 0.0, 0.25));
+
+        let accumulator_error = accumulator.relative_error(1);
+
+        Checkpoint {
+            fingerprint: 42,
+            accumulator,
+        }
+        .save(&path)
+        .unwrap();
+
+        assert!(matches!(
+            Checkpoint::load(&path, 43, (3, 2)),
+            Err(CheckpointError::Mismatch)
+        ));
+        assert!(matches!(
+            Checkpoint::load(&path, 42, (2, 3)),
+            Err(CheckpointError::Corrupt)
+        ));
+
+        let loaded = Checkpoint::load(&path, 42, (3, 2)).unwrap().accumulator;
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!((loaded.width, loaded.height), (3, 2));
+        assert_eq!(loaded.samples(1), 2);
+        assert_eq!(loaded.color(1), Vec3(0.75, 1.25, 1.75));
+        assert_eq!(loaded.samples(5), 1);
+        assert_eq!(loaded.samples(0), 0);
+        assert_eq!(loaded.relative_error(1), accumulator_error);
+    }
+
+    #[test]
+    fn huge_sizes_are_rejected() {
+        let path = std::env::temp_dir().join(format!("huge-{}.bin", std::process::id()));
+
+        let mut bytes = MAGIC.to_vec();
+        bytes.extend_from_slice(&VERSION.to_le_bytes());
+        bytes.extend_from_slice(&42_u64.to_le_bytes());
+        bytes.extend_from_slice(&u64::MAX.to_le_bytes());
+        bytes.extend_from_slice(&u64::MAX.to_le_bytes());
+        fs::write(&path, bytes).unwrap();
+
+        let loaded = Checkpoint::load(&path, 42, (3, 2));
+        fs::remove_file(&path).unwrap();
+
+        assert!(matches!(loaded, Err(CheckpointError::Corrupt)));
+    }
+}
@@ -0,0 +1,710 @@
+//! Turning a scene into an image.
+
+pub mod checkpoint;
+pub mod tile;
+
+use crate::bvh::BVH;
+use crate::camera::Camera;
+use crate::hittable::Hittable;
+use crate::material::{Scatter, ScatterType};
+use crate::pdf::PDF;
+use crate::ray::Ray;
+use crate::renderer::checkpoint::{Checkpoint, Checkpointing};
+use crate::renderer::tile::{Rect, TileOrder};
+use crate::sampler::{self, SampleStream, Sampler};
+use crate::tonemap::ToneMapper;
+use crate::vec3;
+use crate::vec3::{Channel::*, Vec3};
+use indicatif::{ProgressBar, ProgressStyle};
+use rand::prelude::*;
+use rand_chacha::ChaCha8Rng;
+use rayon::prelude::*;
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc, Mutex,
+};
+use std::time::Instant;
+
+/// Radiance below this is treated as black when estimating how noisy a pixel
+/// is. See `Accumulator::relative_error`.
+pub const BLACK_LEVEL: f32 = 0.01;
+
+/// The default width and height of a tile, in pixels.
+pub const DEFAULT_TILE_SIZE: usize = 16;
+
+/// A pixel. Components are ordered `R`, `G`, `B`. Each component should range
+/// from 0-255.
+pub type Pixel = (u32, u32, u32);
+
+fn ray_color(
+    samples: &mut SampleStream,
+    ray: &Ray,
+    background_color: &Vec3,
+    bvh: &BVH,
+    lights: Option<&Arc<dyn Hittable>>,
+    reflection_depth: usize,
+) -> Vec3 {
+    if reflection_depth == 0 {
+        // If we've exceeded the ray bounce limit, no more light is gathered.
+        vec3!()
+    } else if let Some(hit_record) = bvh.hit(ray, 0.001, f32::INFINITY) {
+        //                                        ^^^^^
+        //                                          |
+        // This `0.001` is so that we don't get weird "shadow acne" due to
+        // floating-point errors.
+        //
+        // We hit something! Scatter the ray based on material type. If it
+        // successfully scattered, reflect the ray according by the material
+        // type, and recurse. If it was absorbed, just return black.
+        //
+        // We also add on some emitted light if the ray hit some emitting material.
+
+        let emitted =
+            hit_record
+                .material
+                .emitted(&hit_record, hit_record.uv, &hit_record.hit_point);
+
+        if let Some(Scatter {
+            attenuation,
+            scattered,
+        }) = hit_record.material.scatter(samples, ray, &hit_record)
+        {
+            match scattered {
+                ScatterType::Specular(specular_ray) => {
+                    attenuation
+                        * ray_color(
+                            samples,
+                            &specular_ray,
+                            background_color,
+                            bvh,
+                            lights,
+                            reflection_depth - 1,
+                        )
+                }
+
+                ScatterType::PDF(scatter_pdf) => {
+                    // Bias the scattered ray towards the lights, if there are
+                    // any.
+                    let pdf = if let Some(lights) = lights {
+                        let light_pdf = PDF::hittable(lights.clone(), hit_record.hit_point);
+                        PDF::mixture(&light_pdf, &scatter_pdf)
+                    } else {
+                        scatter_pdf
+                    };
+
+                    let scattered = Ray::new(
+                        hit_record.hit_point,
+                        pdf.generate(samples.next_2d()),
+                        ray.time,
+                    );
+                    let pdf_val = pdf.value(&scattered.direction);
+
+                    emitted
+                        + attenuation
+                            * hit_record.material.scattering_pdf(
+                                samples.rng(),
+                                ray,
+                                &hit_record,
+                                &scattered,
+                            )
+                            * ray_color(
+                                samples,
+                                &scattered,
+                                background_color,
+                                bvh,
+                                lights,
+                                reflection_depth - 1,
+                            )
+                            / pdf_val
+                }
+            }
+        } else {
+            emitted
+        }
+    } else {
+        // Didn't hit anything! Just render the background colour.
+        *background_color
+    }
+}
+
+/// Settings for adaptive sampling, which stops sampling a pixel once its
+/// colour is known precisely enough.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AdaptiveSampling {
+    /// Every pixel gets at least this many samples, however precise it seems.
+    /// With too few samples, the error estimate can't be trusted.
+    pub min_samples: usize,
+    /// A pixel is done once the (estimated) relative standard error of each of
+    /// its colour components falls below this. See
+    /// `Accumulator::relative_error`.
+    pub threshold: f32,
+}
+
+/// Everything needed to render a scene.
+#[derive(Debug)]
+pub struct Renderer {
+    pub width: usize,
+    pub height: usize,
+    /// The maximum number of samples per pixel. Without adaptive sampling,
+    /// every pixel gets exactly this many.
+    pub samples_per_pixel: usize,
+    /// If `None`, every pixel gets `samples_per_pixel` samples.
+    pub adaptive: Option<AdaptiveSampling>,
+    /// Chooses the random numbers used for each sample.
+    pub sampler: Sampler,
+    /// Every random number used in the render comes from this. Rendering the
+    /// same scene with the same settings and seed always gives exactly the
+    /// same image, no matter how many threads are used.
+    pub seed: u64,
+    pub max_reflection_depth: usize,
+    pub bvh: BVH,
+    /// If `None`, scattered rays won't be biased towards anything.
+    pub lights: Option<Arc<dyn Hittable>>,
+    pub camera: Camera,
+    pub background_color: Vec3,
+    /// The image is rendered in tiles of `tile_size` by `tile_size` pixels.
+    pub tile_size: usize,
+    pub tile_order: TileOrder,
+    /// Only render this part of the image. If `None`, the whole image gets
+    /// rendered.
+    pub region: Option<Rect>,
+    /// Periodically save the render in progress. If `None`, nothing is saved.
+    pub checkpointing: Option<Checkpointing>,
+}
+
+impl Renderer {
+    /// The part of the image that gets rendered. The rendered output has the
+    /// same dimensions as this.
+    pub fn region(&self) -> Rect {
+        self.region
+            .unwrap_or_else(|| Rect::new(0, 0, self.width, self.height))
+    }
+
+    /// The tiles that make up the rendered region, in the order that they
+    /// get rendered.
+    pub fn tiles(&self) -> Vec<Rect> {
+        tile::tiles(self.region(), self.tile_size, self.tile_order)
+    }
+
+    /// A fingerprint of everything that affects what the rendered image looks
+    /// like (other than the number of samples), for telling checkpoints of
+    /// different renders apart. The scene's geometry and materials aren't
+    /// included, so the caller should identify the scene with `scene` (e.g.
+    /// the contents of its scene file).
+    pub fn fingerprint(&self, scene: &[u8]) -> u64 {
+        let settings = format!(
+            "{}x{} {:?} {} {:?} {:?}",
+            self.width,
+            self.height,
+            self.region(),
+            self.max_reflection_depth,
+            self.camera,
+            self.background_color,
+        );
+
+        checkpoint::fnv1a(&[scene, settings.as_bytes()].concat())
+    }
+
+    /// Render the scene, without clamping or gamma-correcting anything.
+    /// Outputs a vector of linear RGB radiance values, one for each pixel of
+    /// the rendered region, in rows from its top-left.
+    pub fn render(&self) -> Vec<Vec3> {
+        let region = self.region();
+        let accumulator = Mutex::new(Accumulator::new(region.width, region.height));
+        self.render_into(&accumulator);
+
+        accumulator.into_inner().unwrap().image()
+    }
+
+    /// Keep sampling the pixels in `accumulator`, which should be the same
+    /// size as the rendered region, until each of them has
+    /// `samples_per_pixel` samples (or has converged, with adaptive
+    /// sampling). Pixels that already have enough samples, e.g. from a
+    /// checkpoint, are left alone.
+    pub fn render_into(&self, accumulator: &Mutex<Accumulator>) {
+        let last_checkpoint = Mutex::new(Instant::now());
+
+        let pb = progress_bar(self.region().area() as u64);
+        self.render_pass(
+            accumulator,
+            self.samples_per_pixel,
+            &AtomicBool::new(false),
+            Some(&pb),
+            &last_checkpoint,
+        );
+        pb.finish();
+
+        self.save_checkpoint(accumulator, &last_checkpoint, true);
+    }
+
+    /// Render the scene one sample per pixel at a time, adding each tile to
+    /// `accumulator` as soon as it's done, so that it always holds a complete
+    /// (if noisy) image that can be looked at while the render carries on.
+    /// `accumulator` should be the same size as the rendered region, and can
+    /// already have some samples in it.
+    ///
+    /// Setting `stop` abandons the render as soon as possible. Any pixels
+    /// that were already sampled in the current pass are kept. Returns the
+    /// number of passes that were completed.
+    pub fn render_progressive(&self, accumulator: &Mutex<Accumulator>, stop: &AtomicBool) -> usize {
+        let last_checkpoint = Mutex::new(Instant::now());
+        let first_pass = accumulator.lock().unwrap().min_samples() as usize + 1;
+
+        let pb = progress_bar(self.samples_per_pixel as u64);
+        pb.set_position(first_pass as u64 - 1);
+
+        let mut passes = 0;
+        for target in first_pass..=self.samples_per_pixel {
+            self.render_pass(accumulator, target, stop, None, &last_checkpoint);
+            if stop.load(Ordering::Relaxed) {
+                break;
+            }
+
+            passes += 1;
+            pb.inc(1);
+        }
+        pb.finish_and_clear();
+
+        self.save_checkpoint(accumulator, &last_checkpoint, true);
+        passes
+    }
+
+    /// Sample every pixel in the rendered region until it has `target`
+    /// samples, a tile at a time, adding each finished tile to
+    /// `accumulator`.
+    fn render_pass(
+        &self,
+        accumulator: &Mutex<Accumulator>,
+        target: usize,
+        stop: &AtomicBool,
+        pb: Option<&ProgressBar>,
+        last_checkpoint: &Mutex<Instant>,
+    ) {
+        let region = self.region();
+
+        // `par_bridge` hands the tiles out to threads in order, unlike
+        // `into_par_iter`, which splits the list up into big chunks.
+        self.tiles().into_iter().par_bridge().for_each(|tile| {
+            let (x, y) = (tile.x - region.x, tile.y - region.y);
+
+            // Only this thread touches this tile until the pass is over,
+            // so it can be worked on outside the lock.
+            let mut tile_accumulator =
+                accumulator
+                    .lock()
+                    .unwrap()
+                    .crop(&Rect::new(x, y, tile.width, tile.height));
+
+            for (index, (i, j)) in tile.pixels().enumerate() {
+                while !self.pixel_done(&tile_accumulator, index, target) {
+                    if stop.load(Ordering::Relaxed) {
+                        break;
+                    }
+                    let sample = tile_accumulator.samples(index) as usize;
+                    tile_accumulator.add_sample(index, self.sample(i, j, sample));
+                }
+            }
+
+            accumulator.lock().unwrap().paste(&tile_accumulator, x, y);
+
+            if let Some(pb) = pb {
+                pb.inc(tile.area() as u64);
+            }
+
+            self.save_checkpoint(accumulator, last_checkpoint, false);
+        });
+    }
+
+    /// Whether the pixel at `index` has had enough samples for now: either
+    /// `target` samples, or (with adaptive sampling) few enough to be sure
+    /// of its colour.
+    fn pixel_done(&self, accumulator: &Accumulator, index: usize, target: usize) -> bool {
+        let samples = accumulator.samples(index) as usize;
+
+        samples >= target
+            || self.adaptive.is_some_and(|adaptive| {
+                samples >= adaptive.min_samples
+                    && accumulator.relative_error(index) <= adaptive.threshold
+            })
+    }
+
+    /// Save a checkpoint, if checkpointing is on and it's been long enough
+    /// since the last one (or `force` is set). Failing to save a checkpoint
+    /// isn't worth abandoning the render over, so errors are just reported.
+    fn save_checkpoint(
+        &self,
+        accumulator: &Mutex<Accumulator>,
+        last_checkpoint: &Mutex<Instant>,
+        force: bool,
+    ) {
+        let checkpointing = match &self.checkpointing {
+            Some(checkpointing) => checkpointing,
+            None => return,
+        };
+
+        // Only one thread needs to save a checkpoint at a time.
+        let mut last_checkpoint = match last_checkpoint.try_lock() {
+            Ok(last_checkpoint) => last_checkpoint,
+            Err(_) if force => last_checkpoint.lock().unwrap(),
+            Err(_) => return,
+        };
+
+        if force || last_checkpoint.elapsed() >= checkpointing.interval {
+            let checkpoint = Checkpoint {
+                fingerprint: checkpointing.fingerprint,
+                accumulator: accumulator.lock().unwrap().clone(),
+            };
+
+            if let Err(e) = checkpoint.save(&checkpointing.path) {
+                eprintln!(
+                    "warning: could not save a checkpoint to {}: {}",
+                    checkpointing.path.display(),
+                    e
+                );
+            }
+
+            *last_checkpoint = Instant::now();
+        }
+    }
+
+    /// Trace a single ray through a random point of pixel (`i`, `j`),
+    /// counting from the top-left of the image. `index` is the number of
+    /// samples the pixel has already had.
+    fn sample(&self, i: usize, j: usize, index: usize) -> Vec3 {
+        // Each sample gets its own random number generator, so that it comes
+        // out the same no matter which thread takes it, or when.
+        let seed = [i, j, index]
+            .iter()
+            .fold(self.seed, |seed, &n| sampler::mix(seed, n as u64));
+        let mut rng = ChaCha8Rng::seed_from_u64(seed);
+        let mut samples =
+            self.sampler
+                .start(&mut rng, self.seed, (i, j), index, self.samples_per_pixel);
+
+        // The camera counts from the bottom-left.
+        let j = self.height - 1 - j;
+
+        // Each sample is offset by a small, random amount.
+        let (jitter_u, jitter_v) = samples.next_2d();
+        let u = ((i as f32) + jitter_u) / (self.width as f32);
+        let v = ((j as f32) + jitter_v) / (self.height as f32);
+
+        let lens = samples.next_2d();
+        let time = samples.next_1d();
+        let ray = self.camera.get_ray_sampled(u, v, lens, time);
+        ray_color(
+            &mut samples,
+            &ray,
+            &self.background_color,
+            &self.bvh,
+            self.lights.as_ref(),
+            self.max_reflection_depth,
+        )
+    }
+}
+
+/// A running sum of the samples taken for each pixel of an image, along with
+/// the sum of their squares, for estimating how noisy each pixel still is.
+#[derive(Debug, Clone)]
+pub struct Accumulator {
+    pub width: usize,
+    pub height: usize,
+    sums: Vec<Vec3>,
+    squares: Vec<Vec3>,
+    counts: Vec<u32>,
+}
+
+impl Accumulator {
+    /// An accumulator for a `width` by `height` image with no samples in it.
+    pub fn new(width: usize, height: usize) -> Self {
+        Self {
+            width,
+            height,
+            sums: vec![vec3!(); width * height],
+            squares: vec![vec3!(); width * height],
+            counts: vec![0; width * height],
+        }
+    }
+
+    /// Add a sample to the pixel at `index`, counting in rows from the
+    /// top-left of the image. NaN components are counted as black.
+    pub fn add_sample(&mut self, index: usize, mut color: Vec3) {
+        for channel in &[R, G, B] {
+            if color[*channel].is_nan() {
+                color[*channel] = 0.0;
+            }
+        }
+
+        self.sums[index] += color;
+        self.squares[index] += color * color;
+        self.counts[index] += 1;
+    }
+
+    /// A copy of part of the accumulator.
+    pub fn crop(&self, rect: &Rect) -> Accumulator {
+        let mut cropped = Accumulator::new(rect.width, rect.height);
+        for row in 0..rect.height {
+            let start = (rect.y + row) * self.width + rect.x;
+            let range = start..start + rect.width;
+            let cropped_range = row * rect.width..(row + 1) * rect.width;
+
+            cropped.sums[cropped_range.clone()].copy_from_slice(&self.sums[range.clone()]);
+            cropped.squares[cropped_range.clone()].copy_from_slice(&self.squares[range.clone()]);
+            cropped.counts[cropped_range].copy_from_slice(&self.counts[range]);
+        }
+        cropped
+    }
+
+    /// Overwrite part of the accumulator with `other`, with `other`'s
+    /// top-left corner at (`x`, `y`). The opposite of `crop`.
+    pub fn paste(&mut self, other: &Accumulator, x: usize, y: usize) {
+        for row in 0..other.height {
+            let start = (y + row) * self.width + x;
+            let range = start..start + other.width;
+            let other_range = row * other.width..(row + 1) * other.width;
+
+            self.sums[range.clone()].copy_from_slice(&other.sums[other_range.clone()]);
+            self.squares[range.clone()].copy_from_slice(&other.squares[other_range.clone()]);
+            self.counts[range].copy_from_slice(&other.counts[other_range]);
+        }
+    }
+
+    /// The fewest samples taken for any one pixel.
+    pub fn min_samples(&self) -> u32 {
+        self.counts.iter().copied().min().unwrap_or(0)
+    }
+
+    /// The number of samples taken for the pixel at `index`.
+    pub fn samples(&self, index: usize) -> u32 {
+        self.counts[index]
+    }
+
+    /// The average of the samples taken for the pixel at `index`, or black if
+    /// there aren't any yet.
+    pub fn color(&self, index: usize) -> Vec3 {
+        match self.counts[index] {
+            0 => vec3!(),
+            count => self.sums[index] / (count as f32),
+        }
+    }
+
+    /// An estimate of how far off the colour of the pixel at `index` might
+    /// still be: the largest standard error of the mean of any of its
+    /// components, relative to that component. Components darker than
+    /// `BLACK_LEVEL` count as being that bright, so that nearly black pixels
+    /// don't soak up samples hunting for a precise shade of black.
+    ///
+    /// Infinite if there are fewer than two samples.
+    ///
+    /// # Usage
+    ///
+    /// ```
+    /// # use weekend_tracer_rs::{renderer::Accumulator, vec3, vec3::Vec3};
+    /// let mut accumulator = Accumulator::new(2, 1);
+    /// for _ in 0..8 {
+    ///     accumulator.add_sample(0, vec3!(0.5, 0.5, 0.5));
+    ///     accumulator.add_sample(1, vec3!(0.0, 0.5, 1.0));
+    ///     accumulator.add_sample(1, vec3!(1.0, 0.5, 0.0));
+    /// }
+    /// assert_eq!(accumulator.relative_error(0), 0.0);
+    /// assert!(accumulator.relative_error(1) > 0.2);
+    /// ```
+    pub fn relative_error(&self, index: usize) -> f32 {
+        let n = self.counts[index] as f32;
+        if n < 2.0 {
+            return f32::INFINITY;
+        }
+
+        let mean = self.sums[index] / n;
+        let squares = self.squares[index] / n;
+
+        [R, G, B]
+            .iter()
+            .map(|&channel| {
+                // Sample variance, with Bessel's correction.
+                let variance =
+                    (squares[channel] - mean[channel] * mean[channel]).max(0.0) * n / (n - 1.0);
+                (variance / n).sqrt() / mean[channel].max(BLACK_LEVEL)
+            })
+            .fold(0.0, f32::max)
+    }
+
+    /// A false-colour image of the number of samples taken for each pixel,
+    /// from black (no samples), through blue and red, to yellow (the most
+    /// samples taken for any pixel). Handy for seeing where adaptive sampling
+    /// spent its time.
+    pub fn sample_heatmap(&self) -> Vec<Pixel> {
+        const RAMP: [Vec3; 4] = [
+            Vec3(0.0, 0.0, 0.0),
+            Vec3(0.1, 0.1, 0.8),
+            Vec3(0.9, 0.1, 0.1),
+            Vec3(1.0, 0.9, 0.1),
+        ];
+
+        let max = self.counts.iter().copied().max().unwrap_or(0).max(1) as f32;
+
+        self.counts
+            .iter()
+            .map(|&count| {
+                let t = (count as f32) / max * ((RAMP.len() - 1) as f32);
+                let segment = (t as usize).min(RAMP.len() - 2);
+                let t = t - segment as f32;
+                let color = (1.0 - t) * RAMP[segment] + t * RAMP[segment + 1];
+
+                (
+                    (255.0 * color[R]) as u32,
+                    (255.0 * color[G]) as u32,
+                    (255.0 * color[B]) as u32,
+                )
+            })
+            .collect()
+    }
+
+    /// The averaged, linear image, in rows from the top-left.
+    pub fn image(&self) -> Vec<Vec3> {
+        (0..self.sums.len())
+            .map(|index| self.color(index))
+            .collect()
+    }
+}
+
+/// Render the scene. Outputs a vector of (r, g, b) integer triples, one for
+/// each pixel, which can range from 0 to 255. The image is clamped and sRGB
+/// encoded; use `render_linear` and a `ToneMapper` for anything fancier.
+///
+/// If `lights` is `None`, scattered rays won't be biased towards anything.
+#[allow(clippy::too_many_arguments)]
+pub fn render(
+    width: usize,
+    height: usize,
+    samples_per_pixel: usize,
+    max_reflection_depth: usize,
+    bvh: BVH,
+    lights: Option<Arc<dyn Hittable>>,
+    camera: Camera,
+    background_color: Vec3,
+) -> Vec<Pixel> {
+    let rendered = render_linear(
+        width,
+        height,
+        samples_per_pixel,
+        max_reflection_depth,
+        bvh,
+        lights,
+        camera,
+        background_color,
+    );
+
+    ToneMapper::default().to_pixels(&rendered)
+}
+
+/// Render the scene, without clamping or gamma-correcting anything. Outputs a
+/// vector of linear RGB radiance values, one for each pixel, in rows from the
+/// top-left of the image. See `Renderer::render`.
+///
+/// If `lights` is `None`, scattered rays won't be biased towards anything.
+#[allow(clippy::too_many_arguments)]
+pub fn render_linear(
+    width: usize,
+    height: usize,
+    samples_per_pixel: usize,
+    max_reflection_depth: usize,
+    bvh: BVH,
+    lights: Option<Arc<dyn Hittable>>,
+    camera: Camera,
+    background_color: Vec3,
+) -> Vec<Vec3> {
+    Renderer {
+        width,
+        height,
+        samples_per_pixel,
+        adaptive: None,
+        sampler: Sampler::default(),
+        seed: 0,
+        max_reflection_depth,
+        bvh,
+        lights,
+        camera,
+        background_color,
+        tile_size: DEFAULT_TILE_SIZE,
+        tile_order: TileOrder::default(),
+        region: None,
+        checkpointing: None,
+    }
+    .render()
+}
+
+fn progress_bar(len: u64) -> ProgressBar {
+    let pb_style = ProgressStyle::default_bar()
+        .template("{spinner} {msg} [{elapsed_precise}] [{bar:30.yellow/blue}] {pos}/{len}")
+        .progress_chars("=>-");
+
+    let pb = ProgressBar::new(len);
+    pb.set_style(pb_style);
+    pb
+}
+
+/// Convert a rendered scene into a iterator over
+/// [ARGB](https://en.wikipedia.org/wiki/RGBA_color_model#ARGB_(word-order))
+/// 32-bit unsigned colour integers:
+/// - The upper 8 bits is for the alpha channel.
+/// - The next 8 bits is for the red channel.
+/// - The next 8 bits is for the blue channel.
+/// - The lowest 8 bits is for the green channel.
+pub fn convert_to_argb<I>(rendered_scene: I) -> impl Iterator<Item = u32>
+where
+    I: IntoIterator<Item = Pixel>,
+{
+    rendered_scene.into_iter().map(|pixel| {
+        let (r, g, b) = pixel;
+        (255 << 24) | (r << 16) | (g << 8) | b
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scenes;
+
+    fn renderer(seed: u64) -> Renderer {
+        // This one has a bit of everything, including volumes and noise.
+        let scene = (scenes::builtin_scene("next_week_final").unwrap().build)(
+            1.0,
+            &mut ChaCha8Rng::seed_from_u64(0),
+        );
+        let time = (scene.camera.time0, scene.camera.time1);
+
+        Renderer {
+            width: 12,
+            height: 12,
+            samples_per_pixel: 3,
+            adaptive: None,
+            sampler: Sampler::Sobol,
+            seed,
+            max_reflection_depth: 5,
+            bvh: BVH::new(scene.world.objects, time.0, time.1),
+            lights: Some(Arc::new(scene.lights)),
+            camera: scene.camera,
+            background_color: scene.background,
+            tile_size: 4,
+            tile_order: TileOrder::default(),
+            region: None,
+            checkpointing: None,
+        }
+    }
+
+    fn render_with_threads(seed: u64, threads: usize) -> Vec<Vec3> {
+        rayon::ThreadPoolBuilder::new()
+            .num_threads(threads)
+            .build()
+            .unwrap()
+            .install(|| renderer(seed).render())
+    }
+
+    #[test]
+    fn renders_are_reproducible() {
+        let image = render_with_threads(7, 1);
+        assert_eq!(image, render_with_threads(7, 3));
+        assert_ne!(image, render_with_threads(8, 2));
+    }
+}
@@ -0,0 +1,256 @@
+//! Splitting an image up into tiles (or buckets), and choosing what order to
+//! render them in.
+//!
+//! Rendering a tile at a time keeps each thread working on pixels that are
+//! close together, which tend to hit the same parts of the scene.
+
+use std::{fmt, str::FromStr};
+
+/// A rectangle of pixels, counting from the top-left of the image.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rect {
+    pub x: usize,
+    pub y: usize,
+    pub width: usize,
+    pub height: usize,
+}
+
+impl Rect {
+    pub fn new(x: usize, y: usize, width: usize, height: usize) -> Self {
+        Self {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+
+    /// The number of pixels in the rectangle.
+    pub fn area(&self) -> usize {
+        self.width * self.height
+    }
+
+    /// Whether `other` lies entirely within this rectangle.
+    ///
+    /// # Usage
+    ///
+    /// ```
+    /// # use weekend_tracer_rs::renderer::tile::Rect;
+    /// let image = Rect::new(0, 0, 400, 300);
+    /// assert!(image.contains(&Rect::new(100, 100, 300, 200)));
+    /// assert!(!image.contains(&Rect::new(100, 100, 301, 200)));
+    /// ```
+    pub fn contains(&self, other: &Rect) -> bool {
+        other.x >= self.x
+            && other.y >= self.y
+            && other.x + other.width <= self.x + self.width
+            && other.y + other.height <= self.y + self.height
+    }
+
+    /// The (x, y) coordinates of every pixel in the rectangle, row by row.
+    pub fn pixels(&self) -> impl Iterator<Item = (usize, usize)> {
+        let Rect {
+            x,
+            y,
+            width,
+            height,
+        } = *self;
+        (y..y + height).flat_map(move |j| (x..x + width).map(move |i| (i, j)))
+    }
+}
+
+/// The order in which tiles get rendered.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TileOrder {
+    /// Row by row, from the top-left.
+    Scanline,
+    /// Spiralling outwards from the centre of the image, where the
+    /// interesting stuff usually is.
+    Spiral,
+    /// Along a Hilbert curve, which keeps consecutive tiles next to each
+    /// other.
+    #[default]
+    Hilbert,
+}
+
+/// The names of all the tile orders, as accepted by `TileOrder::from_str`.
+pub const TILE_ORDER_NAMES: &[&str] = &["scanline", "spiral", "hilbert"];
+
+impl fmt::Display for TileOrder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            TileOrder::Scanline => "scanline",
+            TileOrder::Spiral => "spiral",
+            TileOrder::Hilbert => "hilbert",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl FromStr for TileOrder {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "scanline" => Ok(TileOrder::Scanline),
+            "spiral" => Ok(TileOrder::Spiral),
+            "hilbert" => Ok(TileOrder::Hilbert),
+            _ => Err(format!(
+                "unknown tile order {:?} (expected one of: {})",
+                s,
+                TILE_ORDER_NAMES.join(", ")
+            )),
+        }
+    }
+}
+
+/// Cut `area` up into tiles of (at most) `tile_size` by `tile_size` pixels,
+/// in the given order. Tiles along the right and bottom edges get cut short
+/// if `area` isn't a multiple of `tile_size`.
+///
+/// # Usage
+///
+/// ```
+/// # use weekend_tracer_rs::renderer::tile::{tiles, Rect, TileOrder};
+/// let tiles = tiles(Rect::new(0, 0, 40, 20), 16, TileOrder::Scanline);
+/// assert_eq!(tiles.len(), 6);
+/// assert_eq!(tiles[2], Rect::new(32, 0, 8, 16));
+/// assert_eq!(tiles[5], Rect::new(32, 16, 8, 4));
+/// ```
+pub fn tiles(area: Rect, tile_size: usize, order: TileOrder) -> Vec<Rect> {
+    assert!(tile_size > 0, "Tiles must be at least one pixel wide.");
+
+    let columns = area.width.div_ceil(tile_size);
+    let rows = area.height.div_ceil(tile_size);
+
+    let cells = match order {
+        TileOrder::Scanline => (0..rows)
+            .flat_map(|row| (0..columns).map(move |column| (column, row)))
+            .collect(),
+        TileOrder::Spiral => spiral(columns, rows),
+        TileOrder::Hilbert => hilbert(columns, rows),
+    };
+
+    cells
+        .into_iter()
+        .map(|(column, row)| {
+            let x = column * tile_size;
+            let y = row * tile_size;
+            Rect::new(
+                area.x + x,
+                area.y + y,
+                tile_size.min(area.width - x),
+                tile_size.min(area.height - y),
+            )
+        })
+        .collect()
+}
+
+/// Every cell of a `columns` by `rows` grid, spiralling outwards from the
+/// middle.
+fn spiral(columns: usize, rows: usize) -> Vec<(usize, usize)> {
+    let total = columns * rows;
+    let mut cells = Vec::with_capacity(total);
+
+    let (mut x, mut y) = (((columns as isize) - 1) / 2, ((rows as isize) - 1) / 2);
+    let (mut dx, mut dy) = (1, 0);
+    let mut leg_length = 1;
+
+    // Walk a square spiral (right, down, left, left, up, up, right, right,
+    // right...), keeping any cells that land inside the grid.
+    while cells.len() < total {
+        for _ in 0..2 {
+            for _ in 0..leg_length {
+                if (0..columns as isize).contains(&x) && (0..rows as isize).contains(&y) {
+                    cells.push((x as usize, y as usize));
+                }
+                x += dx;
+                y += dy;
+            }
+            // Turn clockwise.
+            let turned = (-dy, dx);
+            dx = turned.0;
+            dy = turned.1;
+        }
+        leg_length += 1;
+    }
+
+    cells
+}
+
+/// Every cell of a `columns` by `rows` grid, in the order they're visited by
+/// a Hilbert curve covering the smallest power-of-two square that fits the
+/// grid.
+fn hilbert(columns: usize, rows: usize) -> Vec<(usize, usize)> {
+    let n = columns.max(rows).next_power_of_two();
+
+    (0..n * n)
+        .map(|d| hilbert_d_to_xy(n, d))
+        .filter(|&(x, y)| x < columns && y < rows)
+        .collect()
+}
+
+/// Convert a distance along a Hilbert curve filling an `n` by `n` square
+/// into (x, y) coordinates. `n` must be a power of two.
+fn hilbert_d_to_xy(n: usize, d: usize) -> (usize, usize) {
+    let (mut x, mut y) = (0, 0);
+    let mut t = d;
+    let mut s = 1;
+
+    while s < n {
+        let rx = 1 & (t / 2);
+        let ry = 1 & (t ^ rx);
+
+        // Rotate the quadrant.
+        if ry == 0 {
+            if rx == 1 {
+                x = s - 1 - x;
+                y = s - 1 - y;
+            }
+            std::mem::swap(&mut x, &mut y);
+        }
+
+        x += s * rx;
+        y += s * ry;
+        t /= 4;
+        s *= 2;
+    }
+
+    (x, y)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_order_covers_every_pixel_once() {
+        let area = Rect::new(3, 5, 67, 41);
+        for &order in &[TileOrder::Scanline, TileOrder::Spiral, TileOrder::Hilbert] {
+            let mut covered = vec![0; area.area()];
+            for tile in tiles(area, 8, order) {
+                assert!(area.contains(&tile), "{} made {:?}", order, tile);
+                for (i, j) in tile.pixels() {
+                    covered[(j - area.y) * area.width + (i - area.x)] += 1;
+                }
+            }
+            assert!(covered.iter().all(|&count| count == 1), "{}", order);
+        }
+    }
+
+    #[test]
+    fn consecutive_hilbert_tiles_are_neighbours() {
+        let cells = hilbert(8, 8);
+        for pair in cells.windows(2) {
+            let (a, b) = (pair[0], pair[1]);
+            let distance =
+                (a.0 as isize - b.0 as isize).abs() + (a.1 as isize - b.1 as isize).abs();
+            assert_eq!(distance, 1);
+        }
+    }
+
+    #[test]
+    fn spiral_starts_in_the_middle() {
+        assert_eq!(spiral(5, 3)[0], (2, 1));
+    }
+}
@@ -0,0 +1,486 @@
+//! Sample generators, for choosing the "random" numbers used to render each
+//! sample of each pixel.
+//!
+//! Every sample of a pixel needs a bunch of random numbers: where in the pixel
+//! the ray goes, where on the lens it starts, what time it's sent out at,
+//! which way it bounces at each hit... Each of these is a *dimension* of the
+//! sample. Plain independent random numbers clump together, which shows up as
+//! noise. The other samplers spread each dimension's values out evenly over
+//! the samples of a pixel, so the image converges faster.
+//!
+//! A `Sampler` says which kind of sequence to use. For each sample of each
+//! pixel, `Sampler::start` gives a `SampleStream`, which hands out the values
+//! for each dimension in turn.
+
+use rand::{Rng, RngCore};
+use std::{fmt, str::FromStr};
+
+/// A kind of sample sequence.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Sampler {
+    /// Independent, uniformly distributed random numbers.
+    #[default]
+    Independent,
+    /// Stratified (jittered) sampling. Each dimension is cut up into one
+    /// stratum (or grid cell, in 2D) per sample, and each sample lands
+    /// somewhere random in its own stratum.
+    Stratified,
+    /// The Halton sequence, randomized with a Cranley-Patterson rotation for
+    /// each pixel.
+    Halton,
+    /// The first two dimensions of the Sobol sequence, Owen scrambled and
+    /// shuffled for each pixel and dimension, as in Brent Burley's "Practical
+    /// Hash-based Owen Scrambling".
+    Sobol,
+    /// Andrew Kensler's correlated multi-jittered sampling, which is
+    /// stratified in 2D and in each of the 1D projections.
+    CorrelatedMultiJittered,
+}
+
+/// The names of all the samplers, as accepted by `Sampler::from_str`.
+pub const SAMPLER_NAMES: &[&str] = &["independent", "stratified", "halton", "sobol", "cmj"];
+
+impl fmt::Display for Sampler {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Sampler::Independent => "independent",
+            Sampler::Stratified => "stratified",
+            Sampler::Halton => "halton",
+            Sampler::Sobol => "sobol",
+            Sampler::CorrelatedMultiJittered => "cmj",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl FromStr for Sampler {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "independent" | "random" => Ok(Sampler::Independent),
+            "stratified" | "jittered" => Ok(Sampler::Stratified),
+            "halton" => Ok(Sampler::Halton),
+            "sobol" => Ok(Sampler::Sobol),
+            "cmj" | "correlated-multi-jittered" => Ok(Sampler::CorrelatedMultiJittered),
+            _ => Err(format!(
+                "unknown sampler {:?} (expected one of: {})",
+                s,
+                SAMPLER_NAMES.join(", ")
+            )),
+        }
+    }
+}
+
+impl Sampler {
+    /// Start taking sample number `index` (counting from 0) of the `count`
+    /// samples that pixel (`x`, `y`) will get. `rng` is used for jittering,
+    /// and for any dimensions that the sampler can't provide. `seed` picks
+    /// which scrambling of the sequence each pixel gets.
+    ///
+    /// # Usage
+    ///
+    /// ```
+    /// # use weekend_tracer_rs::sampler::Sampler;
+    /// let mut rng = rand::thread_rng();
+    ///
+    /// // Every 1D value of a stratified pixel lands in a different quarter.
+    /// let mut strata = (0..4)
+    ///     .map(|index| {
+    ///         let mut samples = Sampler::Stratified.start(&mut rng, 0, (3, 4), index, 4);
+    ///         (samples.next_1d() * 4.0) as usize
+    ///     })
+    ///     .collect::<Vec<_>>();
+    /// strata.sort();
+    /// assert_eq!(strata, vec![0, 1, 2, 3]);
+    /// ```
+    pub fn start<'a>(
+        self,
+        rng: &'a mut dyn RngCore,
+        seed: u64,
+        (x, y): (usize, usize),
+        index: usize,
+        count: usize,
+    ) -> SampleStream<'a> {
+        SampleStream {
+            sampler: self,
+            rng,
+            pixel_seed: mix(mix(seed, x as u64), y as u64),
+            index: index as u32,
+            count: count.max(1) as u32,
+            dimension: 0,
+        }
+    }
+}
+
+/// The values for each dimension of a single sample, in turn.
+pub struct SampleStream<'a> {
+    sampler: Sampler,
+    rng: &'a mut dyn RngCore,
+    pixel_seed: u64,
+    index: u32,
+    count: u32,
+    dimension: u32,
+}
+
+impl fmt::Debug for SampleStream<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SampleStream")
+            .field("sampler", &self.sampler)
+            .field("index", &self.index)
+            .field("count", &self.count)
+            .field("dimension", &self.dimension)
+            .finish()
+    }
+}
+
+impl<'a> SampleStream<'a> {
+    /// A stream of independent random numbers, for when there's no pixel
+    /// sample to speak of.
+    pub fn independent(rng: &'a mut dyn RngCore) -> Self {
+        Sampler::Independent.start(rng, 0, (0, 0), 0, 1)
+    }
+
+    /// The random number generator behind the stream, for anything that
+    /// really does just want random numbers.
+    pub fn rng(&mut self) -> &mut dyn RngCore {
+        self.rng
+    }
+
+    /// The value for the next dimension, in `[0, 1)`.
+    pub fn next_1d(&mut self) -> f32 {
+        let seed = self.dimension_seed();
+        self.dimension += 1;
+
+        // The sequences only have `count` well-spread-out points. Any samples
+        // past that (say, if a resumed render asks for more samples than the
+        // original did) just get random numbers.
+        if self.index >= self.count {
+            return self.rng.gen();
+        }
+
+        match self.sampler {
+            Sampler::Independent => self.rng.gen(),
+            Sampler::Stratified | Sampler::CorrelatedMultiJittered => {
+                let stratum = permute(self.index, self.count, seed);
+                let jitter = match self.sampler {
+                    Sampler::Stratified => self.rng.gen(),
+                    _ => rand_float(self.index, seed.wrapping_mul(0x967a_889b)),
+                };
+                ((stratum as f32 + jitter) / self.count as f32).min(ONE_MINUS_EPSILON)
+            }
+            Sampler::Halton => match PRIMES.get(self.dimension as usize - 1) {
+                Some(&base) => rotate(radical_inverse(base, self.index), seed),
+                None => self.rng.gen(),
+            },
+            Sampler::Sobol => {
+                let index = nested_uniform_scramble(self.index, seed);
+                to_float(nested_uniform_scramble(
+                    sobol_first_dimension(index),
+                    hash_u32(seed, 1),
+                ))
+            }
+        }
+    }
+
+    /// The values for the next two dimensions, in `[0, 1)`. These are spread
+    /// out evenly in 2D, not just individually.
+    pub fn next_2d(&mut self) -> (f32, f32) {
+        let seed = self.dimension_seed();
+
+        if self.index >= self.count {
+            self.dimension += 2;
+            return (self.rng.gen(), self.rng.gen());
+        }
+
+        match self.sampler {
+            Sampler::Independent => {
+                self.dimension += 2;
+                (self.rng.gen(), self.rng.gen())
+            }
+            Sampler::Stratified => {
+                self.dimension += 2;
+
+                // Use the squarest grid with at least one cell per sample.
+                let columns = (self.count as f32).sqrt().ceil() as u32;
+                let rows = self.count.div_ceil(columns);
+                let cell = permute(self.index, columns * rows, seed);
+
+                (
+                    ((cell % columns) as f32 + self.rng.gen::<f32>()) / columns as f32,
+                    ((cell / columns) as f32 + self.rng.gen::<f32>()) / rows as f32,
+                )
+            }
+            Sampler::Halton => (self.next_1d(), self.next_1d()),
+            Sampler::Sobol => {
+                self.dimension += 2;
+
+                let index = nested_uniform_scramble(self.index, seed);
+                let x = nested_uniform_scramble(sobol_first_dimension(index), hash_u32(seed, 1));
+                let y = nested_uniform_scramble(sobol_second_dimension(index), hash_u32(seed, 2));
+
+                (to_float(x), to_float(y))
+            }
+            Sampler::CorrelatedMultiJittered => {
+                self.dimension += 2;
+                correlated_multi_jittered(self.index, self.count, seed)
+            }
+        }
+    }
+
+    /// A seed that's different for every pixel and every dimension.
+    fn dimension_seed(&self) -> u32 {
+        mix(self.pixel_seed, u64::from(self.dimension)) as u32
+    }
+}
+
+/// The largest `f32` below 1.
+const ONE_MINUS_EPSILON: f32 = 1.0 - f32::EPSILON / 2.0;
+
+/// The first 64 primes, used as the bases of the Halton sequence's
+/// dimensions.
+const PRIMES: [u32; 64] = [
+    2, 3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37, 41, 43, 47, 53, 59, 61, 67, 71, 73, 79, 83, 89, 97,
+    101, 103, 107, 109, 113, 127, 131, 137, 139, 149, 151, 157, 163, 167, 173, 179, 181, 191, 193,
+    197, 199, 211, 223, 227, 229, 233, 239, 241, 251, 257, 263, 269, 271, 277, 281, 283, 293, 307,
+    311,
+];
+
+/// Mix `value` into `seed`, using the SplitMix64 finalizer. Handy for
+/// deriving lots of unrelated seeds from one.
+///
+/// # Usage
+///
+/// ```
+/// # use weekend_tracer_rs::sampler::mix;
+/// assert_eq!(mix(1, 2), mix(1, 2));
+/// assert_ne!(mix(1, 2), mix(2, 1));
+/// ```
+pub fn mix(seed: u64, value: u64) -> u64 {
+    let mut z = seed
+        ^ value
+            .wrapping_add(0x9e37_79b9_7f4a_7c15)
+            .wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+fn hash_u32(seed: u32, value: u32) -> u32 {
+    mix(u64::from(seed), u64::from(value)) as u32
+}
+
+/// Convert 32 random bits into a float in `[0, 1)`.
+fn to_float(bits: u32) -> f32 {
+    (bits >> 8) as f32 / (1 << 24) as f32
+}
+
+/// Shift `x` by a random amount (chosen by `seed`), wrapping around at 1.
+fn rotate(x: f32, seed: u32) -> f32 {
+    let shifted = x + to_float(seed);
+    let wrapped = if shifted >= 1.0 {
+        shifted - 1.0
+    } else {
+        shifted
+    };
+    wrapped.min(ONE_MINUS_EPSILON)
+}
+
+/// Reflect the digits of `index`, written in some `base`, about the decimal
+/// point.
+fn radical_inverse(base: u32, mut index: u32) -> f32 {
+    let inverse_base = 1.0 / f64::from(base);
+    let mut reversed = 0.0;
+    let mut digit_weight = inverse_base;
+
+    while index > 0 {
+        reversed += f64::from(index % base) * digit_weight;
+        index /= base;
+        digit_weight *= inverse_base;
+    }
+
+    (reversed as f32).min(ONE_MINUS_EPSILON)
+}
+
+/// The first dimension of the Sobol sequence, which is just the index with
+/// its bits reversed.
+fn sobol_first_dimension(index: u32) -> u32 {
+    index.reverse_bits()
+}
+
+/// The second dimension of the Sobol sequence, with the direction numbers of
+/// the primitive polynomial `x + 1`.
+fn sobol_second_dimension(index: u32) -> u32 {
+    let mut direction = 1 << 31;
+    let mut result = 0;
+
+    for bit in 0..32 {
+        if index & (1 << bit) != 0 {
+            result ^= direction;
+        }
+        direction ^= direction >> 1;
+    }
+
+    result
+}
+
+/// Samuli Laine and Tero Karras' hash, which only lets each bit affect the
+/// bits above it.
+fn laine_karras_permutation(mut x: u32, seed: u32) -> u32 {
+    x = x.wrapping_add(seed);
+    x ^= x.wrapping_mul(0x6c50_b47c);
+    x ^= x.wrapping_mul(0xb82f_1e52);
+    x ^= x.wrapping_mul(0xc7af_e638);
+    x ^= x.wrapping_mul(0x8d22_f6e6);
+    x
+}
+
+/// Owen scrambling of the bits of `x`: each bit gets flipped (or not)
+/// depending on the bits more significant than it.
+fn nested_uniform_scramble(x: u32, seed: u32) -> u32 {
+    laine_karras_permutation(x.reverse_bits(), seed).reverse_bits()
+}
+
+/// Andrew Kensler's hash-based permutation of `[0, length)`. `seed` chooses
+/// which permutation.
+fn permute(mut i: u32, length: u32, seed: u32) -> u32 {
+    let p = seed;
+    let mut w = length - 1;
+    w |= w >> 1;
+    w |= w >> 2;
+    w |= w >> 4;
+    w |= w >> 8;
+    w |= w >> 16;
+
+    loop {
+        i ^= p;
+        i = i.wrapping_mul(0xe170_893d);
+        i ^= p >> 16;
+        i ^= (i & w) >> 4;
+        i ^= p >> 8;
+        i = i.wrapping_mul(0x0929_eb3f);
+        i ^= p >> 23;
+        i ^= (i & w) >> 1;
+        i = i.wrapping_mul(1 | p >> 27);
+        i = i.wrapping_mul(0x6935_fa69);
+        i ^= (i & w) >> 11;
+        i = i.wrapping_mul(0x74dc_b303);
+        i ^= (i & w) >> 2;
+        i = i.wrapping_mul(0x9e50_1cc3);
+        i ^= (i & w) >> 2;
+        i = i.wrapping_mul(0xc860_a3df);
+        i &= w;
+        i ^= i >> 5;
+
+        if i < length {
+            break;
+        }
+    }
+
+    (i.wrapping_add(p)) % length
+}
+
+/// Andrew Kensler's hash-based random float in `[0, 1)`.
+fn rand_float(mut i: u32, p: u32) -> f32 {
+    i ^= p;
+    i ^= i >> 17;
+    i ^= i >> 10;
+    i = i.wrapping_mul(0xb365_34e5);
+    i ^= i >> 12;
+    i ^= i >> 21;
+    i = i.wrapping_mul(0x93fc_4795);
+    i ^= 0xdf6e_307f;
+    i ^= i >> 17;
+    i = i.wrapping_mul(1 | p >> 18);
+    (i as f32 * (1.0 / 4_294_967_808.0)).min(ONE_MINUS_EPSILON)
+}
+
+/// Sample number `s` of `count` correlated multi-jittered samples. From
+/// Andrew Kensler's "Correlated Multi-Jittered Sampling".
+fn correlated_multi_jittered(s: u32, count: u32, p: u32) -> (f32, f32) {
+    let m = (count as f32).sqrt() as u32;
+    let n = count.div_ceil(m);
+
+    let s = permute(s, count, p.wrapping_mul(0x5163_3e2d));
+    let sx = permute(s % m, m, p.wrapping_mul(0x68bc_21eb));
+    let sy = permute(s / m, n, p.wrapping_mul(0x02e5_be93));
+    let jx = rand_float(s, p.wrapping_mul(0x967a_889b));
+    let jy = rand_float(s, p.wrapping_mul(0x368c_c8b7));
+
+    (
+        ((sx as f32 + (sy as f32 + jx) / n as f32) / m as f32).min(ONE_MINUS_EPSILON),
+        ((s as f32 + jy) / count as f32).min(ONE_MINUS_EPSILON),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLERS: &[Sampler] = &[
+        Sampler::Independent,
+        Sampler::Stratified,
+        Sampler::Halton,
+        Sampler::Sobol,
+        Sampler::CorrelatedMultiJittered,
+    ];
+
+    #[test]
+    fn samples_are_in_range() {
+        let mut rng = rand::thread_rng();
+        for &sampler in SAMPLERS {
+            for index in 0..20 {
+                let mut samples = sampler.start(&mut rng, 3, (7, 11), index, 16);
+                for _ in 0..40 {
+                    let x = samples.next_1d();
+                    let (y, z) = samples.next_2d();
+                    for &value in &[x, y, z] {
+                        assert!((0.0..1.0).contains(&value), "{} gave {}", sampler, value);
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn samples_are_stratified() {
+        let mut rng = rand::thread_rng();
+        let stratified = &[
+            Sampler::Stratified,
+            Sampler::Sobol,
+            Sampler::CorrelatedMultiJittered,
+        ];
+        for &sampler in stratified {
+            // Skip a few dimensions, and check that the 16 2D samples of a
+            // pixel land in different cells of a 4x4 grid, and in different
+            // 1D strata along each axis.
+            let mut cells = [0; 16];
+            let mut columns = [0; 16];
+            for index in 0..16 {
+                let mut samples = sampler.start(&mut rng, 3, (5, 9), index, 16);
+                samples.next_2d();
+                samples.next_2d();
+                let (x, y) = samples.next_2d();
+
+                cells[(y * 4.0) as usize * 4 + (x * 4.0) as usize] += 1;
+                columns[(x * 16.0) as usize] += 1;
+            }
+
+            assert!(cells.iter().all(|&count| count == 1), "{}", sampler);
+            if sampler != Sampler::Stratified {
+                assert!(columns.iter().all(|&count| count == 1), "{}", sampler);
+            }
+        }
+    }
+
+    #[test]
+    fn permute_is_a_permutation() {
+        for &length in &[1, 2, 7, 16, 100] {
+            let mut seen = vec![false; length as usize];
+            for i in 0..length {
+                seen[permute(i, length, 0xdead_beef) as usize] = true;
+            }
+            assert!(seen.iter().all(|&seen| seen));
+        }
+    }
+}
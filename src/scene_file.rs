@@ -0,0 +1,1400 @@
+//! Declarative scene files.
+//!
+//! Scenes can be described in a [RON](https://github.com/ron-rs/ron) file
+//! instead of being hard-coded. A scene file has a camera, a background colour,
+//! a table of named materials, a list of objects, and a list of objects that
+//! should be importance-sampled (usually the lights):
+//!
+//! ```text
+//! Scene(
+//!     camera: (
+//!         look_from: (278.0, 278.0, -800.0),
+//!         look_at: (278.0, 278.0, 0.0),
+//!         vfov: 40.0,
+//!     ),
+//!     background: (0.0, 0.0, 0.0),
+//!     materials: {
+//!         "white": Lambertian(albedo: Constant((0.73, 0.73, 0.73))),
+//!         "light": DiffuseLight(emit: Constant((7.0, 7.0, 7.0))),
+//!     },
+//!     objects: [
+//!         XZRect(x0: 0.0, x1: 555.0, z0: 0.0, z1: 555.0, k: 0.0, material: Named("white")),
+//!         FlipFace(XZRect(x0: 213.0, x1: 343.0, z0: 227.0, z1: 332.0, k: 554.0, material: Named("light"))),
+//!         Translate(
+//!             offset: (265.0, 0.0, 295.0),
+//!             object: Rotate(
+//!                 axis: Y,
+//!                 angle: 15.0,
+//!                 object: Scale(
+//!                     factor: (165.0, 330.0, 165.0),
+//!                     object: Block(min: (0.0, 0.0, 0.0), max: (1.0, 1.0, 1.0), material: Named("white")),
+//!                 ),
+//!             ),
+//!         ),
+//!     ],
+//!     lights: [
+//!         XZRect(x0: 213.0, x1: 343.0, z0: 227.0, z1: 332.0, k: 554.0, material: Named("light")),
+//!     ],
+//! )
+//! ```
+//!
+//! Triangle meshes can be loaded from OBJ, PLY and glTF files (see the `mesh`
+//! module). A glTF file brings along its own materials, and can hold a whole
+//! scene of meshes:
+//!
+//! ```text
+//! Mesh(path: "teapot.obj", material: Named("white"))
+//! Mesh(path: "bunny.ply")
+//! Mesh(path: "living_room.glb")
+//! ```
+//!
+//! Terrain can be made from a greyscale height map image:
+//!
+//! ```text
+//! Heightfield(path: "terrain.png", corner: (-50.0, 0.0, -50.0), size: (100.0, 10.0, 100.0), material: Named("grass"))
+//! ```
+//!
+//! Relative image texture, mesh and height map paths are resolved relative to
+//! the scene file.
+
+use crate::{
+    bvh::BVH,
+    camera::Camera,
+    hittable::{
+        aa_rect::{XYRect, XZRect, YZRect},
+        block::Block,
+        cone::Cone,
+        constant_medium::ConstantMedium,
+        csg::CSG,
+        curve::Curve,
+        cylinder::Cylinder,
+        disk::Disk,
+        heightfield::Heightfield,
+        moving_sphere::MovingSphere,
+        plane::Plane,
+        quad::Quad,
+        sphere::Sphere,
+        torus::Torus,
+        triangle::Triangle,
+        world::World,
+        Hittable,
+    },
+    material::{conductor::Ior, Material},
+    mesh,
+    mesh::MeshError,
+    quaternion::{EulerOrder, Quaternion},
+    scenes::Scene,
+    texture,
+    texture::Texture,
+    vec3,
+    vec3::{Axis, Vec3},
+};
+use ron::extensions::Extensions;
+use serde::Deserialize;
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    fmt, io,
+    path::{Path, PathBuf},
+};
+
+/// How many times a named material may refer to another named material before
+/// we give up and assume that there's a cycle.
+const MAX_MATERIAL_ALIAS_DEPTH: usize = 16;
+
+/// Something that went wrong while loading a scene file.
+#[derive(Debug)]
+pub enum SceneError {
+    /// The scene file couldn't be read.
+    Io { path: PathBuf, source: io::Error },
+    /// The scene file isn't valid. `line` and `column` are 1-based.
+    Parse {
+        path: PathBuf,
+        line: usize,
+        column: usize,
+        message: String,
+    },
+    /// A `Named` material was used, but never defined in `materials`.
+    UnknownMaterial { path: PathBuf, name: String },
+    /// An image texture couldn't be loaded.
+    Texture {
+        path: PathBuf,
+        image_path: PathBuf,
+        source: image::ImageError,
+    },
+    /// A mesh couldn't be loaded.
+    Mesh { path: PathBuf, source: MeshError },
+    /// A heightfield's image couldn't be loaded, or is smaller than 2x2
+    /// pixels.
+    Heightfield {
+        path: PathBuf,
+        image_path: PathBuf,
+        source: image::ImageError,
+    },
+    /// An object was scaled by 0 along some axis, which flattens it.
+    DegenerateScale { path: PathBuf, factor: Vec3 },
+    /// An object was rotated about a zero-length axis, or by a zero
+    /// quaternion, neither of which is a rotation.
+    DegenerateRotation { path: PathBuf },
+    /// The scene doesn't have any objects in it.
+    Empty { path: PathBuf },
+}
+
+impl fmt::Display for SceneError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io { path, source } => {
+                write!(
+                    f,
+                    "{}: could not read scene file: {}",
+                    path.display(),
+                    source
+                )
+            }
+            Self::Parse {
+                path,
+                line,
+                column,
+                message,
+            } => write!(f, "{}:{}:{}: {}", path.display(), line, column, message),
+            Self::UnknownMaterial { path, name } => write!(
+                f,
+                "{}: material {:?} is used but never defined (or is part of a cycle)",
+                path.display(),
+                name
+            ),
+            Self::Texture {
+                path,
+                image_path,
+                source,
+            } => write!(
+                f,
+                "{}: could not load image texture {}: {}",
+                path.display(),
+                image_path.display(),
+                source
+            ),
+            Self::Mesh { path, source } => {
+                write!(f, "{}: could not load mesh: {}", path.display(), source)
+            }
+            Self::Heightfield {
+                path,
+                image_path,
+                source,
+            } => write!(
+                f,
+                "{}: could not load heightfield {}: {}",
+                path.display(),
+                image_path.display(),
+                source
+            ),
+            Self::DegenerateScale { path, factor } => write!(
+                f,
+                "{}: can't scale an object by ({}, {}, {}), which is 0 along some axis",
+                path.display(),
+                factor.0,
+                factor.1,
+                factor.2
+            ),
+            Self::DegenerateRotation { path } => write!(
+                f,
+                "{}: can't rotate an object about a zero-length axis or by a zero quaternion",
+                path.display()
+            ),
+            Self::Empty { path } => {
+                write!(f, "{}: the scene doesn't have any objects", path.display())
+            }
+        }
+    }
+}
+
+impl std::error::Error for SceneError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Io { source, .. } => Some(source),
+            Self::Texture { source, .. } => Some(source),
+            Self::Mesh { source, .. } => Some(source),
+            Self::Heightfield { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+/// The top-level description of a scene file.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename = "Scene", deny_unknown_fields)]
+pub struct SceneDescription {
+    pub camera: CameraDescription,
+    #[serde(default)]
+    pub background: Vec3,
+    #[serde(default)]
+    pub materials: HashMap<String, MaterialDescription>,
+    pub objects: Vec<ObjectDescription>,
+    #[serde(default)]
+    pub lights: Vec<ObjectDescription>,
+}
+
+/// Describes a `Camera`. The aspect ratio isn't part of the scene, and is
+/// instead taken from the dimensions of the render.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CameraDescription {
+    pub look_from: Vec3,
+    pub look_at: Vec3,
+    #[serde(default = "default_vup")]
+    pub vup: Vec3,
+    /// Vertical field of view, in degrees.
+    #[serde(default = "default_vfov")]
+    pub vfov: f32,
+    #[serde(default)]
+    pub aperture: f32,
+    /// Defaults to the distance between `look_from` and `look_at`.
+    #[serde(default)]
+    pub focus_distance: Option<f32>,
+    #[serde(default)]
+    pub time0: f32,
+    #[serde(default = "default_time1")]
+    pub time1: f32,
+}
+
+fn default_vup() -> Vec3 {
+    vec3!(0.0, 1.0, 0.0)
+}
+
+fn default_vfov() -> f32 {
+    40.0
+}
+
+fn default_time1() -> f32 {
+    1.0
+}
+
+fn default_capped() -> bool {
+    true
+}
+
+fn default_segments() -> usize {
+    1
+}
+
+fn default_shine() -> f32 {
+    0.2
+}
+
+fn default_roughness() -> f32 {
+    0.1
+}
+
+fn default_albedo() -> Vec3 {
+    vec3!(1.0, 1.0, 1.0)
+}
+
+/// Describes a `Texture`, using the constructors in the `texture` module.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub enum TextureDescription {
+    Constant(Vec3),
+    Checkerboard {
+        odd: Box<TextureDescription>,
+        even: Box<TextureDescription>,
+    },
+    PerlinNoise {
+        scale: f32,
+    },
+    PerlinTurbulence {
+        scale: f32,
+        #[serde(default)]
+        depth: Option<usize>,
+    },
+    SimpleMarble {
+        scale: f32,
+        axis: Axis,
+    },
+    Image {
+        path: PathBuf,
+    },
+}
+
+/// Describes a `Material`. `Named` refers to an entry in the scene's
+/// `materials` table.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub enum MaterialDescription {
+    Lambertian {
+        albedo: TextureDescription,
+    },
+    /// The books' fuzzy metal, made into a `Conductor` that reflects
+    /// `albedo` head on, with `fuzz` as its roughness.
+    Metal {
+        albedo: Vec3,
+        #[serde(default)]
+        fuzz: f32,
+    },
+    /// A physically based metal, like `Conductor(ior: Gold, roughness: 0.2)`.
+    /// `roughness_v` makes it rougher (or smoother) across the surface's
+    /// tangent than along it.
+    Conductor {
+        ior: Ior,
+        #[serde(default)]
+        roughness: f32,
+        #[serde(default)]
+        roughness_v: Option<f32>,
+    },
+    /// Glass, water and so on. Any `roughness` above 0 makes it frosted, like
+    /// `Dielectric(refractive_index: 1.5, roughness: 0.3)`.
+    Dielectric {
+        refractive_index: f32,
+        #[serde(default)]
+        density: f32,
+        #[serde(default = "default_albedo")]
+        albedo: Vec3,
+        #[serde(default)]
+        roughness: f32,
+    },
+    DiffuseLight {
+        emit: TextureDescription,
+    },
+    Isotropic {
+        albedo: TextureDescription,
+    },
+    /// For `Curve`s. See `material::hair`.
+    Hair {
+        albedo: TextureDescription,
+        #[serde(default = "default_shine")]
+        shine: f32,
+        #[serde(default = "default_roughness")]
+        roughness: f32,
+    },
+    Named(String),
+}
+
+/// Describes a `Hittable` object.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub enum ObjectDescription {
+    Sphere {
+        center: Vec3,
+        radius: f32,
+        material: MaterialDescription,
+    },
+    MovingSphere {
+        center0: Vec3,
+        center1: Vec3,
+        #[serde(default)]
+        time0: f32,
+        #[serde(default = "default_time1")]
+        time1: f32,
+        radius: f32,
+        material: MaterialDescription,
+    },
+    XYRect {
+        x0: f32,
+        x1: f32,
+        y0: f32,
+        y1: f32,
+        k: f32,
+        material: MaterialDescription,
+    },
+    XZRect {
+        x0: f32,
+        x1: f32,
+        z0: f32,
+        z1: f32,
+        k: f32,
+        material: MaterialDescription,
+    },
+    YZRect {
+        y0: f32,
+        y1: f32,
+        z0: f32,
+        z1: f32,
+        k: f32,
+        material: MaterialDescription,
+    },
+    /// An infinite plane, facing towards `normal`.
+    Plane {
+        point: Vec3,
+        normal: Vec3,
+        material: MaterialDescription,
+    },
+    /// A parallelogram with a corner at `corner` and sides along `u` and
+    /// `v`, facing towards `u × v`.
+    Quad {
+        corner: Vec3,
+        u: Vec3,
+        v: Vec3,
+        material: MaterialDescription,
+    },
+    Disk {
+        center: Vec3,
+        normal: Vec3,
+        radius: f32,
+        material: MaterialDescription,
+    },
+    Cylinder {
+        base: Vec3,
+        top: Vec3,
+        radius: f32,
+        #[serde(default = "default_capped")]
+        capped: bool,
+        material: MaterialDescription,
+    },
+    Cone {
+        base: Vec3,
+        apex: Vec3,
+        radius: f32,
+        #[serde(default = "default_capped")]
+        capped: bool,
+        material: MaterialDescription,
+    },
+    /// A torus going around `axis`.
+    Torus {
+        center: Vec3,
+        axis: Vec3,
+        major_radius: f32,
+        minor_radius: f32,
+        material: MaterialDescription,
+    },
+    Block {
+        min: Vec3,
+        max: Vec3,
+        material: MaterialDescription,
+    },
+    /// A strand along a cubic Bézier curve, `width.0` wide at the start and
+    /// `width.1` wide at the end. Long, bendy strands should be cut into a
+    /// few `segments`.
+    Curve {
+        points: (Vec3, Vec3, Vec3, Vec3),
+        width: (f32, f32),
+        #[serde(default = "default_segments")]
+        segments: usize,
+        material: MaterialDescription,
+    },
+    /// Terrain from a greyscale height map image, covering `size` along X and
+    /// Z from `corner`, with white pixels `size.1` high.
+    Heightfield {
+        path: PathBuf,
+        corner: Vec3,
+        size: Vec3,
+        material: MaterialDescription,
+    },
+    Triangle {
+        /// In counterclockwise order, looking at the front of the triangle.
+        vertices: (Vec3, Vec3, Vec3),
+        material: MaterialDescription,
+    },
+    /// The triangle meshes in a model file. Faces that don't get a material
+    /// from the file use `material`, or a plain grey if there isn't one.
+    Mesh {
+        path: PathBuf,
+        #[serde(default)]
+        material: Option<MaterialDescription>,
+    },
+    ConstantMedium {
+        boundary: Box<ObjectDescription>,
+        density: f32,
+        albedo: TextureDescription,
+    },
+    Group(Vec<ObjectDescription>),
+    /// Everything inside either of two closed objects.
+    Union(Box<ObjectDescription>, Box<ObjectDescription>),
+    /// Everything inside both of two closed objects.
+    Intersection(Box<ObjectDescription>, Box<ObjectDescription>),
+    /// The first closed object, with the second one carved out of it.
+    Difference(Box<ObjectDescription>, Box<ObjectDescription>),
+    Translate {
+        offset: Vec3,
+        object: Box<ObjectDescription>,
+    },
+    Rotate {
+        axis: Axis,
+        /// In degrees, counterclockwise.
+        angle: f32,
+        object: Box<ObjectDescription>,
+    },
+    /// Rotates about an arbitrary axis through the origin.
+    RotateAbout {
+        axis: Vec3,
+        /// In degrees, counterclockwise.
+        angle: f32,
+        object: Box<ObjectDescription>,
+    },
+    /// Rotates about the X, Y and Z axes by the matching component of
+    /// `angles` (in degrees, counterclockwise), in the given `order`.
+    RotateEuler {
+        order: EulerOrder,
+        angles: Vec3,
+        object: Box<ObjectDescription>,
+    },
+    /// Rotates by a quaternion, like `(w: 0.924, x: 0.0, y: 0.383, z: 0.0)`,
+    /// which doesn't need to be normalized.
+    RotateQuaternion {
+        rotation: Quaternion,
+        object: Box<ObjectDescription>,
+    },
+    /// Scales about the origin. Every component of `factor` has to be
+    /// non-zero; negative components mirror the object.
+    Scale {
+        factor: Vec3,
+        object: Box<ObjectDescription>,
+    },
+    FlipFace(Box<ObjectDescription>),
+}
+
+/// Load a scene from a RON scene file. `aspect` is the aspect ratio
+/// (width:height) of the render, which is needed for the camera.
+pub fn load<P: AsRef<Path>>(path: P, aspect: f32) -> Result<Scene, SceneError> {
+    load_with_assets(path, aspect).map(|(scene, _)| scene)
+}
+
+/// Like `load`, but also returns the paths of the files that the scene file
+/// names (meshes, heightfields and image textures), in the order they were
+/// first loaded. Files that those pull in themselves, like a glTF file's
+/// buffers, aren't included.
+pub fn load_with_assets<P: AsRef<Path>>(
+    path: P,
+    aspect: f32,
+) -> Result<(Scene, Vec<PathBuf>), SceneError> {
+    let path = path.as_ref();
+    let source = std::fs::read_to_string(path).map_err(|source| SceneError::Io {
+        path: path.to_path_buf(),
+        source,
+    })?;
+
+    parse_with_assets(&source, path, aspect)
+}
+
+/// Parse a scene from a string. `path` is only used for error messages and to
+/// resolve relative texture paths.
+pub fn from_str(source: &str, path: &Path, aspect: f32) -> Result<Scene, SceneError> {
+    parse_with_assets(source, path, aspect).map(|(scene, _)| scene)
+}
+
+fn parse_with_assets(
+    source: &str,
+    path: &Path,
+    aspect: f32,
+) -> Result<(Scene, Vec<PathBuf>), SceneError> {
+    let description: SceneDescription = ron::Options::default()
+        .with_default_extension(Extensions::IMPLICIT_SOME)
+        .from_str(source)
+        .map_err(|e| SceneError::Parse {
+            path: path.to_path_buf(),
+            line: e.position.line,
+            column: e.position.col,
+            message: e.code.to_string(),
+        })?;
+
+    let builder = Builder {
+        path,
+        base_dir: path.parent().unwrap_or_else(|| Path::new("")),
+        materials: &description.materials,
+        assets: RefCell::default(),
+    };
+    let scene = builder.build(&description, aspect)?;
+
+    Ok((scene, builder.assets.into_inner()))
+}
+
+/// Turns descriptions into actual scene objects.
+struct Builder<'a> {
+    path: &'a Path,
+    base_dir: &'a Path,
+    materials: &'a HashMap<String, MaterialDescription>,
+    /// The files loaded so far.
+    assets: RefCell<Vec<PathBuf>>,
+}
+
+impl Builder<'_> {
+    /// Resolve a path from the scene file, and remember it as an asset.
+    fn asset(&self, path: &Path) -> PathBuf {
+        let resolved = self.base_dir.join(path);
+        let mut assets = self.assets.borrow_mut();
+        if !assets.contains(&resolved) {
+            assets.push(resolved.clone());
+        }
+        resolved
+    }
+
+    fn build(&self, description: &SceneDescription, aspect: f32) -> Result<Scene, SceneError> {
+        let world = World::new(self.objects(&description.objects)?);
+        if world.objects.is_empty() {
+            return Err(SceneError::Empty {
+                path: self.path.to_path_buf(),
+            });
+        }
+
+        let lights = World::new(self.objects(&description.lights)?);
+
+        let c = &description.camera;
+        let camera = Camera::new(
+            c.look_from,
+            c.look_at,
+            c.vup,
+            c.vfov,
+            aspect,
+            c.aperture,
+            c.focus_distance
+                .unwrap_or_else(|| (c.look_from - c.look_at).length()),
+            c.time0,
+            c.time1,
+        );
+
+        Ok(Scene {
+            world,
+            lights,
+            camera,
+            background: description.background,
+        })
+    }
+
+    fn objects(&self, objects: &[ObjectDescription]) -> Result<Vec<Box<dyn Hittable>>, SceneError> {
+        let mut built: Vec<Box<dyn Hittable>> = Vec::with_capacity(objects.len());
+        for object in objects {
+            match object {
+                // Empty groups don't add anything, so they're left out. That
+                // way a scene made of nothing but empty groups is still empty.
+                ObjectDescription::Group(children) => {
+                    let children = self.objects(children)?;
+                    if !children.is_empty() {
+                        built.push(Box::new(World::new(children)));
+                    }
+                }
+                _ => built.push(self.object(object)?),
+            }
+        }
+        Ok(built)
+    }
+
+    fn object(&self, object: &ObjectDescription) -> Result<Box<dyn Hittable>, SceneError> {
+        use ObjectDescription as O;
+
+        Ok(match object {
+            O::Sphere {
+                center,
+                radius,
+                material,
+            } => Box::new(Sphere::new(*center, *radius, self.material(material)?)),
+
+            O::MovingSphere {
+                center0,
+                center1,
+                time0,
+                time1,
+                radius,
+                material,
+            } => Box::new(MovingSphere::new(
+                *center0,
+                *center1,
+                *time0,
+                *time1,
+                *radius,
+                self.material(material)?,
+            )),
+
+            O::XYRect {
+                x0,
+                x1,
+                y0,
+                y1,
+                k,
+                material,
+            } => Box::new(XYRect::new(
+                *x0,
+                *x1,
+                *y0,
+                *y1,
+                *k,
+                self.material(material)?,
+            )),
+
+            O::XZRect {
+                x0,
+                x1,
+                z0,
+                z1,
+                k,
+                material,
+            } => Box::new(XZRect::new(
+                *x0,
+                *x1,
+                *z0,
+                *z1,
+                *k,
+                self.material(material)?,
+            )),
+
+            O::YZRect {
+                y0,
+                y1,
+                z0,
+                z1,
+                k,
+                material,
+            } => Box::new(YZRect::new(
+                *y0,
+                *y1,
+                *z0,
+                *z1,
+                *k,
+                self.material(material)?,
+            )),
+
+            O::Plane {
+                point,
+                normal,
+                material,
+            } => Box::new(Plane::new(*point, *normal, self.material(material)?)),
+
+            O::Quad {
+                corner,
+                u,
+                v,
+                material,
+            } => Box::new(Quad::new(*corner, *u, *v, self.material(material)?)),
+
+            O::Disk {
+                center,
+                normal,
+                radius,
+                material,
+            } => Box::new(Disk::new(
+                *center,
+                *normal,
+                *radius,
+                self.material(material)?,
+            )),
+
+            O::Cylinder {
+                base,
+                top,
+                radius,
+                capped,
+                material,
+            } => {
+                let cylinder = Cylinder::new(*base, *top, *radius, self.material(material)?);
+                if *capped {
+                    Box::new(cylinder)
+                } else {
+                    Box::new(cylinder.uncapped())
+                }
+            }
+
+            O::Cone {
+                base,
+                apex,
+                radius,
+                capped,
+                material,
+            } => {
+                let cone = Cone::new(*base, *apex, *radius, self.material(material)?);
+                if *capped {
+                    Box::new(cone)
+                } else {
+                    Box::new(cone.uncapped())
+                }
+            }
+
+            O::Torus {
+                center,
+                axis,
+                major_radius,
+                minor_radius,
+                material,
+            } => Box::new(Torus::new(
+                *center,
+                *axis,
+                *major_radius,
+                *minor_radius,
+                self.material(material)?,
+            )),
+
+            O::Block { min, max, material } => {
+                Box::new(Block::new(*min, *max, self.material(material)?))
+            }
+
+            O::Curve {
+                points: (a, b, c, d),
+                width,
+                segments,
+                material,
+            } => {
+                let curve = Curve::new([*a, *b, *c, *d], *width, self.material(material)?);
+                if *segments > 1 {
+                    let pieces = curve
+                        .split(*segments)
+                        .into_iter()
+                        .map(|piece| Box::new(piece) as Box<dyn Hittable>)
+                        .collect();
+                    Box::new(BVH::new(pieces, 0.0, 0.0))
+                } else {
+                    Box::new(curve)
+                }
+            }
+
+            O::Heightfield {
+                path,
+                corner,
+                size,
+                material,
+            } => {
+                let image_path = self.asset(path);
+                let error = |source| SceneError::Heightfield {
+                    path: self.path.to_path_buf(),
+                    image_path: image_path.clone(),
+                    source,
+                };
+
+                let img = image::open(&image_path).map_err(error)?;
+                let (width, height) = image::GenericImageView::dimensions(&img);
+                if width < 2 || height < 2 {
+                    return Err(error(image::ImageError::Parameter(
+                        image::error::ParameterError::from_kind(
+                            image::error::ParameterErrorKind::DimensionMismatch,
+                        ),
+                    )));
+                }
+
+                Box::new(Heightfield::from_image(
+                    img,
+                    *corner,
+                    *size,
+                    self.material(material)?,
+                ))
+            }
+
+            O::Triangle {
+                vertices: (a, b, c),
+                material,
+            } => Box::new(Triangle::new(*a, *b, *c, self.material(material)?)),
+
+            O::Mesh { path, material } => {
+                let material = match material {
+                    Some(material) => self.material(material)?,
+                    None => Material::lambertian(texture::constant(Vec3::from(0.73))),
+                };
+
+                Box::new(mesh::load(self.asset(path), material).map_err(|source| {
+                    SceneError::Mesh {
+                        path: self.path.to_path_buf(),
+                        source,
+                    }
+                })?)
+            }
+
+            O::ConstantMedium {
+                boundary,
+                density,
+                albedo,
+            } => Box::new(ConstantMedium::new(
+                self.object(boundary)?,
+                *density,
+                self.texture(albedo)?,
+            )),
+
+            O::Group(objects) => Box::new(World::new(self.objects(objects)?)),
+
+            O::Translate { offset, object } => Box::new(self.object(object)?.translate(*offset)),
+
+            O::Rotate {
+                axis,
+                angle,
+                object,
+            } => Box::new(self.object(object)?.rotate(*axis, *angle)),
+
+            O::RotateAbout {
+                axis,
+                angle,
+                object,
+            } => {
+                if axis.length_squared() == 0.0 {
+                    return Err(SceneError::DegenerateRotation {
+                        path: self.path.to_path_buf(),
+                    });
+                }
+                Box::new(self.object(object)?.rotate_about(*axis, *angle))
+            }
+
+            O::RotateEuler {
+                order,
+                angles,
+                object,
+            } => Box::new(self.object(object)?.rotate_euler(*order, *angles)),
+
+            O::RotateQuaternion { rotation, object } => {
+                if rotation.length_squared() == 0.0 {
+                    return Err(SceneError::DegenerateRotation {
+                        path: self.path.to_path_buf(),
+                    });
+                }
+                Box::new(self.object(object)?.rotate_quaternion(*rotation))
+            }
+
+            O::Scale { factor, object } => {
+                if factor.0 == 0.0 || factor.1 == 0.0 || factor.2 == 0.0 {
+                    return Err(SceneError::DegenerateScale {
+                        path: self.path.to_path_buf(),
+                        factor: *factor,
+                    });
+                }
+                Box::new(self.object(object)?.scale(*factor))
+            }
+
+            O::Union(left, right) => Box::new(CSG::union(self.object(left)?, self.object(right)?)),
+
+            O::Intersection(left, right) => {
+                Box::new(CSG::intersection(self.object(left)?, self.object(right)?))
+            }
+
+            O::Difference(left, right) => {
+                Box::new(CSG::difference(self.object(left)?, self.object(right)?))
+            }
+
+            O::FlipFace(object) => Box::new(self.object(object)?.flip_face()),
+        })
+    }
+
+    fn material(&self, material: &MaterialDescription) -> Result<Material, SceneError> {
+        self.material_with_depth(material, 0)
+    }
+
+    fn material_with_depth(
+        &self,
+        material: &MaterialDescription,
+        depth: usize,
+    ) -> Result<Material, SceneError> {
+        use MaterialDescription as M;
+
+        Ok(match material {
+            M::Lambertian { albedo } => Material::lambertian(self.texture(albedo)?),
+            M::Metal { albedo, fuzz } => Material::conductor(Ior::Reflectance(*albedo), *fuzz),
+            M::Conductor {
+                ior,
+                roughness,
+                roughness_v,
+            } => Material::anisotropic_conductor(
+                *ior,
+                (*roughness, roughness_v.unwrap_or(*roughness)),
+            ),
+            M::Dielectric {
+                refractive_index,
+                density,
+                albedo,
+                roughness,
+            } => {
+                if *roughness > 0.0 {
+                    Material::rough_dielectric(*albedo, *refractive_index, *density, *roughness)
+                } else {
+                    Material::dielectric_with_albedo(*albedo, *refractive_index, *density)
+                }
+            }
+            M::DiffuseLight { emit } => Material::diffuse_light(self.texture(emit)?),
+            M::Isotropic { albedo } => Material::isotropic(self.texture(albedo)?),
+            M::Hair {
+                albedo,
+                shine,
+                roughness,
+            } => Material::hair(self.texture(albedo)?, *shine, *roughness),
+            M::Named(name) => match self.materials.get(name) {
+                Some(named) if depth < MAX_MATERIAL_ALIAS_DEPTH => {
+                    self.material_with_depth(named, depth + 1)?
+                }
+                _ => {
+                    return Err(SceneError::UnknownMaterial {
+                        path: self.path.to_path_buf(),
+                        name: name.clone(),
+                    })
+                }
+            },
+        })
+    }
+
+    fn texture(&self, texture: &TextureDescription) -> Result<Texture, SceneError> {
+        use TextureDescription as T;
+
+        Ok(match texture {
+            T::Constant(color) => texture::constant(*color),
+            T::Checkerboard { odd, even } => {
+                texture::checkerboard(self.texture(odd)?, self.texture(even)?)
+            }
+            T::PerlinNoise { scale } => texture::perlin_noise(*scale),
+            T::PerlinTurbulence { scale, depth } => texture::perlin_turbulence(*scale, *depth),
+            T::SimpleMarble { scale, axis } => texture::simple_marble(*scale, *axis),
+            T::Image { path } => {
+                let image_path = self.asset(path);
+                texture::try_image(&image_path).map_err(|source| SceneError::Texture {
+                    path: self.path.to_path_buf(),
+                    image_path,
+                    source,
+                })?
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ray::Ray;
+
+    fn parse(source: &str) -> Result<Scene, SceneError> {
+        from_str(source, Path::new("test.ron"), 1.0)
+    }
+
+    #[test]
+    fn minimal_scene() {
+        let scene = parse(
+            r#"Scene(
+                camera: (look_from: (0.0, 0.0, 5.0), look_at: (0.0, 0.0, 0.0)),
+                materials: {
+                    "grey": Lambertian(albedo: Constant((0.5, 0.5, 0.5))),
+                },
+                objects: [
+                    Sphere(center: (0.0, 0.0, 0.0), radius: 1.0, material: Named("grey")),
+                    Translate(
+                        offset: (0.0, 1.0, 0.0),
+                        object: FlipFace(Block(
+                            min: (0.0, 0.0, 0.0),
+                            max: (1.0, 1.0, 1.0),
+                            material: Metal(albedo: (0.8, 0.8, 0.8), fuzz: 0.1),
+                        )),
+                    ),
+                ],
+            )"#,
+        )
+        .unwrap();
+
+        assert_eq!(scene.world.objects.len(), 2);
+        assert!(scene.lights.objects.is_empty());
+        assert_eq!(scene.background, vec3!());
+    }
+
+    #[test]
+    fn conductors() {
+        let scene = parse(
+            r#"Scene(
+                camera: (look_from: (0.0, 0.0, 5.0), look_at: (0.0, 0.0, 0.0)),
+                objects: [
+                    Sphere(center: (-2.0, 0.0, 0.0), radius: 0.5, material: Conductor(ior: Gold, roughness: 0.3)),
+                    Sphere(center: (0.0, 0.0, 0.0), radius: 0.5, material: Conductor(ior: Custom(eta: (0.2, 0.9, 1.1), k: (3.9, 2.5, 2.1)))),
+                    Sphere(center: (2.0, 0.0, 0.0), radius: 0.5, material: Conductor(ior: Reflectance((0.9, 0.9, 0.9)), roughness: 0.1, roughness_v: 0.6)),
+                    Sphere(center: (4.0, 0.0, 0.0), radius: 0.5, material: Metal(albedo: (0.9, 0.9, 0.9), fuzz: 0.5)),
+                ],
+            )"#,
+        )
+        .unwrap();
+
+        let material = |x: f32| {
+            let ray = Ray::new(vec3!(x, 0.0, 5.0), vec3!(0.0, 0.0, -1.0), 0.0);
+            let rec = scene.world.hit(&ray, 0.001, f32::MAX).unwrap();
+            match rec.material.as_ref() {
+                Material::Conductor(c) => *c,
+                m => panic!("expected a conductor, got {:?}", m),
+            }
+        };
+
+        assert_eq!(material(-2.0).eta, Ior::Gold.eta_k().0);
+        assert!((material(-2.0).alpha.0 - 0.09).abs() < 1e-6);
+        assert_eq!(material(0.0).alpha, (0.0, 0.0));
+        assert!((material(2.0).alpha.0 - 0.01).abs() < 1e-6);
+        assert!((material(2.0).alpha.1 - 0.36).abs() < 1e-6);
+        assert_eq!(material(4.0).eta, material(2.0).eta);
+        assert!((material(4.0).alpha.0 - 0.25).abs() < 1e-6);
+    }
+
+    #[test]
+    fn frosted_glass() {
+        let scene = parse(
+            r#"Scene(
+                camera: (look_from: (0.0, 0.0, 5.0), look_at: (0.0, 0.0, 0.0)),
+                objects: [
+                    Sphere(center: (-1.0, 0.0, 0.0), radius: 0.5, material: Dielectric(refractive_index: 1.5)),
+                    Sphere(center: (1.0, 0.0, 0.0), radius: 0.5, material: Dielectric(refractive_index: 1.3, density: 2.0, albedo: (0.8, 0.9, 1.0), roughness: 0.5)),
+                ],
+            )"#,
+        )
+        .unwrap();
+
+        let material = |x: f32| {
+            let ray = Ray::new(vec3!(x, 0.0, 5.0), vec3!(0.0, 0.0, -1.0), 0.0);
+            scene.world.hit(&ray, 0.001, f32::MAX).unwrap().material
+        };
+
+        assert!(matches!(*material(-1.0), Material::Dielectric(_)));
+        match material(1.0).as_ref() {
+            Material::RoughDielectric(d) => {
+                assert_eq!(d.dielectric.refractive_index, 1.3);
+                assert_eq!(d.dielectric.density, 2.0);
+                assert_eq!(d.dielectric.albedo, vec3!(0.8, 0.9, 1.0));
+                assert!((d.alpha - 0.25).abs() < 1e-6);
+            }
+            m => panic!("expected a rough dielectric, got {:?}", m),
+        }
+    }
+
+    #[test]
+    fn shaped_lights() {
+        let scene = parse(
+            r#"Scene(
+                camera: (look_from: (0.0, 0.0, 5.0), look_at: (0.0, 0.0, 0.0)),
+                materials: {
+                    "light": DiffuseLight(emit: Constant((4.0, 4.0, 4.0))),
+                },
+                objects: [
+                    Quad(corner: (0.0, 0.0, 0.0), u: (1.0, 0.0, 0.0), v: (0.0, 1.0, 1.0), material: Named("light")),
+                    Disk(center: (0.0, 3.0, 0.0), normal: (0.0, -1.0, 0.0), radius: 1.0, material: Named("light")),
+                    Cylinder(base: (2.0, 0.0, 0.0), top: (2.0, 1.0, 0.0), radius: 0.1, capped: false, material: Named("light")),
+                    Cone(base: (-2.0, 0.0, 0.0), apex: (-2.0, 1.0, 0.0), radius: 0.5, material: Named("light")),
+                    Torus(center: (0.0, 0.0, -2.0), axis: (0.0, 1.0, 0.0), major_radius: 1.0, minor_radius: 0.2, material: Named("light")),
+                ],
+                lights: [
+                    Disk(center: (0.0, 3.0, 0.0), normal: (0.0, -1.0, 0.0), radius: 1.0, material: Named("light")),
+                    Torus(center: (0.0, 0.0, -2.0), axis: (0.0, 1.0, 0.0), major_radius: 1.0, minor_radius: 0.2, material: Named("light")),
+                ],
+            )"#,
+        )
+        .unwrap();
+
+        assert_eq!(scene.world.objects.len(), 5);
+        assert_eq!(scene.lights.objects.len(), 2);
+
+        // The disk is straight up from the origin.
+        let origin = vec3!(0.0, 0.0, 0.0);
+        assert!(scene.lights.pdf_value(&origin, &vec3!(0.0, 1.0, 0.0)) > 0.0);
+    }
+
+    #[test]
+    fn carved_objects() {
+        let scene = parse(
+            r#"Scene(
+                camera: (look_from: (0.0, 0.0, 5.0), look_at: (0.0, 0.0, 0.0)),
+                materials: {
+                    "glass": Dielectric(refractive_index: 1.5),
+                },
+                objects: [
+                    Difference(
+                        Sphere(center: (0.0, 0.0, 0.0), radius: 1.0, material: Named("glass")),
+                        Cylinder(base: (0.0, -2.0, 0.0), top: (0.0, 2.0, 0.0), radius: 0.5, material: Named("glass")),
+                    ),
+                ],
+            )"#,
+        )
+        .unwrap();
+
+        let down_the_hole = Ray::new(vec3!(0.0, 5.0, 0.0), vec3!(0.0, -1.0, 0.0), 0.0);
+        assert!(scene.world.hit(&down_the_hole, 0.001, f32::MAX).is_none());
+    }
+
+    #[test]
+    fn heightfields_come_from_images() {
+        let dir = std::env::temp_dir().join(format!("heightfield-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        image::GrayImage::from_pixel(4, 4, image::Luma([255]))
+            .save(dir.join("hill.png"))
+            .unwrap();
+        image::GrayImage::from_pixel(1, 4, image::Luma([255]))
+            .save(dir.join("sliver.png"))
+            .unwrap();
+
+        let scene = |image: &str| {
+            parse_with_assets(
+                &format!(
+                    r#"Scene(
+                        camera: (look_from: (0.0, 5.0, 5.0), look_at: (0.0, 0.0, 0.0)),
+                        objects: [
+                            Heightfield(
+                                path: "{}",
+                                corner: (-1.0, 0.0, -1.0),
+                                size: (2.0, 0.5, 2.0),
+                                material: Lambertian(albedo: Constant((0.5, 0.5, 0.5))),
+                            ),
+                        ],
+                    )"#,
+                    image
+                ),
+                &dir.join("test.ron"),
+                1.0,
+            )
+        };
+        let hill = scene("hill.png");
+        let sliver = scene("sliver.png");
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        let (hill, assets) = hill.unwrap();
+        assert_eq!(assets, vec![dir.join("hill.png")]);
+
+        let ray = Ray::new(vec3!(0.2, 5.0, 0.3), vec3!(0.0, -1.0, 0.0), 0.0);
+        let rec = hill.world.hit(&ray, 0.001, f32::MAX).unwrap();
+        assert!((rec.hit_point.1 - 0.5).abs() < 1e-5);
+
+        assert!(matches!(
+            sliver.unwrap_err(),
+            SceneError::Heightfield { .. }
+        ));
+    }
+
+    #[test]
+    fn hair_strands() {
+        let scene = parse(
+            r#"Scene(
+                camera: (look_from: (0.0, 0.0, 5.0), look_at: (0.0, 0.0, 0.0)),
+                materials: {
+                    "hair": Hair(albedo: Constant((0.3, 0.2, 0.1)), shine: 0.3),
+                },
+                objects: [
+                    Curve(
+                        points: ((-1.0, 0.0, 0.0), (-0.3, 1.0, 0.0), (0.3, -1.0, 0.0), (1.0, 0.0, 0.0)),
+                        width: (0.1, 0.01),
+                        segments: 8,
+                        material: Named("hair"),
+                    ),
+                    Curve(
+                        points: ((-1.0, 2.0, 0.0), (-0.3, 2.0, 0.0), (0.3, 2.0, 0.0), (1.0, 2.0, 0.0)),
+                        width: (0.1, 0.1),
+                        material: Named("hair"),
+                    ),
+                ],
+            )"#,
+        )
+        .unwrap();
+
+        // The first strand crosses the Y axis on the way down.
+        let ray = Ray::new(vec3!(0.0, 0.0, 5.0), vec3!(0.0, 0.0, -1.0), 0.0);
+        let rec = scene.world.hit(&ray, 0.001, f32::MAX).unwrap();
+        assert!((rec.uv.0 - 0.5).abs() < 0.01);
+        assert!(rec.tangent.is_some());
+        assert!(matches!(rec.material.as_ref(), Material::Hair(_)));
+
+        let ray = Ray::new(vec3!(0.5, 2.0, 5.0), vec3!(0.0, 0.0, -1.0), 0.0);
+        assert!(scene.world.hit(&ray, 0.001, f32::MAX).is_some());
+    }
+
+    #[test]
+    fn parse_errors_have_a_location() {
+        let err = parse(
+            "Scene(\n    camera: (look_from: (0.0, 0.0, 5.0), look_at: (0.0, 0.0, 0.0)),\n    objects: [Spheer()],\n)",
+        )
+        .unwrap_err();
+
+        match err {
+            SceneError::Parse { line, .. } => assert_eq!(line, 3),
+            e => panic!("expected a parse error, got {:?}", e),
+        }
+    }
+
+    #[test]
+    fn unknown_materials_are_reported() {
+        let err = parse(
+            r#"Scene(
+                camera: (look_from: (0.0, 0.0, 5.0), look_at: (0.0, 0.0, 0.0)),
+                objects: [Sphere(center: (0.0, 0.0, 0.0), radius: 1.0, material: Named("nope"))],
+            )"#,
+        )
+        .unwrap_err();
+
+        assert!(matches!(err, SceneError::UnknownMaterial { ref name, .. } if name == "nope"));
+    }
+
+    #[test]
+    fn empty_scenes_are_reported() {
+        let err = parse(
+            r#"Scene(
+                camera: (look_from: (0.0, 0.0, 5.0), look_at: (0.0, 0.0, 0.0)),
+                objects: [],
+            )"#,
+        )
+        .unwrap_err();
+
+        assert!(matches!(err, SceneError::Empty { .. }));
+
+        let err = parse(
+            r#"Scene(
+                camera: (look_from: (0.0, 0.0, 5.0), look_at: (0.0, 0.0, 0.0)),
+                objects: [Group([]), Group([Group([])])],
+            )"#,
+        )
+        .unwrap_err();
+
+        assert!(matches!(err, SceneError::Empty { .. }));
+    }
+
+    #[test]
+    fn flat_scales_are_reported() {
+        let err = parse(
+            r#"Scene(
+                camera: (look_from: (0.0, 0.0, 5.0), look_at: (0.0, 0.0, 0.0)),
+                objects: [Scale(
+                    factor: (1.0, 0.0, 1.0),
+                    object: Sphere(center: (0.0, 0.0, 0.0), radius: 1.0, material: Metal(albedo: (0.8, 0.8, 0.8), fuzz: 0.0)),
+                )],
+            )"#,
+        )
+        .unwrap_err();
+
+        assert!(matches!(err, SceneError::DegenerateScale { .. }));
+    }
+
+    #[test]
+    fn rotations_about_any_axis() {
+        let scene = parse(
+            r#"Scene(
+                camera: (look_from: (0.0, 0.0, 5.0), look_at: (0.0, 0.0, 0.0)),
+                materials: {
+                    "white": Lambertian(albedo: Constant((0.73, 0.73, 0.73))),
+                },
+                objects: [
+                    RotateAbout(
+                        axis: (0.0, 0.0, 2.0),
+                        angle: 90.0,
+                        object: Sphere(center: (3.0, 0.0, 0.0), radius: 0.5, material: Named("white")),
+                    ),
+                    RotateEuler(
+                        order: ZYX,
+                        angles: (90.0, 0.0, 90.0),
+                        object: Sphere(center: (0.0, 0.0, 3.0), radius: 0.5, material: Named("white")),
+                    ),
+                    RotateQuaternion(
+                        rotation: (w: 0.0, x: 0.0, y: 0.0, z: 2.0),
+                        object: Sphere(center: (3.0, 0.0, 0.0), radius: 0.5, material: Named("white")),
+                    ),
+                ],
+            )"#,
+        )
+        .unwrap();
+
+        // The first sphere ends up on +Y. The second stays put about Z, and
+        // then goes from +Z to -Y about X. The third turns halfway about Z,
+        // from +X to -X.
+        let hits = |origin: Vec3, direction: Vec3| {
+            let mut t_min = 0.001;
+            let mut count = 0;
+            while let Some(rec) =
+                scene
+                    .world
+                    .hit(&Ray::new(origin, direction, 0.0), t_min, f32::MAX)
+            {
+                count += 1;
+                t_min = rec.t + 0.001;
+            }
+            count
+        };
+        let down = vec3!(0.0, 0.0, -1.0);
+        assert_eq!(hits(vec3!(0.0, 3.0, 5.0), down), 2);
+        assert_eq!(hits(vec3!(0.0, -3.0, 5.0), down), 2);
+        assert_eq!(hits(vec3!(-3.0, 0.0, 5.0), down), 2);
+        assert_eq!(hits(vec3!(3.0, 0.0, 5.0), down), 0);
+
+        let err = parse(
+            r#"Scene(
+                camera: (look_from: (0.0, 0.0, 5.0), look_at: (0.0, 0.0, 0.0)),
+                objects: [RotateAbout(
+                    axis: (0.0, 0.0, 0.0),
+                    angle: 45.0,
+                    object: Sphere(center: (0.0, 0.0, 0.0), radius: 1.0, material: Metal(albedo: (0.8, 0.8, 0.8), fuzz: 0.0)),
+                )],
+            )"#,
+        )
+        .unwrap_err();
+        assert!(matches!(err, SceneError::DegenerateRotation { .. }));
+    }
+}
@@ -1,29 +1,160 @@
 //! Some pre-made scenes for your use.
 use crate::{
+    aabb::AABB,
+    bvh::BVH,
     camera::Camera,
     create_world,
     hittable::{
         aa_rect::{XYRect, XZRect, YZRect},
         block::Block,
         constant_medium::ConstantMedium,
+        instance::Instance,
         moving_sphere::MovingSphere,
+        sdf,
+        sdf::SdfObject,
         sphere::Sphere,
         world::World,
         Hittable,
     },
-    material::Material,
+    material::{conductor::Ior, Material},
+    matrix::Matrix4,
     texture, vec3,
     vec3::{Axis::*, Vec3},
 };
 use rand::prelude::*;
+use std::sync::Arc;
+
+/// A light blue "sky" background, for scenes that are lit by their background.
+pub const SKY_BACKGROUND: Vec3 = Vec3(0.7, 0.8, 1.0);
+
+/// A complete scene, ready to be rendered.
+#[derive(Debug, Clone)]
+pub struct Scene {
+    /// Everything that can be hit by a ray.
+    pub world: World,
+    /// The objects that rays should be biased towards. Usually the lights in
+    /// the scene, but can also be things like glass spheres. May be empty.
+    pub lights: World,
+    /// The camera to view the world through.
+    pub camera: Camera,
+    /// The colour that rays which don't hit anything will return.
+    pub background: Vec3,
+}
+
+/// A pre-made scene that can be looked up by name.
+pub struct BuiltinScene {
+    /// The name used to select the scene (e.g. on the command line).
+    pub name: &'static str,
+    /// A short, human-readable description of the scene.
+    pub description: &'static str,
+    /// Builds the scene for a render with some aspect ratio (width:height).
+    /// Scenes with randomly-placed objects will draw from `rng`.
+    pub build: fn(aspect: f32, rng: &mut dyn RngCore) -> Scene,
+}
+
+impl std::fmt::Debug for BuiltinScene {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("BuiltinScene")
+            .field("name", &self.name)
+            .field("description", &self.description)
+            .finish()
+    }
+}
+
+/// Every pre-made scene, in the order they should be listed.
+pub const BUILTIN_SCENES: &[BuiltinScene] = &[
+    BuiltinScene {
+        name: "cornell_box",
+        description: "A Cornell box with a rotated block and a glass sphere.",
+        build: |aspect, _rng| cornell_box(aspect),
+    },
+    BuiltinScene {
+        name: "random_scene",
+        description: "Lots of random little spheres, from the first book.",
+        build: |aspect, rng| random_scene(aspect, rng),
+    },
+    BuiltinScene {
+        name: "next_week_final",
+        description: "The final scene from Ray Tracing: The Next Week.",
+        build: |aspect, rng| tracer_the_next_week_final_scene(aspect, rng),
+    },
+    BuiltinScene {
+        name: "simple_lit_two_perlin_spheres",
+        description: "Two marble-ish spheres, lit by a glowing sphere and a rectangle.",
+        build: |aspect, _rng| simple_lit_two_perlin_spheres(aspect),
+    },
+    BuiltinScene {
+        name: "two_checkerboard_spheres",
+        description: "Two checkerboarded spheres.",
+        build: |aspect, _rng| two_checkerboard_spheres(aspect),
+    },
+    BuiltinScene {
+        name: "two_perlin_spheres",
+        description: "Two spheres with perlin turbulence textures.",
+        build: |aspect, _rng| two_perlin_spheres(aspect),
+    },
+    BuiltinScene {
+        name: "two_marble_ish_spheres",
+        description: "Two spheres that kind-of look like marble.",
+        build: |aspect, _rng| two_marble_ish_spheres(aspect),
+    },
+    BuiltinScene {
+        name: "distance_fields",
+        description: "A Mandelbulb and a blob, sphere traced from distance functions.",
+        build: |aspect, _rng| distance_fields(aspect),
+    },
+    BuiltinScene {
+        name: "metals",
+        description: "Gold, copper, aluminium and silver spheres, from smooth to rough.",
+        build: |aspect, _rng| metals(aspect),
+    },
+    BuiltinScene {
+        name: "frosted_glass",
+        description: "Glass spheres, from smooth to frosted, the last one tinted blue.",
+        build: |aspect, _rng| frosted_glass(aspect),
+    },
+    BuiltinScene {
+        name: "earth_on_checkerboard",
+        description: "The Earth sitting on top of a checkerboard floor.",
+        build: |aspect, _rng| earth_on_checkerboard(aspect),
+    },
+];
+
+/// Look up a pre-made scene by name.
+///
+/// ```
+/// use weekend_tracer_rs::scenes::builtin_scene;
+///
+/// assert!(builtin_scene("cornell_box").is_some());
+/// assert!(builtin_scene("not_a_scene").is_none());
+/// ```
+pub fn builtin_scene(name: &str) -> Option<&'static BuiltinScene> {
+    BUILTIN_SCENES.iter().find(|scene| scene.name == name)
+}
+
+/// A camera with no defocus blur, open from time 0 to time 1.
+fn simple_camera(lookfrom: Vec3, lookat: Vec3, vfov: f32, aspect: f32) -> Camera {
+    Camera::new(
+        lookfrom,
+        lookat,
+        vec3!(0.0, 1.0, 0.0),
+        vfov,
+        aspect,
+        0.0,
+        10.0,
+        0.0,
+        1.0,
+    )
+}
 
 /// The final scene from the book *Ray Tracing: The Next Week*.
-pub fn tracer_the_next_week_final_scene() -> World {
-    let mut rng = thread_rng();
+pub fn tracer_the_next_week_final_scene<R: Rng + ?Sized>(aspect: f32, rng: &mut R) -> Scene {
     let mut world: Vec<Box<dyn Hittable>> = vec![];
 
-    // The ground is made up of boxes of randomly varying height:
+    // The ground is made up of boxes of randomly varying height. They're all
+    // instances of one unit cube, stretched into place:
     let ground_mat = Material::lambertian(vec3!(0.48, 0.83, 0.53).into());
+    let unit_box: Arc<dyn Hittable> = Arc::new(Block::new(vec3!(), Vec3::from(1.0), ground_mat));
     let ground_boxes_per_side = 20;
     let ground_box_width = 100.0;
     let mut ground: Vec<Box<dyn Hittable>> =
@@ -34,25 +165,22 @@ pub fn tracer_the_next_week_final_scene() -> World {
             let x0 = -1000.0 + (i as f32 * ground_box_width);
             let y0 = 0.0;
             let z0 = -1000.0 + (j as f32 * ground_box_width);
-            let x1 = x0 + ground_box_width;
             let y1: f32 = rng.gen_range(1.0, 101.0);
-            let z1 = z0 + ground_box_width;
 
-            ground.push(Box::new(Block::new(
-                vec3!(x0, y0, z0),
-                vec3!(x1, y1, z1),
-                ground_mat.clone(),
+            ground.push(Box::new(Instance::new(
+                unit_box.clone(),
+                Matrix4::translation(vec3!(x0, y0, z0))
+                    * Matrix4::scaling(vec3!(ground_box_width, y1 - y0, ground_box_width)),
             )));
         }
     }
 
     world.append(&mut ground);
 
-    // Add a large light:
+    // Add a large light, facing down:
     let light = Material::diffuse_light(Vec3::from(7.0).into());
-    world.push(Box::new(XZRect::new(
-        123.0, 423.0, 147.0, 412.0, 553.9, light,
-    )));
+    let light_rect = XZRect::new(123.0, 423.0, 147.0, 412.0, 554.0, light);
+    world.push(Box::new(light_rect.flip_face()));
 
     // Add a large, thin area fog:
     world.push(Box::new(ConstantMedium::new(
@@ -85,7 +213,7 @@ pub fn tracer_the_next_week_final_scene() -> World {
     world.push(Box::new(Sphere::new(
         vec3!(0.0, 150.0, 145.0),
         50.0,
-        Material::metal(vec3!(0.8, 0.8, 0.9), 10.0),
+        Material::conductor(Ior::Reflectance(vec3!(0.8, 0.8, 0.9)), 1.0),
     )));
 
     // Add a blue subsurface reflection sphere:
@@ -124,25 +252,44 @@ pub fn tracer_the_next_week_final_scene() -> World {
 
     for _ in 0..ns {
         small_spheres.push(Box::new(Sphere::new(
-            Vec3::random_range(&mut rng, 0.0, 165.0),
+            Vec3::random_range(rng, 0.0, 165.0),
             10.0,
             white_mat.clone(),
         )));
     }
 
     world.push(
-        World::new(small_spheres)
+        BVH::new(small_spheres, 0.0, 1.0)
             .rotate(Y, 15.0)
             .translate(vec3!(-100.0, 270.0, 395.0))
             .box_clone(),
     );
 
-    World::new(world)
+    let lookfrom = vec3!(478.0, 278.0, -600.0);
+    let camera = Camera::new(
+        lookfrom,
+        vec3!(278.0, 278.0, 0.0),
+        vec3!(0.0, 1.0, 0.0),
+        40.0,
+        aspect,
+        0.5,
+        // Focus on the glass sphere:
+        (vec3!(260.0, 150.0, 45.0) - lookfrom).length(),
+        0.0,
+        1.0,
+    );
+
+    Scene {
+        world: World::new(world),
+        lights: create_world!(light_rect),
+        camera,
+        background: vec3!(),
+    }
 }
 
 /// A "Cornell Box" scene. Introduced in 1984, and is used to model the
 /// interaction of light between diffuse surfaces.
-pub fn cornell_box(aspect: f32) -> (World, World, Camera) {
+pub fn cornell_box(aspect: f32) -> Scene {
     let red = Material::lambertian(vec3!(0.65, 0.05, 0.05).into());
     let white = Material::lambertian(vec3!(0.73, 0.73, 0.73).into());
     let green = Material::lambertian(vec3!(0.12, 0.45, 0.15).into());
@@ -181,7 +328,7 @@ pub fn cornell_box(aspect: f32) -> (World, World, Camera) {
     let t0 = 0.0;
     let t1 = 1.0;
 
-    let cam = Camera::new(
+    let camera = Camera::new(
         lookfrom,
         lookat,
         vup,
@@ -193,17 +340,27 @@ pub fn cornell_box(aspect: f32) -> (World, World, Camera) {
         t1,
     );
 
-    (world, lights, cam)
+    Scene {
+        world,
+        lights,
+        camera,
+        background: vec3!(),
+    }
 }
 
 /// A scene with a perlin turbulence sphere on a perlin turbulence ground, with
 /// a white diffuse light formed by a axis-aligned rectangle (`XYRect`). Oh: and
 /// a floating, glowing sphere.
-pub fn simple_lit_two_perlin_spheres() -> World {
+pub fn simple_lit_two_perlin_spheres(aspect: f32) -> Scene {
     let pertext = texture::simple_marble(4.0, Z);
     let difflight = Material::diffuse_light(vec3!(4.0, 4.0, 4.0).into());
 
-    create_world!(
+    // Floating glowing sphere:
+    let light_sphere = Sphere::new(vec3!(0.0, 7.0), 2.0, difflight.clone());
+    // Glowing rectangle:
+    let light_rect = XYRect::new(3.0, 5.0, 1.0, 3.0, -2.0, difflight);
+
+    let world = create_world!(
         // Ground:
         Sphere::new(
             vec3!(0.0, -1000.0),
@@ -212,74 +369,238 @@ pub fn simple_lit_two_perlin_spheres() -> World {
         ),
         // Sphere:
         Sphere::new(vec3!(0.0, 2.0), 2.0, Material::lambertian(pertext)),
-        // Floating glowing sphere:
-        Sphere::new(vec3!(0.0, 7.0), 2.0, difflight.clone()),
-        // Glowing rectangle:
-        XYRect::new(3.0, 5.0, 1.0, 3.0, -2.0, difflight),
-    )
+        light_sphere.clone(),
+        light_rect.clone(),
+    );
+
+    Scene {
+        world,
+        lights: create_world!(light_sphere, light_rect),
+        camera: simple_camera(vec3!(26.0, 3.0, 6.0), vec3!(0.0, 2.0, 0.0), 20.0, aspect),
+        background: vec3!(),
+    }
 }
 
 /// A scene with two checkerboarded spheres.
-pub fn two_checkerboard_spheres() -> World {
+pub fn two_checkerboard_spheres(aspect: f32) -> Scene {
     let texture = texture::checkerboard(vec3!(0.2, 0.3, 0.1).into(), vec3!(0.9, 0.9, 0.9).into());
 
-    create_world!(
+    let world = create_world!(
         Sphere::new(
             vec3!(0.0, -10.0),
             10.0,
             Material::lambertian(texture.clone())
         ),
         Sphere::new(vec3!(0.0, 10.0, 0.0), 10.0, Material::lambertian(texture)),
-    )
+    );
+
+    Scene {
+        world,
+        lights: World::default(),
+        camera: simple_camera(vec3!(13.0, 2.0, 3.0), vec3!(), 20.0, aspect),
+        background: SKY_BACKGROUND,
+    }
 }
 
 /// A scene with two spheres with perlin noise textures.
-pub fn two_perlin_spheres() -> World {
+pub fn two_perlin_spheres(aspect: f32) -> Scene {
     let texture = texture::perlin_turbulence(3.0, None);
 
-    create_world!(
+    let world = create_world!(
         Sphere::new(
             vec3!(0.0, -1000.0, 0.0),
             1000.0,
             Material::lambertian(texture.clone())
         ),
         Sphere::new(vec3!(0.0, 2.0, 0.0), 2.0, Material::lambertian(texture)),
-    )
+    );
+
+    Scene {
+        world,
+        lights: World::default(),
+        camera: simple_camera(vec3!(13.0, 2.0, 3.0), vec3!(), 20.0, aspect),
+        background: SKY_BACKGROUND,
+    }
+}
+
+/// A Mandelbulb fractal next to a blob of spheres melted together, both made
+/// from signed distance functions.
+pub fn distance_fields(aspect: f32) -> Scene {
+    let bulb = sdf::scale(sdf::mandelbulb(8.0, 10), 1.5);
+    let blob = sdf::smooth_union(
+        sdf::smooth_union(
+            sdf::sphere(vec3!(-0.5, 0.0, 0.0), 0.6),
+            sdf::sphere(vec3!(0.5, 0.3, 0.0), 0.5),
+            0.4,
+        ),
+        sdf::torus(vec3!(0.0, -0.5, 0.0), 0.8, 0.15),
+        0.3,
+    );
+
+    let world = create_world!(
+        Sphere::new(
+            vec3!(0.0, -1000.0, 0.0),
+            1000.0,
+            Material::lambertian(texture::checkerboard(
+                texture::constant(vec3!(0.2, 0.3, 0.1)),
+                texture::constant(vec3!(0.9, 0.9, 0.9))
+            ))
+        ),
+        SdfObject::new(
+            sdf::translate(bulb, vec3!(-1.5, 1.7, 0.0)),
+            AABB::new(vec3!(-3.3, -0.1, -1.8), vec3!(0.3, 3.5, 1.8)),
+            Material::conductor(Ior::Reflectance(vec3!(0.8, 0.6, 0.3)), 0.2)
+        ),
+        SdfObject::new(
+            sdf::translate(blob, vec3!(2.0, 1.0, 0.0)),
+            AABB::new(vec3!(0.7, 0.1, -1.2), vec3!(3.3, 2.1, 1.2)),
+            Material::dielectric(1.5, 0.0)
+        ),
+    );
+
+    Scene {
+        world,
+        lights: World::default(),
+        camera: simple_camera(vec3!(0.0, 3.0, 12.0), vec3!(0.0, 1.5, 0.0), 30.0, aspect),
+        background: SKY_BACKGROUND,
+    }
+}
+
+/// A row of physically based metal spheres, each one rougher than the last,
+/// under an area light.
+pub fn metals(aspect: f32) -> Scene {
+    let light = XZRect::new(
+        -3.0,
+        3.0,
+        -1.5,
+        1.5,
+        6.0,
+        Material::diffuse_light(texture::constant(vec3!(4.0, 4.0, 4.0))),
+    );
+
+    let mut world = create_world!(
+        Sphere::new(
+            vec3!(0.0, -1000.0, 0.0),
+            1000.0,
+            Material::lambertian(texture::checkerboard(
+                texture::constant(vec3!(0.1, 0.1, 0.1)),
+                texture::constant(vec3!(0.8, 0.8, 0.8))
+            ))
+        ),
+        light.flip_face(),
+    );
+
+    let metals = [Ior::Gold, Ior::Copper, Ior::Aluminium, Ior::Silver];
+    for (i, &ior) in metals.iter().enumerate() {
+        let x = 2.2 * i as f32 - 3.3;
+        world.add(Box::new(Sphere::new(
+            vec3!(x, 1.0, 0.0),
+            1.0,
+            Material::conductor(ior, 0.05 + 0.15 * i as f32),
+        )));
+    }
+
+    Scene {
+        world,
+        lights: create_world!(light),
+        camera: simple_camera(vec3!(0.0, 3.0, 12.0), vec3!(0.0, 1.0, 0.0), 35.0, aspect),
+        background: 0.3 * SKY_BACKGROUND,
+    }
+}
+
+/// A row of glass spheres, each one rougher than the last, in front of a
+/// checkerboard wall and under an area light.
+pub fn frosted_glass(aspect: f32) -> Scene {
+    let light = XZRect::new(
+        -3.0,
+        3.0,
+        -1.5,
+        1.5,
+        6.0,
+        Material::diffuse_light(texture::constant(vec3!(4.0, 4.0, 4.0))),
+    );
+    let checkerboard = || {
+        Material::lambertian(texture::checkerboard(
+            texture::constant(vec3!(0.1, 0.1, 0.1)),
+            texture::constant(vec3!(0.8, 0.8, 0.8)),
+        ))
+    };
+
+    let mut world = create_world!(
+        Sphere::new(vec3!(0.0, -1000.0, 0.0), 1000.0, checkerboard()),
+        XYRect::new(-20.0, 20.0, 0.0, 20.0, -3.0, checkerboard()),
+        light.flip_face(),
+    );
+
+    for (i, &roughness) in [0.0, 0.1, 0.25, 0.5].iter().enumerate() {
+        let x = 2.2 * i as f32 - 3.3;
+        let albedo = if i == 3 {
+            vec3!(0.6, 0.8, 1.0)
+        } else {
+            Vec3::from(1.0)
+        };
+        world.add(Box::new(Sphere::new(
+            vec3!(x, 1.0, 0.0),
+            1.0,
+            Material::rough_dielectric(albedo, 1.5, 1.0, roughness),
+        )));
+    }
+
+    Scene {
+        world,
+        lights: create_world!(light),
+        camera: simple_camera(vec3!(0.0, 3.0, 12.0), vec3!(0.0, 1.0, 0.0), 35.0, aspect),
+        background: 0.3 * SKY_BACKGROUND,
+    }
 }
 
 /// A scene with two spheres that kind-of look like marble if you squint enough.
-pub fn two_marble_ish_spheres() -> World {
+pub fn two_marble_ish_spheres(aspect: f32) -> Scene {
     let texture = texture::simple_marble(3.0, Z);
 
-    create_world!(
+    let world = create_world!(
         Sphere::new(
             vec3!(0.0, -1000.0, 0.0),
             1000.0,
             Material::lambertian(texture.clone())
         ),
         Sphere::new(vec3!(0.0, 2.0, 0.0), 2.0, Material::lambertian(texture)),
-    )
+    );
+
+    Scene {
+        world,
+        lights: World::default(),
+        camera: simple_camera(vec3!(13.0, 2.0, 3.0), vec3!(), 20.0, aspect),
+        background: SKY_BACKGROUND,
+    }
 }
 
 /// A scene with the earth sitting on top of a checkerboard floor.
-pub fn earth_on_checkerboard() -> World {
+pub fn earth_on_checkerboard(aspect: f32) -> Scene {
     // Image taken from wikimedia commons:
     let earth_tex = texture::image("./images/Mercator-projection.jpg");
     let checker_tex =
         texture::checkerboard(vec3!(0.2, 0.3, 0.1).into(), vec3!(0.9, 0.9, 0.9).into());
 
-    create_world!(
+    let world = create_world!(
         Sphere::new(
             vec3!(0.0, -1000.0, 0.0),
             1000.0,
             Material::lambertian(checker_tex)
         ),
         Sphere::new(vec3!(0.0, 2.0, 0.0), 2.0, Material::lambertian(earth_tex)),
-    )
+    );
+
+    Scene {
+        world,
+        lights: World::default(),
+        camera: simple_camera(vec3!(13.0, 2.0, 3.0), vec3!(), 20.0, aspect),
+        background: SKY_BACKGROUND,
+    }
 }
 
 /// Create a random scene for funsies!
-pub fn random_scene<R: Rng + ?Sized>(rng: &mut R) -> World {
+pub fn random_scene<R: Rng + ?Sized>(aspect: f32, rng: &mut R) -> Scene {
     let mut objects: Vec<Box<dyn Hittable>> = Vec::default();
 
     // Ground:
@@ -311,8 +632,8 @@ pub fn random_scene<R: Rng + ?Sized>(rng: &mut R) -> World {
                 } else if choose_mat < 0.95 {
                     // Metal
                     let albedo = Vec3::random_range(rng, 0.5, 1.0);
-                    let fuzz: f32 = rng.gen();
-                    Material::metal(albedo, fuzz)
+                    let roughness: f32 = rng.gen();
+                    Material::conductor(Ior::Reflectance(albedo), roughness)
                 } else {
                     // Glass
                     let albedo = Vec3::random_range(rng, 0.5, 1.0);
@@ -356,8 +677,25 @@ pub fn random_scene<R: Rng + ?Sized>(rng: &mut R) -> World {
     objects.push(Box::new(Sphere::new(
         vec3!(4.0, 1.0),
         1.0,
-        Material::metal(vec3!(0.7, 0.6, 0.5), 0.0),
+        Material::conductor(Ior::Reflectance(vec3!(0.7, 0.6, 0.5)), 0.0),
     )));
 
-    World { objects }
+    let camera = Camera::new(
+        vec3!(13.0, 2.0, 3.0),
+        vec3!(),
+        vec3!(0.0, 1.0, 0.0),
+        20.0,
+        aspect,
+        0.1,
+        10.0,
+        0.0,
+        1.0,
+    );
+
+    Scene {
+        world: World { objects },
+        lights: World::default(),
+        camera,
+        background: SKY_BACKGROUND,
+    }
 }
@@ -5,14 +5,31 @@ use image as i;
 use std::{path::Path, sync::Arc};
 
 /// Renders an image as a texture.
+///
+/// # Panics
+///
+/// Panics if the image could not be opened. Use `try_image` if you want to
+/// handle the error yourself.
 pub fn image<P>(path: P) -> Texture
 where
     P: AsRef<Path>,
 {
-    let img = i::open(path)
-        .unwrap_or_else(|e| panic!("Could not open image for texture!\n{}", e))
-        .into_rgb();
+    try_image(path).unwrap_or_else(|e| panic!("Could not open image for texture!\n{}", e))
+}
+
+/// Renders an image as a texture, returning an error if the image could not be
+/// opened.
+pub fn try_image<P>(path: P) -> i::ImageResult<Texture>
+where
+    P: AsRef<Path>,
+{
+    Ok(from_image(i::open(path)?))
+}
 
+/// Renders an image that's already been loaded (or decoded from memory) as a
+/// texture.
+pub fn from_image(img: i::DynamicImage) -> Texture {
+    let img = img.into_rgb();
     let (width, height) = img.dimensions();
 
     Texture(Arc::new(move |(u, v), _p| {
@@ -17,16 +17,19 @@ pub mod marble;
 pub use marble::simple_marble;
 
 pub mod image;
-pub use crate::texture::image::image;
+pub use crate::texture::image::{from_image, image, try_image};
 
 use crate::hittable::UVCoord;
 use crate::vec3::Vec3;
 use std::sync::Arc;
 
+/// The function behind a `Texture`.
+pub type TextureFn = dyn Fn(UVCoord, &Vec3) -> Vec3 + Send + Sync;
+
 /// A texture function. Takes in (u, v) surface coordinates the a hit point,
 /// and outputs the resulting colour of that point.
 #[derive(Clone)]
-pub struct Texture(pub Arc<dyn Fn(UVCoord, &Vec3) -> Vec3 + Send + Sync>);
+pub struct Texture(pub Arc<TextureFn>);
 
 /// Allows `Texture` to implement `Debug`.
 impl std::fmt::Debug for Texture {
@@ -7,16 +7,18 @@ use crate::{
 };
 use lazy_static::lazy_static;
 use rand::prelude::*;
+use rand_chacha::ChaCha8Rng;
 use std::sync::Arc;
 
 /// The number of points to use for noise generation.
 const POINT_COUNT: usize = 256;
 
+// The noise is part of the scene, so it's the same every time.
 lazy_static! {
-    static ref RANVEC: [Vec3; POINT_COUNT] = generate_vectors(&mut thread_rng());
-    static ref PERM_X: [u8; POINT_COUNT] = perlin_generate_perm(&mut thread_rng());
-    static ref PERM_Y: [u8; POINT_COUNT] = perlin_generate_perm(&mut thread_rng());
-    static ref PERM_Z: [u8; POINT_COUNT] = perlin_generate_perm(&mut thread_rng());
+    static ref RANVEC: [Vec3; POINT_COUNT] = generate_vectors(&mut ChaCha8Rng::seed_from_u64(0));
+    static ref PERM_X: [u8; POINT_COUNT] = perlin_generate_perm(&mut ChaCha8Rng::seed_from_u64(1));
+    static ref PERM_Y: [u8; POINT_COUNT] = perlin_generate_perm(&mut ChaCha8Rng::seed_from_u64(2));
+    static ref PERM_Z: [u8; POINT_COUNT] = perlin_generate_perm(&mut ChaCha8Rng::seed_from_u64(3));
 }
 
 fn permutate<R: Rng + ?Sized>(rng: &mut R, p: &mut [u8; POINT_COUNT], n: usize) {
@@ -0,0 +1,254 @@
+//! Tone mapping: squashing the unbounded, linear radiance that comes out of
+//! the renderer into displayable 8-bit sRGB pixels.
+//!
+//! A `ToneMapper` applies, in order:
+//! 1. An exposure adjustment, measured in stops (each stop doubles or halves
+//!    the incoming light).
+//! 2. A tone mapping `Operator`, which compresses the exposed radiance into
+//!    the range `[0, 1]`.
+//! 3. The sRGB transfer function (the "gamma curve"), followed by
+//!    quantization to 8 bits per channel.
+
+use crate::renderer::Pixel;
+use crate::util::clamp;
+use crate::vec3;
+use crate::vec3::{Channel::*, Vec3};
+use std::{fmt, str::FromStr};
+
+/// The white point used by `Operator::ExtendedReinhard` when none is given.
+pub const DEFAULT_WHITE_POINT: f32 = 4.0;
+
+/// A tone mapping curve.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Operator {
+    /// Clamp each channel to `[0, 1]`. Anything brighter than white gets
+    /// blown out.
+    #[default]
+    Clamp,
+    /// The simple Reinhard operator, `L / (1 + L)`, applied to luminance so
+    /// that hues are preserved. Nothing ever quite reaches white.
+    Reinhard,
+    /// The extended Reinhard operator, which maps a luminance of `white` (and
+    /// anything brighter) to pure white.
+    ExtendedReinhard { white: f32 },
+    /// Krzysztof Narkowicz's curve fit of the ACES filmic tone mapping curve.
+    Aces,
+    /// John Hable's filmic curve from *Uncharted 2*.
+    Hable,
+}
+
+/// The names of all the tone mapping operators, as accepted by
+/// `Operator::from_str`.
+pub const OPERATOR_NAMES: &[&str] = &["clamp", "reinhard", "extended-reinhard", "aces", "hable"];
+
+impl Operator {
+    /// Map a single exposed, linear colour into the range `[0, 1]`.
+    pub fn apply(self, color: Vec3) -> Vec3 {
+        match self {
+            Operator::Clamp => color,
+            Operator::Reinhard => scale_luminance(color, |l| l / (1.0 + l)),
+            Operator::ExtendedReinhard { white } => {
+                scale_luminance(color, |l| l * (1.0 + l / (white * white)) / (1.0 + l))
+            }
+            Operator::Aces => map_channels(color, |x| {
+                (x * (2.51 * x + 0.03)) / (x * (2.43 * x + 0.59) + 0.14)
+            }),
+            Operator::Hable => {
+                const EXPOSURE_BIAS: f32 = 2.0;
+                const WHITE: f32 = 11.2;
+                let white_scale = 1.0 / hable_partial(WHITE);
+                map_channels(color, |x| hable_partial(x * EXPOSURE_BIAS) * white_scale)
+            }
+        }
+    }
+}
+
+impl fmt::Display for Operator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Operator::Clamp => "clamp",
+            Operator::Reinhard => "reinhard",
+            Operator::ExtendedReinhard { .. } => "extended-reinhard",
+            Operator::Aces => "aces",
+            Operator::Hable => "hable",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl FromStr for Operator {
+    type Err = String;
+
+    /// Parse an operator from one of the names in `OPERATOR_NAMES`. The
+    /// extended Reinhard operator gets `DEFAULT_WHITE_POINT` as its white
+    /// point.
+    ///
+    /// # Usage
+    ///
+    /// ```
+    /// # use weekend_tracer_rs::tonemap::Operator;
+    /// assert_eq!("ACES".parse(), Ok(Operator::Aces));
+    /// assert!("sepia".parse::<Operator>().is_err());
+    /// ```
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "clamp" => Ok(Operator::Clamp),
+            "reinhard" => Ok(Operator::Reinhard),
+            "extended-reinhard" | "extended_reinhard" => Ok(Operator::ExtendedReinhard {
+                white: DEFAULT_WHITE_POINT,
+            }),
+            "aces" => Ok(Operator::Aces),
+            "hable" | "uncharted2" => Ok(Operator::Hable),
+            _ => Err(format!(
+                "unknown tone mapping operator {:?} (expected one of: {})",
+                s,
+                OPERATOR_NAMES.join(", ")
+            )),
+        }
+    }
+}
+
+/// Turns linear radiance into 8-bit sRGB pixels.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct ToneMapper {
+    pub operator: Operator,
+    /// Exposure adjustment in stops. `0.0` leaves the image alone, `1.0`
+    /// doubles its brightness, `-1.0` halves it, etc...
+    pub exposure: f32,
+}
+
+impl ToneMapper {
+    pub fn new(operator: Operator, exposure: f32) -> Self {
+        Self { operator, exposure }
+    }
+
+    /// Expose and tone map a linear colour. The result is still linear, but
+    /// lies within `[0, 1]`.
+    pub fn map(&self, color: Vec3) -> Vec3 {
+        let exposed = color * 2f32.powf(self.exposure);
+        let mapped = self.operator.apply(exposed);
+        map_channels(mapped, |x| clamp(x, 0.0, 1.0))
+    }
+
+    /// Expose, tone map, sRGB encode and quantize a linear colour into a
+    /// `Pixel`.
+    ///
+    /// # Usage
+    ///
+    /// ```
+    /// # use weekend_tracer_rs::{tonemap::ToneMapper, vec3, vec3::Vec3};
+    /// let tone_mapper = ToneMapper::default();
+    /// assert_eq!(tone_mapper.to_pixel(vec3!()), (0, 0, 0));
+    /// assert_eq!(tone_mapper.to_pixel(vec3!(7.0, 1.0, 0.5)), (255, 255, 188));
+    /// ```
+    pub fn to_pixel(&self, color: Vec3) -> Pixel {
+        let mapped = self.map(color);
+        let quantize = |x: f32| (256.0 * clamp(linear_to_srgb(x), 0.0, 0.999)) as u32;
+
+        (
+            quantize(mapped[R]),
+            quantize(mapped[G]),
+            quantize(mapped[B]),
+        )
+    }
+
+    /// Convert a whole linear render into `Pixel`s.
+    pub fn to_pixels(&self, rendered: &[Vec3]) -> Vec<Pixel> {
+        rendered.iter().map(|&color| self.to_pixel(color)).collect()
+    }
+}
+
+/// The sRGB transfer function, converting a linear value in `[0, 1]` into an
+/// sRGB-encoded one.
+///
+/// # Usage
+///
+/// ```
+/// # use weekend_tracer_rs::tonemap::linear_to_srgb;
+/// assert_eq!(linear_to_srgb(0.0), 0.0);
+/// assert!((linear_to_srgb(0.001) - 0.01292).abs() < 1e-6);
+/// assert!((linear_to_srgb(1.0) - 1.0).abs() < 1e-6);
+/// ```
+pub fn linear_to_srgb(x: f32) -> f32 {
+    if x <= 0.003_130_8 {
+        12.92 * x
+    } else {
+        1.055 * x.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+/// The relative luminance of a linear sRGB colour.
+pub fn luminance(color: Vec3) -> f32 {
+    0.2126 * color[R] + 0.7152 * color[G] + 0.0722 * color[B]
+}
+
+/// Scale a colour so that its luminance becomes `f(luminance)`.
+fn scale_luminance<F: Fn(f32) -> f32>(color: Vec3, f: F) -> Vec3 {
+    let l = luminance(color);
+    if l <= 0.0 {
+        vec3!()
+    } else {
+        color * (f(l) / l)
+    }
+}
+
+fn map_channels<F: Fn(f32) -> f32>(color: Vec3, f: F) -> Vec3 {
+    vec3!(f(color[R]), f(color[G]), f(color[B]))
+}
+
+fn hable_partial(x: f32) -> f32 {
+    const A: f32 = 0.15;
+    const B: f32 = 0.50;
+    const C: f32 = 0.10;
+    const D: f32 = 0.20;
+    const E: f32 = 0.02;
+    const F: f32 = 0.30;
+
+    ((x * (A * x + C * B) + D * E) / (x * (A * x + B) + D * F)) - E / F
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const OPERATORS: &[Operator] = &[
+        Operator::Clamp,
+        Operator::Reinhard,
+        Operator::ExtendedReinhard { white: 4.0 },
+        Operator::Aces,
+        Operator::Hable,
+    ];
+
+    #[test]
+    fn operators_are_monotonic_and_bounded() {
+        let tone_mapper = |operator| ToneMapper::new(operator, 0.0);
+        for &operator in OPERATORS {
+            let mut previous = 0.0;
+            for i in 0..=200 {
+                let x = i as f32 * 0.1;
+                let mapped = tone_mapper(operator).map(vec3!(x, x, x))[R];
+                assert!(
+                    (0.0..=1.0).contains(&mapped),
+                    "{} mapped {} to {}",
+                    operator,
+                    x,
+                    mapped
+                );
+                assert!(mapped >= previous, "{} is not monotonic at {}", operator, x);
+                previous = mapped;
+            }
+        }
+    }
+
+    #[test]
+    fn extended_reinhard_maps_white_point_to_white() {
+        let mapped = Operator::ExtendedReinhard { white: 4.0 }.apply(vec3!(4.0, 4.0, 4.0));
+        assert!((mapped[R] - 1.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn exposure_is_measured_in_stops() {
+        let tone_mapper = ToneMapper::new(Operator::Clamp, -2.0);
+        assert_eq!(tone_mapper.map(vec3!(0.8, 0.4, 0.0)), vec3!(0.2, 0.1, 0.0));
+    }
+}
@@ -1,6 +1,7 @@
 //! Structs and methods related to operating on 3D vectors.
 
 use rand::prelude::*;
+use serde::Deserialize;
 use std::fmt;
 use std::ops::{Add, AddAssign, Div, DivAssign, Mul, MulAssign, Neg, Sub, SubAssign};
 
@@ -13,7 +14,7 @@ use std::ops::{Add, AddAssign, Div, DivAssign, Mul, MulAssign, Neg, Sub, SubAssi
 ///    weekend_tracer_rs::vec3::Axis::*;` statement.
 /// 3. Using the `Channel` enum: `v[R]`, `v[G]`, `v[B]`. This requires a `use
 ///    weekend_tracer_rs::vec3::Channel::*;` statement.
-#[derive(Clone, Copy, Debug, PartialEq, Default)]
+#[derive(Clone, Copy, Debug, PartialEq, Default, Deserialize)]
 pub struct Vec3(pub f32, pub f32, pub f32);
 
 impl Vec3 {
@@ -273,8 +274,22 @@ impl Vec3 {
     /// assert_eq!(a, Vec3::new(-0.39450094, 0.6357983, 0.6634226));
     /// ```
     pub fn random_cosine_direction<R: Rng + ?Sized>(rng: &mut R) -> Self {
-        let r1: f32 = rng.gen();
-        let r2: f32 = rng.gen();
+        Self::cosine_direction((rng.gen(), rng.gen()))
+    }
+
+    /// Turn a point `(r1, r2)` in the unit square into a direction in the
+    /// hemisphere around +Z, with a cosine distribution. Evenly spread out
+    /// points give evenly spread out directions.
+    ///
+    /// # Usage
+    ///
+    /// ```
+    /// use weekend_tracer_rs::vec3::Vec3;
+    ///
+    /// assert_eq!(Vec3::cosine_direction((0.0, 0.0)), Vec3::new(0.0, 0.0, 1.0));
+    /// assert!((Vec3::cosine_direction((0.3, 0.7)).length() - 1.0).abs() < 1e-6);
+    /// ```
+    pub fn cosine_direction((r1, r2): (f32, f32)) -> Self {
         let z = (1.0 - r2).sqrt();
 
         let phi = 2.0 * std::f32::consts::PI * r1;
@@ -284,6 +299,56 @@ impl Vec3 {
         Self(x, y, z)
     }
 
+    /// Turn a point `(u, v)` in the unit square into a point in the unit disk,
+    /// using Shirley and Chiu's concentric mapping (which keeps nearby points
+    /// nearby).
+    ///
+    /// # Usage
+    ///
+    /// ```
+    /// use weekend_tracer_rs::vec3::{Vec3, Axis::*};
+    ///
+    /// assert_eq!(Vec3::in_unit_disk((0.5, 0.5)), Vec3::new(0.0, 0.0, 0.0));
+    ///
+    /// let a = Vec3::in_unit_disk((0.99, 0.2));
+    /// assert!(a.length_squared() <= 1.0);
+    /// assert_eq!(a[Z], 0.0);
+    /// ```
+    pub fn in_unit_disk((u, v): (f32, f32)) -> Self {
+        let (x, y) = (2.0 * u - 1.0, 2.0 * v - 1.0);
+        if x == 0.0 && y == 0.0 {
+            return Self(0.0, 0.0, 0.0);
+        }
+
+        let quarter_pi = std::f32::consts::FRAC_PI_4;
+        let (radius, theta) = if x.abs() > y.abs() {
+            (x, quarter_pi * (y / x))
+        } else {
+            (y, 2.0 * quarter_pi - quarter_pi * (x / y))
+        };
+
+        Self(radius * theta.cos(), radius * theta.sin(), 0.0)
+    }
+
+    /// Turn a point `(u, v)` in the unit square into a point on the unit
+    /// sphere, with an even distribution.
+    ///
+    /// # Usage
+    ///
+    /// ```
+    /// use weekend_tracer_rs::vec3::Vec3;
+    ///
+    /// assert_eq!(Vec3::on_unit_sphere((0.0, 0.0)), Vec3::new(0.0, 0.0, 1.0));
+    /// assert!((Vec3::on_unit_sphere((0.8, 0.35)).length() - 1.0).abs() < 1e-6);
+    /// ```
+    pub fn on_unit_sphere((u, v): (f32, f32)) -> Self {
+        let z = 1.0 - 2.0 * u;
+        let radius = (1.0 - z * z).max(0.0).sqrt();
+        let phi = 2.0 * std::f32::consts::PI * v;
+
+        Self(radius * phi.cos(), radius * phi.sin(), z)
+    }
+
     /// Returns the length of the vector, squared.
     ///
     /// ```
@@ -648,7 +713,7 @@ impl std::ops::IndexMut<Channel> for Vec3 {
 /// assert_eq!(v[Y], 2.0);
 /// assert_eq!(v[Z], 3.0);
 /// ```
-#[derive(Copy, Clone, Debug)]
+#[derive(Copy, Clone, Debug, Deserialize)]
 pub enum Axis {
     X,
     Y,